@@ -0,0 +1,266 @@
+//! Styled document model produced by the parser
+//!
+//! A [`Document`] is a list of lines, each made of [`Span`]s of text sharing one
+//! [`Style`]. It is an output-independent view of the converted input: the HTML
+//! writer renders from the same spans, and other renderers (terminal, image,
+//! search index) can consume the document directly.
+
+use crate::html::HtmlWriter;
+use crate::{Bitmap, ExtendedColor, MusicNote, Palette, Sink};
+
+/// Colors and attributes applied to a run of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Style {
+    /// Foreground (text) color
+    pub foreground: ExtendedColor,
+    /// Background color
+    pub background: ExtendedColor,
+    /// Text attributes such as blink, underline and italic
+    pub attributes: Attributes,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            foreground: ExtendedColor::Cga(7),
+            background: ExtendedColor::Cga(0),
+            attributes: Attributes::default(),
+        }
+    }
+}
+
+/// Text attributes of a [`Style`] besides its colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Attributes {
+    /// Blinking text (only set when the blink mode is [`BlinkMode::Blink`](crate::BlinkMode::Blink))
+    pub blink: bool,
+    /// Faint / decreased intensity (SGR 2)
+    pub faint: bool,
+    /// Italic (SGR 3)
+    pub italic: bool,
+    /// Single underline (SGR 4)
+    pub underline: bool,
+    /// Double underline (SGR 21)
+    pub double_underline: bool,
+    /// Crossed-out text (SGR 9)
+    pub strikethrough: bool,
+    /// Concealed text (SGR 8)
+    pub conceal: bool,
+    /// Overline (SGR 53)
+    pub overline: bool,
+}
+
+impl Attributes {
+    /// Names of the attributes that are set, as written on the HTML elements
+    pub(crate) fn html_names(&self) -> impl Iterator<Item = &'static str> {
+        [
+            (self.blink, "blink"),
+            (self.faint, "faint"),
+            (self.italic, "italic"),
+            (self.underline, "underline"),
+            (self.double_underline, "double-underline"),
+            (self.strikethrough, "strike"),
+            (self.conceal, "conceal"),
+            (self.overline, "overline"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+    }
+}
+
+/// A run of text rendered with a single style
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    /// Unescaped text of the span
+    pub text: String,
+    /// Style applied to every character in the span
+    pub style: Style,
+}
+
+/// One line of output, without its line terminator
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Line {
+    /// Styled runs in left-to-right order (adjacent spans have different styles)
+    pub spans: Vec<Span>,
+}
+
+impl Line {
+    /// Plain text of the line with styling removed
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+/// Where a music sequence, RIPscrip screen or Sixel image sits in the text of a [`Document`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Anchor {
+    /// Index of the line in [`Document::lines`]
+    pub line: usize,
+    /// Number of characters of the line that come before the element
+    pub column: usize,
+    /// The element placed here
+    pub kind: AnchorKind,
+}
+
+/// Element placed by an [`Anchor`], as an index into its list in the [`Document`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnchorKind {
+    /// Index into [`Document::music`]
+    Music(usize),
+    /// Index into [`Document::graphics`]
+    Graphics(usize),
+    /// Index into [`Document::images`]
+    Image(usize),
+}
+
+/// A converted document: lines of styled spans
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Document {
+    /// Lines in top-to-bottom order
+    pub lines: Vec<Line>,
+    /// Palette the document should be rendered with, when not the default
+    pub palette: Option<Palette>,
+    /// Notes of each ANSI music sequence, in input order (read with
+    /// [`ConvertOptions::ansi_music`](crate::ConvertOptions::ansi_music))
+    pub music: Vec<Vec<MusicNote>>,
+    /// RIPscrip screens rendered as SVG elements, in input order
+    pub graphics: Vec<String>,
+    /// Sixel images, in input order
+    pub images: Vec<Bitmap>,
+    /// Positions of the music, RIPscrip screens and Sixel images in the lines, in output
+    /// order
+    pub anchors: Vec<Anchor>,
+}
+
+impl Document {
+    /// Render the document as a `<pre class="ansi">` HTML fragment.
+    ///
+    /// This produces the same markup as [`convert_with_options`](crate::convert_with_options)
+    /// for the input the document was parsed from, including RIPscrip graphics and Sixel
+    /// images. `<ans-music>` elements are written for all music in the document, as with
+    /// [`ConvertOptions::music_elements`](crate::ConvertOptions::music_elements).
+    pub fn to_html(&self) -> String {
+        let mut writer = HtmlWriter::new(String::new(), true);
+        let mut anchors = self.anchors.iter().peekable();
+        writer.begin(self.palette.as_ref());
+        for (index, line) in self.lines.iter().enumerate() {
+            if index > 0 {
+                writer.newline();
+            }
+            let mut column = 0;
+            for span in &line.spans {
+                let mut text = span.text.as_str();
+                // Split the span around the elements placed inside it
+                while let Some(anchor) = anchors.next_if(|anchor| {
+                    anchor.line == index && anchor.column < column + text.chars().count()
+                }) {
+                    let split = text
+                        .char_indices()
+                        .nth(anchor.column - column)
+                        .map_or(text.len(), |(at, _)| at);
+                    if split > 0 {
+                        writer.text(&text[..split], span.style);
+                        column += text[..split].chars().count();
+                        text = &text[split..];
+                    }
+                    self.write_anchor(&mut writer, anchor);
+                }
+                if !text.is_empty() {
+                    writer.text(text, span.style);
+                    column += text.chars().count();
+                }
+            }
+            while let Some(anchor) = anchors.next_if(|anchor| anchor.line == index) {
+                self.write_anchor(&mut writer, anchor);
+            }
+        }
+        for anchor in anchors {
+            self.write_anchor(&mut writer, anchor);
+        }
+        writer.end();
+        writer.output
+    }
+
+    /// Write the element placed by `anchor`
+    fn write_anchor(&self, writer: &mut HtmlWriter, anchor: &Anchor) {
+        match anchor.kind {
+            AnchorKind::Music(index) => writer.music(&self.music[index]),
+            AnchorKind::Graphics(index) => writer.graphics(&self.graphics[index]),
+            AnchorKind::Image(index) => writer.image(&self.images[index]),
+        }
+    }
+}
+
+/// Sink that collects parser output into a [`Document`]
+#[derive(Default)]
+pub(crate) struct DocumentBuilder {
+    lines: Vec<Line>,
+    line: Line,
+    palette: Option<Palette>,
+    music: Vec<Vec<MusicNote>>,
+    graphics: Vec<String>,
+    images: Vec<Bitmap>,
+    anchors: Vec<Anchor>,
+    /// Number of characters in the current line
+    column: usize,
+}
+
+impl DocumentBuilder {
+    pub fn finish(mut self) -> Document {
+        self.lines.push(self.line);
+        Document {
+            lines: self.lines,
+            palette: self.palette,
+            music: self.music,
+            graphics: self.graphics,
+            images: self.images,
+            anchors: self.anchors,
+        }
+    }
+
+    /// Place an element at the current position
+    fn anchor(&mut self, kind: AnchorKind) {
+        self.anchors.push(Anchor {
+            line: self.lines.len(),
+            column: self.column,
+            kind,
+        });
+    }
+}
+
+impl Sink for DocumentBuilder {
+    fn begin(&mut self, palette: Option<&Palette>) {
+        self.palette = palette.copied();
+    }
+
+    fn text(&mut self, text: &str, style: Style) {
+        self.column += text.chars().count();
+        match self.line.spans.last_mut() {
+            Some(span) if span.style == style => span.text.push_str(text),
+            _ => self.line.spans.push(Span {
+                text: text.to_string(),
+                style,
+            }),
+        }
+    }
+
+    fn newline(&mut self) {
+        self.lines.push(std::mem::take(&mut self.line));
+        self.column = 0;
+    }
+
+    fn music(&mut self, notes: &[MusicNote]) {
+        self.anchor(AnchorKind::Music(self.music.len()));
+        self.music.push(notes.to_vec());
+    }
+
+    fn graphics(&mut self, svg: &str) {
+        self.anchor(AnchorKind::Graphics(self.graphics.len()));
+        self.graphics.push(svg.to_string());
+    }
+
+    fn image(&mut self, image: &Bitmap) {
+        self.anchor(AnchorKind::Image(self.images.len()));
+        self.images.push(image.clone());
+    }
+}
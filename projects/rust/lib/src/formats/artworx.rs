@@ -0,0 +1,69 @@
+//! Artworx (`.adf`) decoder
+//!
+//! An ADF file is a version byte, the 64 VGA palette registers, an 8x16 font of 256
+//! glyphs and then character/attribute pairs for an 80-column image.
+
+use super::{
+    DecodeError, Font, ImageCell, TextImage, dac_palette, pad_rows, parse_sauce, strip_sauce, take,
+};
+
+/// Size of the version byte, palette registers and font before the image data
+const HEADER_LEN: usize = 1 + 192 + 4096;
+
+/// Palette registers holding the sixteen text-mode colors (brown is register 20 and the
+/// bright colors start at register 56)
+const COLOR_REGISTERS: [usize; 16] = [0, 1, 2, 3, 4, 5, 20, 7, 56, 57, 58, 59, 60, 61, 62, 63];
+
+/// Decode an Artworx file.
+///
+/// The embedded palette and font are returned with the image. Images are 80 columns
+/// wide and always use iCE colors.
+///
+/// # Errors
+/// Returns [`DecodeError`] if the version byte is not 1, the file ends inside the
+/// palette or font, or it has no image data.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::decode_artworx;
+///
+/// let mut data = vec![1];
+/// data.extend([0u8; 192 + 4096]);
+/// data.extend_from_slice(b"A\x1f");
+/// let image = decode_artworx(&data).unwrap();
+/// assert_eq!((image.width, image.height), (80, 1));
+/// assert_eq!(image.font.unwrap().data.len(), 4096);
+/// ```
+pub fn decode_artworx(data: &[u8]) -> Result<TextImage, DecodeError> {
+    if data.first() != Some(&1) {
+        return Err(DecodeError::InvalidSignature);
+    }
+    let mut reader = &strip_sauce(data, HEADER_LEN)[1..];
+
+    let registers = take(&mut reader, 192)?;
+    let colors: Vec<u8> = COLOR_REGISTERS
+        .iter()
+        .flat_map(|&register| registers[register * 3..register * 3 + 3].iter().copied())
+        .collect();
+    let font = Font {
+        height: 16,
+        glyphs: 256,
+        data: take(&mut reader, 4096)?.to_vec(),
+    };
+
+    let mut cells: Vec<ImageCell> = reader
+        .chunks_exact(2)
+        .map(|pair| ImageCell::from_attribute(pair[0], pair[1]))
+        .collect();
+    let height = pad_rows(&mut cells, 80)?;
+
+    Ok(TextImage {
+        width: 80,
+        height,
+        cells,
+        palette: Some(dac_palette(&colors)),
+        font: Some(font),
+        ice_colors: true,
+        sauce: parse_sauce(data),
+    })
+}
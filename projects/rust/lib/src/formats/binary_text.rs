@@ -0,0 +1,62 @@
+//! BinaryText (`.bin`) decoder
+//!
+//! BinaryText files are raw video memory: CP437 character and attribute byte pairs with
+//! no header. The width is not stored in the data itself; SAUCE records it in the
+//! FileType field as half the number of columns.
+
+use super::{DecodeError, ImageCell, MAX_CELLS, TextImage, pad_rows, parse_sauce, strip_sauce};
+
+/// Width of BinaryText files without a SAUCE record
+const DEFAULT_WIDTH: u16 = 160;
+
+/// Decode a BinaryText file.
+///
+/// `width` overrides the number of columns; `None` uses the SAUCE width, or 160 columns
+/// when the file has no SAUCE record. Backgrounds 8-15 are iCE colors unless a SAUCE
+/// record clears its iCE colors flag. A partial last row is padded with blank cells.
+///
+/// # Errors
+/// Returns [`DecodeError::InvalidDimensions`] if the file has no cells, the width is zero,
+/// or the cells fill more than 65535 rows or 1048576 cells.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::{decode_binary_text, ConvertOptions};
+///
+/// let image = decode_binary_text(b"H\x1fi\x1f!\x4eX\x07", Some(2)).unwrap();
+/// assert_eq!((image.width, image.height), (2, 2));
+/// let html = image.to_html(&ConvertOptions::default());
+/// assert!(html.contains("<ans-1f>Hi\n</ans-1f><ans-4e>!</ans-4e><ans-07>X</ans-07>"));
+/// ```
+pub fn decode_binary_text(data: &[u8], width: Option<u16>) -> Result<TextImage, DecodeError> {
+    let sauce = parse_sauce(data);
+    let width = width
+        .or_else(|| sauce.as_ref().and_then(|sauce| sauce.binary_text_width()))
+        .unwrap_or(DEFAULT_WIDTH);
+
+    let content = strip_sauce(data, 0);
+    if width > 0
+        && (content.len() / 2).div_ceil(usize::from(width)) * usize::from(width) > MAX_CELLS
+    {
+        return Err(DecodeError::InvalidDimensions);
+    }
+
+    let mut cells: Vec<ImageCell> = content
+        .chunks_exact(2)
+        .map(|pair| ImageCell::from_attribute(pair[0], pair[1]))
+        .collect();
+    let height = pad_rows(&mut cells, width)?;
+
+    Ok(TextImage {
+        width,
+        height,
+        cells,
+        palette: None,
+        font: None,
+        ice_colors: sauce
+            .as_ref()
+            .and_then(|sauce| sauce.ice_colors())
+            .unwrap_or(true),
+        sauce,
+    })
+}
@@ -0,0 +1,101 @@
+//! iCE Draw (`.idf`) decoder
+//!
+//! An IDF file is a 12-byte header (`0x04` + version, then the x1, y1, x2 and y2 corners
+//! of the image), run-length compressed character/attribute pairs, an 8x16 font of 256
+//! glyphs and finally a 16-color palette.
+
+use super::{
+    DecodeError, Font, ImageCell, MAX_CELLS, TextImage, dac_palette, pad_rows, parse_sauce,
+    strip_sauce, take,
+};
+
+/// Size of the font and palette that follow the image data
+const FOOTER_LEN: usize = 4096 + 48;
+
+/// Decode an iCE Draw file.
+///
+/// The embedded palette and font are returned with the image. The width comes from the
+/// header's x coordinates, and images always use iCE colors.
+///
+/// # Errors
+/// Returns [`DecodeError`] if the signature is missing, the coordinates describe an
+/// empty image or one larger than 1048576 cells, the file is too short for the font and
+/// palette, or the image data holds more cells than the coordinates allow.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::decode_ice_draw;
+///
+/// // 4 columns, one run repeating "X" on blue four times
+/// let mut data = b"\x041.4\x00\x00\x00\x00\x03\x00\x00\x00".to_vec();
+/// data.extend_from_slice(b"\x01\x00\x04\x00X\x1f");
+/// data.extend([0u8; 4096 + 48]);
+/// let image = decode_ice_draw(&data).unwrap();
+/// assert_eq!((image.width, image.height), (4, 1));
+/// assert!(image.cells.iter().all(|cell| cell.ch == u16::from(b'X')));
+/// ```
+pub fn decode_ice_draw(data: &[u8]) -> Result<TextImage, DecodeError> {
+    if !data.starts_with(b"\x041.") {
+        return Err(DecodeError::InvalidSignature);
+    }
+    let content = strip_sauce(data, 0);
+    let mut reader = content;
+    take(&mut reader, 4)?;
+    let header = take(&mut reader, 8)?;
+    let x1 = u16::from_le_bytes([header[0], header[1]]);
+    let y1 = u16::from_le_bytes([header[2], header[3]]);
+    let x2 = u16::from_le_bytes([header[4], header[5]]);
+    let y2 = u16::from_le_bytes([header[6], header[7]]);
+    let width = x2
+        .checked_sub(x1)
+        .and_then(|columns| columns.checked_add(1))
+        .ok_or(DecodeError::InvalidDimensions)?;
+    let rows = y2.checked_sub(y1).ok_or(DecodeError::InvalidDimensions)?;
+    let max_cells = usize::from(width) * (usize::from(rows) + 1);
+    if max_cells > MAX_CELLS {
+        return Err(DecodeError::InvalidDimensions);
+    }
+
+    let footer_start = reader
+        .len()
+        .checked_sub(FOOTER_LEN)
+        .ok_or(DecodeError::Truncated)?;
+    let (mut image, mut footer) = reader.split_at(footer_start);
+    let font = Font {
+        height: 16,
+        glyphs: 256,
+        data: take(&mut footer, 4096)?.to_vec(),
+    };
+    let palette = dac_palette(footer);
+
+    let mut cells = Vec::new();
+    while image.len() >= 2 {
+        let pair = take(&mut image, 2)?;
+        if pair == [0x01, 0x00] {
+            // Run: count word, then the repeated pair
+            let run = take(&mut image, 4)?;
+            let count = usize::from(u16::from_le_bytes([run[0], run[1]]));
+            if count > max_cells - cells.len() {
+                return Err(DecodeError::InvalidDimensions);
+            }
+            let cell = ImageCell::from_attribute(run[2], run[3]);
+            cells.extend(std::iter::repeat_n(cell, count));
+        } else {
+            cells.push(ImageCell::from_attribute(pair[0], pair[1]));
+        }
+        if cells.len() > max_cells {
+            return Err(DecodeError::InvalidDimensions);
+        }
+    }
+    let height = pad_rows(&mut cells, width)?;
+
+    Ok(TextImage {
+        width,
+        height,
+        cells,
+        palette: Some(palette),
+        font: Some(font),
+        ice_colors: true,
+        sauce: parse_sauce(data),
+    })
+}
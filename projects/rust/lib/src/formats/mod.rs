@@ -0,0 +1,207 @@
+//! Decoders for binary character/attribute art formats
+//!
+//! Unlike ANSI files, these formats store a fixed-width grid of cells (a CP437 character
+//! plus colors) and often embed their own palette and font. Each decoder produces a
+//! [`TextImage`], which renders through the same HTML writer and document model as
+//! [`convert_with_options`](crate::convert_with_options).
+
+mod artworx;
+mod binary_text;
+mod ice_draw;
+mod tundra;
+mod xbin;
+
+pub use artworx::decode_artworx;
+pub use binary_text::decode_binary_text;
+pub use ice_draw::decode_ice_draw;
+pub use tundra::decode_tundra;
+pub use xbin::decode_xbin;
+
+use crate::document::DocumentBuilder;
+use crate::html::HtmlWriter;
+use crate::{
+    ConvertOptions, Converter, Document, ExtendedColor, Palette, SauceRecord, find_sauce_positions,
+};
+use std::fmt;
+
+/// Largest number of cells in a decoded image (13107 rows of 80 columns)
+pub(crate) const MAX_CELLS: usize = 1 << 20;
+
+/// Error returned when a binary art file cannot be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The data does not start with the format's signature
+    InvalidSignature,
+    /// The data ends before the header, palette, font or image data is complete
+    Truncated,
+    /// The header declares an image without cells, or the image data does not fit the
+    /// declared dimensions
+    InvalidDimensions,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidSignature => write!(f, "missing file signature"),
+            DecodeError::Truncated => write!(f, "file is truncated"),
+            DecodeError::InvalidDimensions => write!(f, "image dimensions are invalid"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// One character cell of a decoded image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageCell {
+    /// Glyph index: a CP437 character, or 256-511 for the second half of a
+    /// 512-character font
+    pub ch: u16,
+    /// Foreground color
+    pub foreground: ExtendedColor,
+    /// Background color (CGA 8-15 are high-intensity backgrounds or blink, see
+    /// [`TextImage::ice_colors`])
+    pub background: ExtendedColor,
+}
+
+impl ImageCell {
+    /// Cell from a CP437 character and a PC text-mode attribute byte
+    /// (background in the high nibble, foreground in the low nibble)
+    pub(crate) fn from_attribute(ch: u8, attribute: u8) -> Self {
+        ImageCell {
+            ch: u16::from(ch),
+            foreground: ExtendedColor::Cga(attribute & 0x0F),
+            background: ExtendedColor::Cga(attribute >> 4),
+        }
+    }
+}
+
+/// Bitmap font embedded in an image file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Font {
+    /// Glyph height in pixels (glyphs are 8 pixels wide)
+    pub height: u8,
+    /// Number of glyphs (256, or 512 for XBin 512-character fonts)
+    pub glyphs: u16,
+    /// Glyph bitmaps, `height` bytes per glyph with the most significant bit leftmost
+    pub data: Vec<u8>,
+}
+
+/// A decoded character/attribute image
+#[derive(Debug, Clone)]
+pub struct TextImage {
+    /// Width in character cells
+    pub width: u16,
+    /// Height in character cells
+    pub height: u16,
+    /// Cells in row-major order (`width * height` of them)
+    pub cells: Vec<ImageCell>,
+    /// Palette embedded in the file, used unless the options select another one
+    pub palette: Option<Palette>,
+    /// Font embedded in the file
+    pub font: Option<Font>,
+    /// Whether backgrounds 8-15 are high-intensity colors (iCE) rather than blink
+    pub ice_colors: bool,
+    pub(crate) sauce: Option<SauceRecord>,
+}
+
+impl TextImage {
+    /// Render the image as a `<pre class="ansi">` HTML fragment.
+    ///
+    /// Rows are written like the virtual screen buffer output, followed by the SAUCE
+    /// metadata lines when the file has a SAUCE record. The image's own palette is
+    /// declared on the `<pre>` element unless [`ConvertOptions::palette`] is set, and
+    /// [`BlinkMode::Auto`](crate::BlinkMode::Auto) follows [`ice_colors`](Self::ice_colors).
+    pub fn to_html(&self, options: &ConvertOptions) -> String {
+        let mut converter = Converter::new(
+            self.render_options(options),
+            HtmlWriter::new(String::new(), options.music_elements),
+        );
+        converter.run_image(self);
+        converter.sink.output
+    }
+
+    /// Render the image into a [`Document`] of styled spans.
+    pub fn to_document(&self, options: &ConvertOptions) -> Document {
+        let mut converter =
+            Converter::new(self.render_options(options), DocumentBuilder::default());
+        converter.run_image(self);
+        converter.sink.finish()
+    }
+
+    fn render_options(&self, options: &ConvertOptions) -> ConvertOptions {
+        ConvertOptions {
+            palette: options.palette.or(self.palette),
+            ..options.clone()
+        }
+    }
+}
+
+/// Take the next `len` bytes from `data`
+pub(crate) fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+    if data.len() < len {
+        return Err(DecodeError::Truncated);
+    }
+    let (head, rest) = data.split_at(len);
+    *data = rest;
+    Ok(head)
+}
+
+/// Palette from sixteen 6-bit VGA DAC RGB triplets
+pub(crate) fn dac_palette(bytes: &[u8]) -> Palette {
+    let scale = |value: u8| {
+        let value = value & 0x3F;
+        (value << 2) | (value >> 4)
+    };
+    let mut colors = [(0, 0, 0); 16];
+    for (color, rgb) in colors.iter_mut().zip(bytes.chunks_exact(3)) {
+        *color = (scale(rgb[0]), scale(rgb[1]), scale(rgb[2]));
+    }
+    Palette {
+        colors,
+        extended: None,
+    }
+}
+
+/// SAUCE record appended to `data`, if any
+pub(crate) fn parse_sauce(data: &[u8]) -> Option<SauceRecord> {
+    let (sauce_pos, comnt_pos, _) = find_sauce_positions(data);
+    let sauce_start = sauce_pos?;
+    let comnt_data = comnt_pos.map(|pos| &data[pos..sauce_start]);
+    SauceRecord::parse(&data[sauce_start..], comnt_data)
+}
+
+/// `data` before an appended SAUCE record or COMNT block
+pub(crate) fn sauce_content(data: &[u8]) -> &[u8] {
+    let (sauce_pos, comnt_pos, _) = find_sauce_positions(data);
+    &data[..comnt_pos.or(sauce_pos).unwrap_or(data.len())]
+}
+
+/// `data` without an appended SAUCE record, COMNT block and the Ctrl-Z before them
+///
+/// Ctrl-Z is a valid character in binary image data, so it is only dropped when it
+/// would leave an odd byte after the `header_len` header bytes and the
+/// character/attribute pairs.
+pub(crate) fn strip_sauce(data: &[u8], header_len: usize) -> &[u8] {
+    let content = sauce_content(data);
+    match content.split_last() {
+        Some((0x1A, rest)) if content.len().saturating_sub(header_len) % 2 == 1 => rest,
+        _ => content,
+    }
+}
+
+/// Rows of `width` cells, padding a partial last row with blank cells.
+///
+/// Fails when there are no cells or more rows than a `u16` height can hold.
+pub(crate) fn pad_rows(cells: &mut Vec<ImageCell>, width: u16) -> Result<u16, DecodeError> {
+    if width == 0 || cells.is_empty() {
+        return Err(DecodeError::InvalidDimensions);
+    }
+    let rows = cells.len().div_ceil(usize::from(width));
+    let height = u16::try_from(rows).map_err(|_| DecodeError::InvalidDimensions)?;
+    cells.resize(
+        rows * usize::from(width),
+        ImageCell::from_attribute(b' ', 0x07),
+    );
+    Ok(height)
+}
@@ -0,0 +1,130 @@
+//! TundraDraw (`.tnd`) decoder
+//!
+//! A TundraDraw file is a 9-byte header (`0x18` + `TUNDRA24`) followed by a stream of
+//! CP437 characters written left to right. Four command bytes change the cursor or the
+//! 24-bit colors:
+//!
+//! | Byte | Followed by | Effect |
+//! |------|-------------|--------|
+//! | 1 | row, column (32-bit big-endian) | Move the cursor |
+//! | 2 | character, foreground | Set the foreground and write the character |
+//! | 4 | character, background | Set the background and write the character |
+//! | 6 | character, foreground, background | Set both colors and write the character |
+//!
+//! Colors are 32-bit big-endian `0x00RRGGBB` values.
+
+use super::{
+    DecodeError, ImageCell, MAX_CELLS, TextImage, pad_rows, parse_sauce, sauce_content, take,
+};
+use crate::ExtendedColor;
+
+/// Width of TundraDraw files without a SAUCE width
+const DEFAULT_WIDTH: u16 = 80;
+
+/// Decode a TundraDraw file.
+///
+/// Colors become [`ExtendedColor::Rgb`] cells, rendered as `<ans-rgb>` elements. Text
+/// written before the first color command is light gray on black. The width comes from
+/// SAUCE, or 80 columns.
+///
+/// # Errors
+/// Returns [`DecodeError`] if the signature is missing, a command is cut short, the
+/// cursor moves past the largest supported image (65535 rows or 1048576 cells), or
+/// nothing is drawn.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::{decode_tundra, ConvertOptions, ExtendedColor};
+///
+/// // "A" in orange, then "B" with the same colors
+/// let data = b"\x18TUNDRA24\x02A\x00\xff\x80\x00B";
+/// let image = decode_tundra(data).unwrap();
+/// assert_eq!(image.cells[1].foreground, ExtendedColor::Rgb(255, 128, 0));
+/// let html = image.to_html(&ConvertOptions::default());
+/// assert!(html.contains("<ans-rgb fg=\"255,128,0\" bg=\"bg-0\">AB</ans-rgb>"));
+/// ```
+pub fn decode_tundra(data: &[u8]) -> Result<TextImage, DecodeError> {
+    if !data.starts_with(b"\x18TUNDRA24") {
+        return Err(DecodeError::InvalidSignature);
+    }
+    let sauce = parse_sauce(data);
+    let width = sauce
+        .as_ref()
+        .and_then(|sauce| sauce.character_width())
+        .unwrap_or(DEFAULT_WIDTH);
+    let columns = usize::from(width);
+    let max_rows = (MAX_CELLS / columns).min(usize::from(u16::MAX));
+
+    // Ctrl-Z is a character in the stream, so it is only an end-of-file marker before SAUCE
+    let content = sauce_content(data);
+    let mut reader = match content.strip_suffix(b"\x1a") {
+        Some(rest) if content.len() < data.len() => &rest[9..],
+        _ => &content[9..],
+    };
+    let mut cells: Vec<ImageCell> = Vec::new();
+    let mut foreground = ExtendedColor::Cga(7);
+    let mut background = ExtendedColor::Cga(0);
+    let (mut row, mut column) = (0usize, 0usize);
+
+    while let Some((&byte, rest)) = reader.split_first() {
+        reader = rest;
+        let ch = match byte {
+            1 => {
+                let position = take(&mut reader, 8)?;
+                row = word(&position[..4]);
+                column = word(&position[4..]);
+                continue;
+            }
+            2 | 4 | 6 => {
+                let ch = take(&mut reader, 1)?[0];
+                if byte != 4 {
+                    foreground = rgb(take(&mut reader, 4)?);
+                }
+                if byte != 2 {
+                    background = rgb(take(&mut reader, 4)?);
+                }
+                ch
+            }
+            _ => byte,
+        };
+
+        if column >= columns {
+            row += column / columns;
+            column %= columns;
+        }
+        if row >= max_rows {
+            return Err(DecodeError::InvalidDimensions);
+        }
+        let index = row * columns + column;
+        if index >= cells.len() {
+            cells.resize(index + 1, ImageCell::from_attribute(b' ', 0x07));
+        }
+        cells[index] = ImageCell {
+            ch: u16::from(ch),
+            foreground,
+            background,
+        };
+        column += 1;
+    }
+    let height = pad_rows(&mut cells, width)?;
+
+    Ok(TextImage {
+        width,
+        height,
+        cells,
+        palette: None,
+        font: None,
+        ice_colors: true,
+        sauce,
+    })
+}
+
+/// 32-bit big-endian value
+fn word(bytes: &[u8]) -> usize {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+}
+
+/// RGB color from a 32-bit big-endian `0x00RRGGBB` value
+fn rgb(bytes: &[u8]) -> ExtendedColor {
+    ExtendedColor::Rgb(bytes[1], bytes[2], bytes[3])
+}
@@ -0,0 +1,149 @@
+//! XBin (`.xb`) decoder
+//!
+//! An XBin file is an 11-byte header (`XBIN` + Ctrl-Z, width, height, font height and
+//! flags), followed by an optional 16-color palette, an optional font and the
+//! character/attribute pairs, which may be run-length compressed.
+
+use super::{DecodeError, Font, ImageCell, MAX_CELLS, TextImage, dac_palette, parse_sauce, take};
+
+const FLAG_PALETTE: u8 = 0x01;
+const FLAG_FONT: u8 = 0x02;
+const FLAG_COMPRESS: u8 = 0x04;
+const FLAG_NON_BLINK: u8 = 0x08;
+const FLAG_512_CHARS: u8 = 0x10;
+
+/// Decode an XBin file.
+///
+/// The embedded palette and font (if present) are returned with the image, and a SAUCE
+/// record appended to the file is shown after the image when rendering.
+///
+/// # Errors
+/// Returns [`DecodeError`] if the signature is missing, the width or height is zero, the
+/// image is larger than 1048576 cells, the file ends before all of the data declared by
+/// the header, or compressed data decodes to more cells than the header declares.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::{decode_xbin, ConvertOptions};
+///
+/// // 2x1 image without palette, font or compression
+/// let data = b"XBIN\x1a\x02\x00\x01\x00\x10\x00A\x1fB\x4e";
+/// let image = decode_xbin(data).unwrap();
+/// assert_eq!((image.width, image.height), (2, 1));
+/// let html = image.to_html(&ConvertOptions::default());
+/// assert!(html.contains("<ans-1f>A</ans-1f><ans-4e>B</ans-4e>"));
+/// ```
+pub fn decode_xbin(data: &[u8]) -> Result<TextImage, DecodeError> {
+    if !data.starts_with(b"XBIN\x1a") {
+        return Err(DecodeError::InvalidSignature);
+    }
+    let mut reader = &data[5..];
+    let header = take(&mut reader, 6)?;
+    let width = u16::from_le_bytes([header[0], header[1]]);
+    let height = u16::from_le_bytes([header[2], header[3]]);
+    let font_height = header[4];
+    let flags = header[5];
+    let count = usize::from(width) * usize::from(height);
+    if count == 0 || count > MAX_CELLS {
+        return Err(DecodeError::InvalidDimensions);
+    }
+
+    let palette = if flags & FLAG_PALETTE != 0 {
+        Some(dac_palette(take(&mut reader, 48)?))
+    } else {
+        None
+    };
+
+    let font = if flags & FLAG_FONT != 0 {
+        let glyphs: u16 = if flags & FLAG_512_CHARS != 0 {
+            512
+        } else {
+            256
+        };
+        let data = take(&mut reader, usize::from(font_height) * usize::from(glyphs))?;
+        Some(Font {
+            height: font_height,
+            glyphs,
+            data: data.to_vec(),
+        })
+    } else {
+        None
+    };
+
+    let pairs = if flags & FLAG_COMPRESS != 0 {
+        decompress(&mut reader, count)?
+    } else {
+        take(&mut reader, count * 2)?
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect()
+    };
+
+    let cells = pairs
+        .into_iter()
+        .map(|(ch, attribute)| {
+            if flags & FLAG_512_CHARS != 0 {
+                // Foreground bit 3 selects the second half of the font
+                let mut cell = ImageCell::from_attribute(ch, attribute & !0x08);
+                cell.ch += u16::from(attribute & 0x08) << 5;
+                cell
+            } else {
+                ImageCell::from_attribute(ch, attribute)
+            }
+        })
+        .collect();
+
+    Ok(TextImage {
+        width,
+        height,
+        cells,
+        palette,
+        font,
+        ice_colors: flags & FLAG_NON_BLINK != 0,
+        sauce: parse_sauce(data),
+    })
+}
+
+/// Decode `count` run-length compressed character/attribute pairs.
+///
+/// Each run starts with a byte whose top two bits give the run type and whose low six
+/// bits give the run length minus one. The header's `count` is not trusted for
+/// preallocation; the output grows with the runs actually read.
+fn decompress(reader: &mut &[u8], count: usize) -> Result<Vec<(u8, u8)>, DecodeError> {
+    let mut pairs = Vec::new();
+    while pairs.len() < count {
+        let run = take(reader, 1)?[0];
+        let len = usize::from(run & 0x3F) + 1;
+        match run >> 6 {
+            0 => {
+                // Uncompressed pairs
+                for pair in take(reader, len * 2)?.chunks_exact(2) {
+                    pairs.push((pair[0], pair[1]));
+                }
+            }
+            1 => {
+                // One character repeated with `len` attributes
+                let ch = take(reader, 1)?[0];
+                for &attribute in take(reader, len)? {
+                    pairs.push((ch, attribute));
+                }
+            }
+            2 => {
+                // One attribute repeated with `len` characters
+                let attribute = take(reader, 1)?[0];
+                for &ch in take(reader, len)? {
+                    pairs.push((ch, attribute));
+                }
+            }
+            _ => {
+                // One pair repeated `len` times
+                let pair = take(reader, 2)?;
+                pairs.extend(std::iter::repeat_n((pair[0], pair[1]), len));
+            }
+        }
+        if pairs.len() > count {
+            return Err(DecodeError::InvalidDimensions);
+        }
+    }
+    Ok(pairs)
+}
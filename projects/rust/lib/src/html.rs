@@ -0,0 +1,257 @@
+//! HTML writer for styled text
+//!
+//! Renders styled text into a `<pre class="ansi">` fragment using the custom
+//! elements understood by the generated CSS and JavaScript:
+//!
+//! - `<ans-KF>` when both colors are CGA (K=background, F=foreground)
+//! - `<ans-256 fg="N" bg="N">` when either color is a 256-color palette index
+//! - `<ans-rgb fg="R,G,B" bg="R,G,B">` when either color is 24-bit RGB
+//!
+//! Text attributes are written as boolean attributes on the same elements, e.g.
+//! `<ans-07 blink underline>`.
+//!
+//! A palette passed to [`Sink::begin`] is declared as `--ans-color-N` custom properties
+//! (and `--ans-256-N` for a custom 256-color table) in the `style` attribute of the `<pre>`
+//! element.
+//!
+//! When enabled, ANSI music is written as `<ans-music notes="...">` elements listing each
+//! note as `frequency,duration,sounding` (Hz and milliseconds, frequency 0 for rests).
+//!
+//! RIPscrip graphics are written as inline `<svg class="rip">` elements and Sixel images
+//! as `<img class="sixel">` elements with PNG data URIs, where they occur.
+//!
+//! Tags are only switched when the style changes, and newlines are written inside
+//! the currently open tag.
+
+#[cfg(feature = "sixel")]
+use crate::Bitmap;
+use crate::{ExtendedColor, MusicNote, Palette, Sink, Style};
+
+/// Tag family used to render a style
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TagKind {
+    /// Standard 16-color CGA mode (uses <ans-KF> tags)
+    Cga,
+    /// 256-color mode (uses <ans-256 fg="N" bg="N"> tags)
+    Color256,
+    /// 24-bit RGB mode (uses <ans-rgb fg="R,G,B" bg="R,G,B"> tags)
+    Rgb,
+}
+
+impl TagKind {
+    fn of(style: &Style) -> Self {
+        let colors = [style.foreground, style.background];
+        if colors.iter().any(|c| matches!(c, ExtendedColor::Rgb(..))) {
+            TagKind::Rgb
+        } else if colors
+            .iter()
+            .any(|c| matches!(c, ExtendedColor::Palette(_)))
+        {
+            TagKind::Color256
+        } else {
+            TagKind::Cga
+        }
+    }
+}
+
+/// Convert a CGA color index (0-15) to its lowercase hex digit
+pub(crate) fn color_to_hex(color: u8) -> char {
+    match color {
+        0..=9 => (b'0' + color) as char,
+        10..=15 => (b'a' + color - 10) as char,
+        _ => '0',
+    }
+}
+
+/// Format an extended color as a string attribute value
+fn format_ext_color(color: &ExtendedColor, is_foreground: bool) -> String {
+    match color {
+        ExtendedColor::Cga(c) => {
+            let prefix = if is_foreground { "fg" } else { "bg" };
+            format!("{}-{}", prefix, color_to_hex(*c))
+        }
+        ExtendedColor::Palette(n) => n.to_string(),
+        ExtendedColor::Rgb(r, g, b) => format!("{},{},{}", r, g, b),
+    }
+}
+
+/// Index of a CGA color, used for `<ans-KF>` tags
+fn cga_index(color: &ExtendedColor) -> u8 {
+    match color {
+        ExtendedColor::Cga(c) => *c,
+        _ => 0,
+    }
+}
+
+/// Writes styled text as HTML into a string buffer
+pub(crate) struct HtmlWriter {
+    /// Rendered HTML; may be drained between calls when streaming
+    pub output: String,
+    /// Style of the currently open tag
+    active: Style,
+    /// Whether ANSI music is written as `<ans-music>` elements
+    music_elements: bool,
+}
+
+impl HtmlWriter {
+    pub fn new(output: String, music_elements: bool) -> Self {
+        Self {
+            output,
+            active: Style::default(),
+            music_elements,
+        }
+    }
+
+    fn open_tag(&mut self, style: &Style) {
+        match TagKind::of(style) {
+            TagKind::Cga => {
+                let bg = color_to_hex(cga_index(&style.background));
+                let fg = color_to_hex(cga_index(&style.foreground));
+                self.output.push_str(&format!("<ans-{}{}", bg, fg));
+            }
+            TagKind::Color256 => {
+                let fg = format_ext_color(&style.foreground, true);
+                let bg = format_ext_color(&style.background, false);
+                self.output
+                    .push_str(&format!("<ans-256 fg=\"{}\" bg=\"{}\"", fg, bg));
+            }
+            TagKind::Rgb => {
+                let fg = format_ext_color(&style.foreground, true);
+                let bg = format_ext_color(&style.background, false);
+                self.output
+                    .push_str(&format!("<ans-rgb fg=\"{}\" bg=\"{}\"", fg, bg));
+            }
+        }
+        for name in style.attributes.html_names() {
+            self.output.push(' ');
+            self.output.push_str(name);
+        }
+        self.output.push('>');
+    }
+
+    fn close_tag(&mut self, style: &Style) {
+        match TagKind::of(style) {
+            TagKind::Cga => {
+                let bg = color_to_hex(cga_index(&style.background));
+                let fg = color_to_hex(cga_index(&style.foreground));
+                self.output.push_str(&format!("</ans-{}{}>", bg, fg));
+            }
+            TagKind::Color256 => {
+                self.output.push_str("</ans-256>");
+            }
+            TagKind::Rgb => {
+                self.output.push_str("</ans-rgb>");
+            }
+        }
+    }
+
+    /// Append a character to the output, escaping HTML special characters
+    fn push_escaped(&mut self, ch: char) {
+        match entity(ch) {
+            Some(entity) => self.output.push_str(entity),
+            None => self.output.push(ch),
+        }
+    }
+}
+
+/// The entity replacing an HTML special character, if `ch` is one
+fn entity(ch: char) -> Option<&'static str> {
+    match ch {
+        '<' => Some("&lt;"),
+        '>' => Some("&gt;"),
+        '&' => Some("&amp;"),
+        '"' => Some("&quot;"),
+        '\'' => Some("&apos;"),
+        _ => None,
+    }
+}
+
+/// Escape HTML special characters in `text`, for use in element content and quoted
+/// attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match entity(ch) {
+            Some(entity) => escaped.push_str(entity),
+            None => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+impl Sink for HtmlWriter {
+    /// Write the opening `<pre>` wrapper (with the scoped palette) and the initial color tag
+    fn begin(&mut self, palette: Option<&Palette>) {
+        match palette {
+            Some(palette) => self.output.push_str(&format!(
+                "<pre class=\"ansi\" style=\"{}\">",
+                palette.css_variables()
+            )),
+            None => self.output.push_str("<pre class=\"ansi\">"),
+        }
+        let style = self.active;
+        self.open_tag(&style);
+    }
+
+    fn text(&mut self, text: &str, style: Style) {
+        if style != self.active {
+            let active = self.active;
+            self.close_tag(&active);
+            self.open_tag(&style);
+            self.active = style;
+        }
+        for ch in text.chars() {
+            self.push_escaped(ch);
+        }
+    }
+
+    fn newline(&mut self) {
+        self.output.push('\n');
+    }
+
+    fn music(&mut self, notes: &[MusicNote]) {
+        if !self.music_elements {
+            return;
+        }
+        let notes: Vec<String> = notes
+            .iter()
+            .map(|note| {
+                format!(
+                    "{:.2},{},{}",
+                    note.frequency().unwrap_or(0.0),
+                    note.duration().as_millis(),
+                    note.sounding_duration().as_millis()
+                )
+            })
+            .collect();
+        self.output.push_str(&format!(
+            "<ans-music notes=\"{}\"></ans-music>",
+            notes.join(" ")
+        ));
+    }
+
+    fn graphics(&mut self, svg: &str) {
+        self.output.push_str(svg);
+    }
+
+    #[cfg(feature = "sixel")]
+    fn image(&mut self, image: &Bitmap) {
+        self.output.push_str(&format!(
+            "<img class=\"sixel\" width=\"{}\" height=\"{}\" style=\"display:block\" alt=\"\" src=\"{}\">",
+            image.width,
+            image.height,
+            image.to_data_uri()
+        ));
+    }
+
+    /// Close the open color tag and the `<pre>` wrapper
+    fn end(&mut self) {
+        let active = self.active;
+        self.close_tag(&active);
+        self.output.push_str("</pre>");
+    }
+
+    fn buffered_len(&self) -> usize {
+        self.output.len()
+    }
+}
@@ -12,12 +12,26 @@
 //!   - SGR (Select Graphic Rendition) color codes: 30-37, 40-47, 90-97, 100-107
 //!   - Bold/bright mode (ESC[1m) - sets high intensity on foreground
 //!   - Dim mode (ESC[2m, ESC[22m) - clears high intensity
-//!   - Blink (ESC[5m, ESC[6m) - sets high intensity on background (CGA style)
+//!   - Blink (ESC[5m, ESC[6m) - sets the blink attribute bit, shown according to
+//!     [`ConvertOptions::blink_mode`]: high-intensity background (iCE colors), blinking
+//!     text, or ignored
 //!   - Reverse video (ESC[7m)
+//!   - 24-bit color: ESC[38;2;R;G;Bm / ESC[48;2;R;G;Bm, and the PabloDraw form
+//!     ESC[1;R;G;Bt (foreground) / ESC[0;R;G;Bt (background)
+//!   - Text attributes, emitted as element attributes: faint (ESC[2m), italic (ESC[3m),
+//!     underline (ESC[4m), double underline (ESC[21m), conceal (ESC[8m), strikethrough
+//!     (ESC[9m), overline (ESC[53m), and their resets (ESC[22m/23m/24m/28m/29m/55m)
 //!   - Reset (ESC[0m)
 //!   - Clear screen (ESC[2J, ESC[3J) - emits three newlines
 //!   - Cursor forward (ESC[C, ESC[nC) - emits n space characters (default 1)
 //!   - Save/restore cursor position (ESC[s/ESC[u and ESC7/ESC8) - collapses text between
+//!   - DCS, OSC, SOS, PM and APC control strings (ESC P, ESC ], ESC X, ESC ^, ESC _ up to
+//!     `ESC \` or, for OSC, BEL) are removed from the text
+//!
+//! - **Virtual screen buffer** (optional): Text is written into a grid of cells so that
+//!   cursor position (ESC[H, ESC[f), cursor movement (ESC[A/B/C/D/E/F/G/d), save/restore
+//!   cursor and erase sequences (ESC[J, ESC[K) are applied as a terminal would, then the
+//!   final screen is serialized into the same `<pre class="ansi">` output.
 //!
 //! - **BBS color code support** (optional):
 //!   - **Synchronet Ctrl-A codes**: Ctrl-A followed by color character
@@ -25,17 +39,91 @@
 //!     - Bright: Uppercase letters for high-intensity versions
 //!     - Background: 0-7 for background colors
 //!     - H(high intensity), N(normal/reset)
+//!     - Screen control: L(clear screen), '(home), J(clear to end of screen), >(clear to
+//!       end of line), <(cursor left), [(carriage return), ](line feed), /(conditional
+//!       new line), 0x7F-0xFF(move right), Z(end of message), "(file display, skipped)
 //!   - **Renegade pipe codes**: `|00` through `|23`
 //!     - `|00`-`|07`: Normal foreground colors
 //!     - `|08`-`|15`: High-intensity foreground colors
 //!     - `|16`-`|23`: Background colors
+//!   - **PCBoard `@X` codes**: `@X` followed by two hex digits (background, foreground)
+//!     - `@X1F`: White on blue, `@X0E`: Yellow on black
+//!     - `@X00` saves the current colors and `@XFF` restores them; `@@` is a literal `@`
+//!   - **Wildcat! codes**: two hex digits (background, foreground) between at signs,
+//!     e.g. `@1F@` for white on blue
+//!   - **Celerity pipe codes**: `|k`-`|w` (colors 0-7), `|K`-`|W` (colors 8-15); `|S`
+//!     switches between setting the foreground and the background
+//!   - **Mystic pipe codes**: the Renegade color codes plus MCI codes `|CL` (clear
+//!     screen), `|CR` (new line), `|BS` (backspace), `|[X##`/`|[Y##` (cursor column/row)
+//!     and `|[A##`-`|[D##` (cursor movement); pause, delay and `|@X##` formatting codes
+//!     are removed
+//!   - **WWIV heart codes**: Ctrl-C followed by a digit 0-9, selecting an attribute from
+//!     the [`WWIV_COLORS`] table or a table supplied in the options
+//!   - **AVATAR/0 codes**: `^V^A<attr>` (color), `^V^B` (blink), `^Y<char><count>`
+//!     (repeat), `^L` (clear screen), `^V^C`-`^V^F` (cursor movement), `^V^G` (clear to end
+//!     of line) and `^V^H<row><col>` (cursor position)
+//!   - **mIRC codes**: `^Cfg,bg` colors (0-15 mapped onto the CGA colors, 16-98 onto the
+//!     256-color palette, 99 for the default color), `^B` bold, `^]` italic, `^_`
+//!     underline, `^^` strikethrough, `^V` reverse and `^O` reset
+//!   - **Door game backtick codes** (LORD and IGMs): `` `1 ``-`` `9 `` and `` `0 `` (colors
+//!     1-10), `` `! ``-`` `% `` (colors 11-15) and `` `r0 ``-`` `r7 `` (background)
+//!
+//! - **Macro substitution** (optional): a [`MacroProvider`] in [`ConvertOptions::macros`]
+//!   supplies the text for `@NAME@` macros such as `@ALIAS@` and for Mystic MCI codes
+//!   such as `|UN`; macros it does not know are left as text.
+//!
+//! - **Binary art formats**: [`decode_xbin`] decodes XBin (`.xb`) files into a
+//!   [`TextImage`] of character cells, keeping the embedded palette (scoped to the output)
+//!   and font bytes. [`decode_binary_text`] decodes raw BinaryText (`.bin`) files, 160
+//!   columns wide unless SAUCE says otherwise, and [`decode_artworx`] /
+//!   [`decode_ice_draw`] decode Artworx (`.adf`) and iCE Draw (`.idf`) files with their
+//!   palette and font. [`decode_tundra`] decodes TundraDraw (`.tnd`) files into 24-bit
+//!   [`ExtendedColor::Rgb`] cells. Images render with [`TextImage::to_html`] or
+//!   [`TextImage::to_document`].
+//!
+//! - **ANSI music** (optional): with [`ConvertOptions::ansi_music`], `ESC[M` / `ESC[N`
+//!   music sequences (a BASIC `PLAY` string ended by Ctrl-N) are removed from the text. Their notes are collected in [`Document::music`]
+//!   as [`MusicNote`]s (see [`parse_music`]), and with [`ConvertOptions::music_elements`]
+//!   they are written as `<ans-music>` elements that the generated JavaScript plays with
+//!   WebAudio.
+//!
+//! - **RIPscrip graphics** (optional): with [`ConvertOptions::ripscrip`], lines starting
+//!   with `!|` are drawn as inline `<svg class="rip">` images (lines, rectangles, circles,
+//!   arcs, Bezier curves, polygons, fills and text) instead of being shown as text. Each
+//!   screen is also collected in [`Document::graphics`].
+//!
+//! - **Sixel images** (optional): with [`ConvertOptions::sixel_images`], Sixel DCS strings
+//!   are decoded into RGBA [`Bitmap`]s (see [`decode_sixel`]) and written as `<img>`
+//!   elements with PNG data URIs where they occur. The images are also collected in
+//!   [`Document::images`]. PNG encoding comes from the `sixel` cargo feature, enabled by
+//!   default; building without default features drops the only dependency.
+//!
+//! - **Streaming**: [`StreamConverter`] accepts input in arbitrary chunks and returns
+//!   HTML fragments as they become available, keeping parser state across chunk boundaries.
+//!
+//! - **Output sinks**: [`convert_to_writer`] and [`convert_to_fmt_writer`] stream HTML into
+//!   an `io::Write` or `fmt::Write` sink, and [`convert_into`] appends to a reusable `String`.
+//!
+//! - **Document model**: [`convert_to_document`] returns a [`Document`] of lines made of
+//!   styled [`Span`]s (text plus [`ExtendedColor`] foreground/background and
+//!   [`Attributes`]), for building renderers other than HTML. The HTML writer renders from
+//!   the same spans, and [`Anchor`]s place music, graphics and images between them.
 //!
 //! - **HTML output**: Results are wrapped in `<pre class="ansi">` with custom elements:
 //!   - `<ans-KF>` - Standard 16-color CGA where K=background, F=foreground (hex 0-F)
 //!   - `<ans-256 fg="N" bg="N">` - 256-color mode (N=0-255, or "fg-#"/"bg-#" for CGA fallback)
 //!   - `<ans-rgb fg="R,G,B" bg="R,G,B">` - 24-bit RGB mode (or "fg-#"/"bg-#" for CGA fallback)
+//!   - Text attributes are boolean attributes on any of these, e.g.
+//!     `<ans-07 blink underline>`, styled by the generated CSS and JavaScript
+//!
+//! - **Color palettes**: [`Palette`] presets (VGA, EGA, Amiga Workbench, xterm, modern)
+//!   or custom colors, selected with [`ConvertOptions::palette`] and passed to
+//!   [`generate_css_with_palette`] / [`generate_js_with_palette`]. Colors are exposed as
+//!   `--ans-color-N` CSS custom properties so a palette can be scoped to one `<pre>`.
 //!
-//! - **Soft returns**: Lines containing ANSI/BBS sequences automatically wrap at column 80.
+//! - **Soft returns**: Lines containing ANSI/BBS sequences automatically wrap at the terminal
+//!   width: [`ConvertOptions::columns`] when set, otherwise the SAUCE width of character
+//!   files, otherwise 80 columns.
 //!
 //! - **SAUCE metadata handling**: Parses SAUCE/COMNT records commonly appended to ANSI art
 //!   files and displays metadata as `Key: Value` lines (Title, Author, Group, Date, Size,
@@ -49,6 +137,8 @@
 //!
 //! ## CGA Color Palette
 //!
+//! The default palette ([`Palette::VGA`]):
+//!
 //! | Code | Name | Hex |
 //! |------|------|-----|
 //! | 0 | Black | #000000 |
@@ -81,7 +171,7 @@
 //! let options = ConvertOptions {
 //!     synchronet_ctrl_a: true,
 //!     renegade_pipe: true,
-//!     utf8_input: false,
+//!     ..Default::default()
 //! };
 //! let bbs_data = b"|04Red |02Green";
 //! let html = convert_with_options(bbs_data, &options);
@@ -92,10 +182,40 @@
 //! ```
 
 mod cp437;
+mod document;
+mod formats;
+mod html;
+mod macros;
+mod music;
+mod palette;
+#[cfg(feature = "sixel")]
+mod png;
+mod rip;
+mod screen;
+mod sixel;
+
+pub use document::{Anchor, AnchorKind, Attributes, Document, Line, Span, Style};
+pub use formats::{
+    DecodeError, Font, ImageCell, TextImage, decode_artworx, decode_binary_text, decode_ice_draw,
+    decode_tundra, decode_xbin,
+};
+pub use html::escape_html;
+pub use macros::{Macro, MacroProvider};
+pub use music::{Articulation, MusicNote, parse_music};
+pub use palette::Palette;
+pub use sixel::{Bitmap, decode_sixel};
 
 use cp437::CP437_TO_UNICODE;
-
-/// CGA color hex values
+use document::DocumentBuilder;
+use html::HtmlWriter;
+use rip::RipCanvas;
+use screen::{Embed, ScreenBuffer};
+use sixel::decode_sixel_within;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::{fmt, io};
+
+/// CGA color hex values (the colors of [`Palette::VGA`])
 pub const CGA_COLORS: [&str; 16] = [
     "#000000", // 0 - Black
     "#0000AA", // 1 - Blue
@@ -115,15 +235,117 @@ pub const CGA_COLORS: [&str; 16] = [
     "#FFFFFF", // F - White
 ];
 
+/// Default WWIV color table: the text-mode attribute selected by heart codes `^C0`-`^C9`
+pub const WWIV_COLORS: [u8; 10] = [
+    0x07, // 0 - Light Gray (normal text)
+    0x0B, // 1 - Light Cyan
+    0x0E, // 2 - Yellow
+    0x05, // 3 - Magenta
+    0x1F, // 4 - White on Blue
+    0x02, // 5 - Green
+    0x8C, // 6 - Blinking Light Red
+    0x09, // 7 - Light Blue
+    0x01, // 8 - Blue
+    0x03, // 9 - Cyan
+];
+
+/// Widest supported terminal; larger [`ConvertOptions::columns`] and SAUCE widths are
+/// clamped to it
+pub const MAX_COLUMNS: u16 = 1000;
+
+/// Buffered output size at which the writer APIs pass HTML on to their sink
+const WRITER_FLUSH_THRESHOLD: usize = 8 * 1024;
+
 /// Options for controlling conversion behavior
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct ConvertOptions {
     /// Enable Synchronet Ctrl-A color codes (Ctrl-A + character)
     pub synchronet_ctrl_a: bool,
     /// Enable Renegade BBS pipe codes (|00 through |23)
     pub renegade_pipe: bool,
+    /// Enable PCBoard `@X` color codes (`@X` + background and foreground hex digits)
+    pub pcboard_at_x: bool,
+    /// Enable Wildcat! `@XX@` color codes (background and foreground hex digits between
+    /// at signs)
+    pub wildcat_at: bool,
+    /// Enable Celerity letter pipe codes (`|k`-`|w`, `|K`-`|W` and `|S`)
+    pub celerity_pipe: bool,
+    /// Enable Mystic BBS pipe codes: the Renegade color codes plus MCI codes such as
+    /// `|CL` (clear screen), `|CR` (new line) and `|[X##` (cursor positioning)
+    pub mystic_mci: bool,
+    /// Enable WWIV heart codes (Ctrl-C + digit)
+    pub wwiv_heart: bool,
+    /// Attributes selected by WWIV heart codes 0-9 (background in the high nibble,
+    /// foreground in the low nibble). `None` uses [`WWIV_COLORS`].
+    pub wwiv_colors: Option<[u8; 10]>,
+    /// Enable AVATAR/0 codes: `^V^A<attr>` colors, `^Y<char><count>` repeats, `^L` clear
+    /// screen and the `^V` cursor and clear-to-end-of-line codes
+    pub avatar: bool,
+    /// Enable mIRC formatting codes: `^C` colors (`^Cfg,bg`, 16 or 99 colors), `^B` bold,
+    /// `^]` italic, `^_` underline, `^^` strikethrough, `^V` reverse and `^O` reset. WWIV
+    /// and AVATAR codes take precedence when they are also enabled.
+    pub mirc_colors: bool,
+    /// Enable LORD / door game backtick color codes (`` `1 ``-`` `0 ``, `` `! ``-`` `% ``
+    /// and `` `r0 ``-`` `r7 `` for backgrounds)
+    pub door_backtick: bool,
+    /// Read ANSI music sequences (`ESC[M` / `ESC[N` + `PLAY` string + Ctrl-N) and remove
+    /// them from the text. Without it, `ESC[M` is Delete Line and the `PLAY` string is
+    /// shown as text.
+    pub ansi_music: bool,
+    /// Emit an `<ans-music>` element for each ANSI music sequence read with
+    /// [`ansi_music`](Self::ansi_music), which the generated JavaScript plays with WebAudio
+    /// when clicked.
+    pub music_elements: bool,
+    /// Render RIPscrip graphics (lines starting with `!|`) as inline SVG images. Other
+    /// lines are converted as text.
+    pub ripscrip: bool,
+    /// Emit Sixel images (`ESC P` ... `q` ... `ESC \`) as `<img>` elements with PNG data
+    /// URIs. When disabled, Sixel data is removed from the output like other control
+    /// strings. The images of one conversion hold at most 4 megapixels and paint at most
+    /// 64 megapixels in total; images past that are removed as well.
+    ///
+    /// Requires the `sixel` cargo feature (enabled by default); without it this option
+    /// has no effect.
+    pub sixel_images: bool,
     /// Treat input as UTF-8 instead of CP437 (only convert control chars < 0x20)
     pub utf8_input: bool,
+    /// Render into a virtual screen buffer so cursor positioning, cursor movement and
+    /// erase sequences place text in its target cell. The final screen is serialized
+    /// into the output once the input has been processed.
+    pub screen_buffer: bool,
+    /// Terminal width used for soft wrapping and the screen buffer. `None` uses the
+    /// SAUCE TInfo1 width of character files when present, otherwise 80 columns.
+    /// `Some(0)` is treated like `None`, and widths above [`MAX_COLUMNS`] are clamped.
+    pub columns: Option<u16>,
+    /// How the blink attribute bit (SGR 5/6, Synchronet `I`, high background codes) is shown
+    pub blink_mode: BlinkMode,
+    /// Colors to render with. `None` uses the palette of the stylesheet generated by
+    /// [`generate_css`] / [`generate_css_with_palette`]; `Some` scopes the given palette to
+    /// the output's `<pre>` element.
+    pub palette: Option<Palette>,
+    /// Supplies the text for `@NAME@` macros (Synchronet, PCBoard, Wildcat!) and, with
+    /// `mystic_mci`, Mystic MCI codes such as `|UN`. Macros without a substitution are
+    /// left in the output. `None` leaves all macros as text.
+    pub macros: Option<Arc<dyn MacroProvider>>,
+}
+
+/// Interpretation of the blink attribute bit
+///
+/// DOS text mode shares one attribute bit between blinking text and high-intensity
+/// backgrounds. Art drawn with iCE colors uses it for the sixteen background colors,
+/// while older art expects blinking text on a low-intensity background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlinkMode {
+    /// Use the SAUCE `TFlags` iCE colors bit when a SAUCE record is present: blink when
+    /// it is clear, iCE colors when it is set. Without SAUCE, iCE colors are used.
+    #[default]
+    Auto,
+    /// High-intensity backgrounds (iCE colors)
+    IceColors,
+    /// Blinking text on the low-intensity background
+    Blink,
+    /// Low-intensity background without blinking
+    Ignore,
 }
 
 /// SAUCE record data (Standard Architecture for Universal Comment Extensions)
@@ -133,8 +355,11 @@ struct SauceRecord {
     author: String,
     group: String,
     date: String,
+    data_type: u8,
+    file_type: u8,
     width: u16,
     height: u16,
+    flags: u8,
     comments: Vec<String>,
     font: String,
 }
@@ -146,31 +371,41 @@ impl SauceRecord {
             return None;
         }
 
-        let mut record = SauceRecord::default();
+        let mut record = SauceRecord {
+            // Parse fields using CP437 decoding, trimming trailing spaces/nulls
+            title: Self::decode_field(&data[7..42]),
+            author: Self::decode_field(&data[42..62]),
+            group: Self::decode_field(&data[62..82]),
+            date: Self::decode_field(&data[82..90]),
+
+            // DataType and FileType (after the 4-byte FileSize)
+            data_type: data[94],
+            file_type: data[95],
+
+            // TInfo1 = width, TInfo2 = height (little-endian u16)
+            width: u16::from_le_bytes([data[96], data[97]]),
+            height: u16::from_le_bytes([data[98], data[99]]),
 
-        // Parse fields using CP437 decoding, trimming trailing spaces/nulls
-        record.title = Self::decode_field(&data[7..42]);
-        record.author = Self::decode_field(&data[42..62]);
-        record.group = Self::decode_field(&data[62..82]);
-        record.date = Self::decode_field(&data[82..90]);
+            // TFlags (after TInfo3, TInfo4 and the comment count)
+            flags: data[105],
 
-        // TInfo1 = width, TInfo2 = height (little-endian u16)
-        record.width = u16::from_le_bytes([data[96], data[97]]);
-        record.height = u16::from_le_bytes([data[98], data[99]]);
+            // TInfoS = font name (22 bytes, null-terminated string)
+            font: Self::decode_field(&data[106..128]),
 
-        // TInfoS = font name (22 bytes, null-terminated string)
-        record.font = Self::decode_field(&data[106..128]);
+            comments: Vec::new(),
+        };
 
         // Parse comments if COMNT block provided
-        if let Some(comnt) = comnt_data {
-            if comnt.len() >= 5 && &comnt[0..5] == b"COMNT" {
-                let comment_bytes = &comnt[5..];
-                // Each comment line is 64 bytes
-                for chunk in comment_bytes.chunks(64) {
-                    let line = Self::decode_field(chunk);
-                    if !line.is_empty() {
-                        record.comments.push(line);
-                    }
+        if let Some(comnt) = comnt_data
+            && comnt.len() >= 5
+            && &comnt[0..5] == b"COMNT"
+        {
+            let comment_bytes = &comnt[5..];
+            // Each comment line is 64 bytes
+            for chunk in comment_bytes.chunks(64) {
+                let line = Self::decode_field(chunk);
+                if !line.is_empty() {
+                    record.comments.push(line);
                 }
             }
         }
@@ -178,6 +413,22 @@ impl SauceRecord {
         Some(record)
     }
 
+    /// Width in columns declared for character-based files (DataType 1), if any
+    fn character_width(&self) -> Option<u16> {
+        (self.data_type == 1 && self.width > 0).then_some(self.width)
+    }
+
+    /// Width in columns of a BinaryText file (DataType 5), stored as half the width in FileType
+    fn binary_text_width(&self) -> Option<u16> {
+        (self.data_type == 5 && self.file_type > 0).then(|| u16::from(self.file_type) * 2)
+    }
+
+    /// Whether the iCE colors flag is set, for the types that define `TFlags`
+    /// (Character and BinaryText)
+    fn ice_colors(&self) -> Option<bool> {
+        matches!(self.data_type, 1 | 5).then_some(self.flags & 0x01 != 0)
+    }
+
     fn decode_field(bytes: &[u8]) -> String {
         bytes
             .iter()
@@ -258,6 +509,98 @@ fn find_sauce_positions(data: &[u8]) -> (Option<usize>, Option<usize>, Option<us
     (sauce_pos, comnt_pos, after_sauce)
 }
 
+/// CGA color of a Celerity pipe letter: lowercase k,b,g,c,r,m,y,w for colors 0-7 and
+/// uppercase for colors 8-15
+fn celerity_color(code: u8) -> Option<u8> {
+    let color = b"kbgcrmyw"
+        .iter()
+        .position(|&c| c == code.to_ascii_lowercase())? as u8;
+    Some(if code.is_ascii_uppercase() {
+        color + 8
+    } else {
+        color
+    })
+}
+
+/// CGA color of a door game backtick code: `1`-`9` and `0` for colors 1-10, then `!`, `@`,
+/// `#`, `$` and `%` for colors 11-15
+fn door_color(code: u8) -> Option<u8> {
+    b"1234567890!@#$%"
+        .iter()
+        .position(|&c| c == code)
+        .map(|index| index as u8 + 1)
+}
+
+/// Longest ANSI music string kept; the rest of an overlong sequence is dropped
+const MAX_MUSIC_LEN: usize = 4096;
+
+/// Longest RIPscrip line kept, including continuation lines
+const MAX_RIP_LINE_LEN: usize = 65536;
+
+/// Longest control string kept (larger Sixel images are cut off)
+const MAX_CONTROL_STRING_LEN: usize = 8 * 1024 * 1024;
+
+/// What is kept of the control string being read
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ControlStringContent {
+    /// Not a Sixel image: the rest of the string is skipped
+    Skipped,
+    /// Sixel DCS string, still reading the numeric parameters before `q`
+    SixelParameters,
+    /// Sixel image data after `q`
+    SixelData,
+}
+
+/// mIRC color code for the terminal's default color
+const MIRC_DEFAULT_COLOR: u8 = 99;
+
+/// CGA colors of mIRC colors 0-15
+const MIRC_CGA_COLORS: [u8; 16] = [
+    15, // 0 - White
+    0,  // 1 - Black
+    1,  // 2 - Blue
+    2,  // 3 - Green
+    12, // 4 - Light Red
+    4,  // 5 - Brown (maroon)
+    5,  // 6 - Purple
+    6,  // 7 - Orange
+    14, // 8 - Yellow
+    10, // 9 - Light Green
+    3,  // 10 - Cyan
+    11, // 11 - Light Cyan
+    9,  // 12 - Light Blue
+    13, // 13 - Pink
+    8,  // 14 - Grey
+    7,  // 15 - Light Grey
+];
+
+/// 256-color palette indices of the extended mIRC colors 16-98
+const MIRC_EXTENDED_COLORS: [u8; 83] = [
+    52, 94, 100, 58, 22, 29, 23, 24, 17, 54, 53, 89, // 16-27
+    88, 130, 142, 64, 28, 35, 30, 25, 18, 91, 90, 125, // 28-39
+    124, 166, 184, 106, 34, 49, 37, 33, 19, 129, 127, 161, // 40-51
+    196, 208, 226, 154, 46, 86, 51, 75, 21, 171, 201, 198, // 52-63
+    203, 215, 227, 191, 83, 122, 87, 111, 63, 177, 207, 205, // 64-75
+    217, 223, 229, 193, 157, 158, 159, 153, 147, 183, 219, 212, // 76-87
+    16, 233, 235, 237, 239, 241, 244, 247, 250, 254, 231, // 88-98
+];
+
+/// Longest `@NAME@` macro name; longer runs are treated as text
+const MAX_MACRO_NAME_LEN: usize = 32;
+
+/// Whether `ch` can appear in an `@NAME@` macro name
+fn is_macro_char(ch: char) -> bool {
+    ch.is_ascii_uppercase() || ch.is_ascii_digit() || matches!(ch, '-' | '_' | ':')
+}
+
+/// Value of an ASCII hex digit (either case)
+fn hex_digit(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        _ => (digit | 0x20) - b'a' + 10,
+    }
+}
+
 /// Extended color mode for 256-color and RGB support
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 enum ColorMode {
@@ -270,10 +613,10 @@ enum ColorMode {
     Rgb,
 }
 
-/// Extended color value (for 256-color and RGB modes)
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum ExtendedColor {
-    /// CGA color fallback (0-15)
+/// Color value of a [`Style`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtendedColor {
+    /// CGA color index (0-15), also used as fallback alongside extended colors
     Cga(u8),
     /// 256-color palette index (0-255)
     Palette(u8),
@@ -287,22 +630,96 @@ impl Default for ExtendedColor {
     }
 }
 
+/// Destination for parser output (HTML writer or document builder)
+trait Sink {
+    /// Called once before any text, with the palette scoped to the output (if any)
+    fn begin(&mut self, _palette: Option<&Palette>) {}
+    /// Append text in the given style
+    fn text(&mut self, text: &str, style: Style);
+    /// End the current line
+    fn newline(&mut self);
+    /// Called for each ANSI music sequence, with its notes and rests
+    fn music(&mut self, _notes: &[MusicNote]) {}
+    /// Called with each RIPscrip screen, rendered as an SVG element
+    fn graphics(&mut self, _svg: &str) {}
+    /// Called with each decoded Sixel image
+    fn image(&mut self, _image: &Bitmap) {}
+    /// Called once after all text
+    fn end(&mut self) {}
+    /// Size of output held by the sink that has not been drained yet
+    fn buffered_len(&self) -> usize {
+        0
+    }
+}
+
 /// Parser state for ANSI escape sequences
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum ParseState {
     Normal,
     Escape,
     Csi,
+    /// DCS, OSC, SOS, PM or APC control string (collecting it in `control_string` until
+    /// the string terminator; holds the introducer after ESC)
+    ControlString(u8),
+    /// ESC inside a control string (the string terminator is `ESC \`)
+    ControlStringEscape(u8),
     /// Synchronet Ctrl-A code (waiting for color character)
     SynchronetCtrlA,
-    /// Renegade pipe code (waiting for first digit)
+    /// Synchronet `^A"` file display (skipping the file name)
+    SynchronetFileName,
+    /// Synchronet `^AZ` end of message (the rest of the input is ignored)
+    EndOfMessage,
+    /// WWIV heart code (waiting for the color digit)
+    WwivHeart,
+    /// Pipe code (waiting for the first digit or letter)
     RenegadePipe1,
     /// Renegade pipe code (waiting for second digit)
     RenegadePipe2(u8),
+    /// Mystic MCI code (waiting for the second character, holds the first)
+    MysticMci(u8),
+    /// Mystic `|[` or `|@` code (waiting for the command letter, holds the prefix)
+    MysticCommand(u8),
+    /// Mystic `|[X##` or `|@X##` code (waiting for the two digits, holds the prefix, the
+    /// command letter and the first digit once read)
+    MysticArgument(u8, u8, Option<u8>),
+    /// PCBoard or Wildcat! code or macro (waiting for the character after `@`)
+    AtSign,
+    /// `@NAME@` macro (collecting the name in `macro_name`)
+    AtMacro,
+    /// AVATAR code (waiting for the command after `^V`)
+    AvatarCommand,
+    /// AVATAR code waiting for its arguments (command, first argument)
+    ///
+    /// The command is `^A` (attribute), `^H` (row, column) or `^Y` (character, count).
+    AvatarArgument(u8, Option<char>),
+    /// mIRC color code (foreground value and digit count so far)
+    MircForeground(u8, u8),
+    /// mIRC color code after the comma (foreground)
+    MircComma(u8),
+    /// mIRC color code with one background digit (foreground, background)
+    MircBackground(u8, u8),
+    /// Door game code (waiting for the character after the backtick)
+    DoorBacktick,
+    /// Door game background code (waiting for the digit after `` `r ``)
+    DoorBackground,
+    /// ANSI music (collecting the `PLAY` string in `music` until Ctrl-N)
+    Music,
+    /// `!` at the start of a line (waiting for the `|` of a RIPscrip command)
+    RipStart,
+    /// RIPscrip line (collecting the commands in `rip_line` until the end of the line)
+    RipLine,
+    /// PCBoard `@X` code (waiting for the background digit)
+    PcboardColor1,
+    /// PCBoard `@X` code (waiting for the foreground digit, holds the background digit)
+    PcboardColor2(u8),
+    /// Wildcat! code (waiting for the foreground digit, holds the background digit)
+    WildcatColor2(u8),
+    /// Wildcat! code (waiting for the closing `@`, holds both digits)
+    WildcatEnd(u8, u8),
 }
 
 /// Converter state
-struct Converter {
+struct Converter<S: Sink> {
     foreground: u8,
     background: u8,
     /// Extended foreground color (for 256-color and RGB modes)
@@ -311,137 +728,195 @@ struct Converter {
     ext_background: ExtendedColor,
     /// Current color mode
     color_mode: ColorMode,
-    output: String,
+    /// SGR text attributes (blink is derived from the background when rendering)
+    attributes: Attributes,
+    sink: S,
+    /// Terminal width for soft wrapping
+    columns: u32,
     current_column: u32,
     has_encountered_ansi: bool,
     save_position_active: bool,
     parse_state: ParseState,
     csi_params: String,
+    /// Contents of the control string being read
+    control_string: String,
+    control_string_content: ControlStringContent,
+    /// Pixels left for the Sixel images of this conversion
+    sixel_pixels: usize,
+    /// Pixels the Sixel images of this conversion may still paint
+    sixel_paint: usize,
     options: ConvertOptions,
+    /// Blink interpretation, with `Auto` resolved
+    blink_mode: BlinkMode,
+    /// Virtual screen (only when `options.screen_buffer` is enabled)
+    screen: Option<ScreenBuffer>,
+    /// Background and foreground saved by PCBoard `@X00`
+    pcboard_saved_colors: (u8, u8),
+    /// Whether Celerity color codes set the background (toggled by `|S`)
+    celerity_background: bool,
+    /// Name of the `@NAME@` macro being read
+    macro_name: String,
+    /// Whether mIRC bold (`^B`) is on
+    mirc_bold: bool,
+    /// Last mIRC foreground color (99 for the default color)
+    mirc_foreground: u8,
+    /// `PLAY` string of the ANSI music sequence being read
+    music: String,
+    /// Commands of the RIPscrip line being read
+    rip_line: String,
+    /// RIPscrip drawing state and the graphics drawn since the last erase
+    rip: RipCanvas,
 }
 
-impl Converter {
-    fn new(options: ConvertOptions) -> Self {
+impl<S: Sink> Converter<S> {
+    fn new(mut options: ConvertOptions, sink: S) -> Self {
+        // A width of zero would wrap before every character; use the default instead
+        options.columns = options
+            .columns
+            .filter(|&columns| columns > 0)
+            .map(|columns| columns.min(MAX_COLUMNS));
         Self {
             foreground: 7,  // Light Gray
             background: 0,  // Black
             ext_foreground: ExtendedColor::Cga(7),
             ext_background: ExtendedColor::Cga(0),
             color_mode: ColorMode::Cga,
-            output: String::new(),
+            attributes: Attributes::default(),
+            sink,
+            columns: options.columns.map_or(80, u32::from),
             current_column: 0,
             has_encountered_ansi: false,
             save_position_active: false,
             parse_state: ParseState::Normal,
             csi_params: String::new(),
+            control_string: String::new(),
+            control_string_content: ControlStringContent::Skipped,
+            sixel_pixels: sixel::MAX_PIXELS,
+            sixel_paint: sixel::MAX_PAINT,
+            blink_mode: match options.blink_mode {
+                BlinkMode::Auto => BlinkMode::IceColors,
+                mode => mode,
+            },
+            screen: options
+                .screen_buffer
+                .then(|| ScreenBuffer::new(options.columns.map_or(80, usize::from))),
+            pcboard_saved_colors: (0, 7),
+            celerity_background: false,
+            macro_name: String::new(),
+            mirc_bold: false,
+            mirc_foreground: MIRC_DEFAULT_COLOR,
+            music: String::new(),
+            rip_line: String::new(),
+            rip: RipCanvas::default(),
             options,
         }
     }
 
-    fn color_to_hex(color: u8) -> char {
-        match color {
-            0..=9 => (b'0' + color) as char,
-            10..=15 => (b'a' + color - 10) as char,
-            _ => '0',
-        }
-    }
-
-    /// Format an extended color as a string attribute value
-    fn format_ext_color(color: &ExtendedColor, is_foreground: bool) -> String {
-        match color {
-            ExtendedColor::Cga(c) => {
-                let prefix = if is_foreground { "fg" } else { "bg" };
-                format!("{}-{}", prefix, Self::color_to_hex(*c))
-            }
-            ExtendedColor::Palette(n) => n.to_string(),
-            ExtendedColor::Rgb(r, g, b) => format!("{},{},{}", r, g, b),
+    /// Change the terminal width (only valid before any input has been processed)
+    fn set_columns(&mut self, columns: u16) {
+        let columns = columns.min(MAX_COLUMNS);
+        self.columns = u32::from(columns);
+        if self.screen.is_some() {
+            self.screen = Some(ScreenBuffer::new(usize::from(columns)));
         }
     }
 
-    fn open_tag(&mut self) {
-        match self.color_mode {
-            ColorMode::Cga => {
-                let bg = Self::color_to_hex(self.background);
-                let fg = Self::color_to_hex(self.foreground);
-                self.output.push_str(&format!("<ans-{}{}>", bg, fg));
-            }
-            ColorMode::Color256 => {
-                let fg = Self::format_ext_color(&self.ext_foreground, true);
-                let bg = Self::format_ext_color(&self.ext_background, false);
-                self.output.push_str(&format!("<ans-256 fg=\"{}\" bg=\"{}\">", fg, bg));
-            }
-            ColorMode::Rgb => {
-                let fg = Self::format_ext_color(&self.ext_foreground, true);
-                let bg = Self::format_ext_color(&self.ext_background, false);
-                self.output.push_str(&format!("<ans-rgb fg=\"{}\" bg=\"{}\">", fg, bg));
-            }
-        }
-    }
+    /// Style of the current colors and attributes, as rendered in the output
+    fn current_style(&self) -> Style {
+        let mut style = match self.color_mode {
+            ColorMode::Cga => Style {
+                foreground: ExtendedColor::Cga(self.foreground),
+                background: ExtendedColor::Cga(self.background),
+                attributes: self.attributes,
+            },
+            ColorMode::Color256 | ColorMode::Rgb => Style {
+                foreground: self.ext_foreground,
+                background: self.ext_background,
+                attributes: self.attributes,
+            },
+        };
 
-    fn close_tag(&mut self) {
-        match self.color_mode {
-            ColorMode::Cga => {
-                let bg = Self::color_to_hex(self.background);
-                let fg = Self::color_to_hex(self.foreground);
-                self.output.push_str(&format!("</ans-{}{}>", bg, fg));
-            }
-            ColorMode::Color256 => {
-                self.output.push_str("</ans-256>");
-            }
-            ColorMode::Rgb => {
-                self.output.push_str("</ans-rgb>");
+        // Outside iCE mode the high-intensity background bit is the blink attribute. SGR 5
+        // keeps setting it while the background is a 256-color or RGB color, so blink
+        // survives those backgrounds too.
+        if matches!(self.blink_mode, BlinkMode::Blink | BlinkMode::Ignore)
+            && self.background & 0x08 != 0
+        {
+            if let ExtendedColor::Cga(bg) = style.background {
+                style.background = ExtendedColor::Cga(bg & 0x07);
             }
+            style.attributes.blink = self.blink_mode == BlinkMode::Blink;
         }
+        style
     }
 
-    /// Check if color state has changed (considering mode and extended colors)
-    fn colors_changed(&self, new_mode: ColorMode, new_bg: u8, new_fg: u8,
-                      new_ext_bg: ExtendedColor, new_ext_fg: ExtendedColor) -> bool {
-        if new_mode != self.color_mode {
-            return true;
-        }
-        match self.color_mode {
-            ColorMode::Cga => new_bg != self.background || new_fg != self.foreground,
-            ColorMode::Color256 | ColorMode::Rgb => {
-                new_ext_bg != self.ext_background || new_ext_fg != self.ext_foreground
+    /// Set colors that may be extended (used by the image decoders)
+    fn set_colors(&mut self, foreground: ExtendedColor, background: ExtendedColor) {
+        match (foreground, background) {
+            (ExtendedColor::Cga(fg), ExtendedColor::Cga(bg)) => self.switch_color(bg, fg),
+            _ => {
+                let any_rgb = [foreground, background]
+                    .iter()
+                    .any(|c| matches!(c, ExtendedColor::Rgb(..)));
+                self.color_mode = if any_rgb {
+                    ColorMode::Rgb
+                } else {
+                    ColorMode::Color256
+                };
+                self.ext_foreground = foreground;
+                self.ext_background = background;
             }
         }
     }
 
     fn switch_color(&mut self, new_bg: u8, new_fg: u8) {
         // Stay in CGA mode
-        let new_ext_fg = ExtendedColor::Cga(new_fg);
-        let new_ext_bg = ExtendedColor::Cga(new_bg);
-        if self.colors_changed(ColorMode::Cga, new_bg, new_fg, new_ext_bg, new_ext_fg) {
-            self.close_tag();
-            self.color_mode = ColorMode::Cga;
-            self.background = new_bg;
-            self.foreground = new_fg;
-            self.ext_foreground = new_ext_fg;
-            self.ext_background = new_ext_bg;
-            self.open_tag();
+        self.color_mode = ColorMode::Cga;
+        self.background = new_bg;
+        self.foreground = new_fg;
+        self.ext_foreground = ExtendedColor::Cga(new_fg);
+        self.ext_background = ExtendedColor::Cga(new_bg);
+    }
+
+    /// Glyph shown for a text character of the input: CP437 input maps control codes, DEL
+    /// and bytes above 127 through the CP437 table, UTF-8 input only control codes
+    fn display_char(&self, ch: char) -> char {
+        let code = ch as u32;
+        if code < 0x20 || (!self.options.utf8_input && code >= 0x7F) {
+            CP437_TO_UNICODE[code as usize]
+        } else {
+            ch
         }
     }
 
     fn emit_char(&mut self, ch: char) {
+        let style = self.current_style();
+        if let Some(screen) = self.screen.as_mut() {
+            match ch {
+                '\n' => screen.line_feed(),
+                '\r' => screen.carriage_return(),
+                _ => screen.put(ch, style),
+            }
+            return;
+        }
+
         if self.save_position_active {
             return;
         }
 
-        // Check for soft return at column 80 (only for CP437 mode with ANSI sequences)
-        if !self.options.utf8_input && self.has_encountered_ansi && self.current_column >= 80 && ch != '\n' {
-            self.output.push('\n');
+        // Check for soft return at the terminal width (only for CP437 mode with ANSI sequences)
+        if !self.options.utf8_input
+            && self.has_encountered_ansi
+            && self.current_column >= self.columns
+            && ch != '\n'
+        {
+            self.sink.newline();
             self.current_column = 0;
         }
 
         match ch {
-            '<' => self.output.push_str("&lt;"),
-            '>' => self.output.push_str("&gt;"),
-            '&' => self.output.push_str("&amp;"),
-            '"' => self.output.push_str("&quot;"),
-            '\'' => self.output.push_str("&apos;"),
             '\n' => {
-                self.output.push('\n');
+                self.sink.newline();
                 self.current_column = 0;
                 // Note: has_encountered_ansi is NOT reset - it's a file-level flag
             }
@@ -449,12 +924,58 @@ impl Converter {
                 // Suppress carriage returns
             }
             _ => {
-                self.output.push(ch);
+                self.sink.text(ch.encode_utf8(&mut [0; 4]), style);
                 self.current_column += 1;
             }
         }
     }
 
+    /// Pass the virtual screen to the sink, one line per row with trailing blank cells trimmed.
+    /// Anchored graphics are passed in front of the cell they were received at.
+    fn render_screen(&mut self) {
+        let Some(mut screen) = self.screen.take() else {
+            return;
+        };
+
+        screen.sort_embeds();
+        let mut embeds = screen.embeds().iter().peekable();
+        for index in 0..screen.height() {
+            if index > 0 {
+                self.sink.newline();
+            }
+            let row = screen.rows().get(index).map_or(&[][..], Vec::as_slice);
+            let len = row
+                .iter()
+                .rposition(|cell| !cell.is_blank())
+                .map_or(0, |pos| pos + 1);
+            let mut start = 0;
+            while let Some((_, col, embed)) = embeds.next_if(|&&(row, _, _)| row == index) {
+                let end = (*col).clamp(start, len.max(start));
+                for cell in &row[start..end] {
+                    self.sink.text(cell.ch.encode_utf8(&mut [0; 4]), cell.style);
+                }
+                start = end;
+                match embed {
+                    Embed::Graphics(svg) => self.sink.graphics(svg),
+                    Embed::Image(image) => self.sink.image(image),
+                }
+            }
+            for cell in &row[start..len] {
+                self.sink.text(cell.ch.encode_utf8(&mut [0; 4]), cell.style);
+            }
+        }
+    }
+
+    /// Start a new line below all existing content (used before SAUCE metadata)
+    fn begin_trailer_line(&mut self) {
+        if let Some(screen) = self.screen.as_mut() {
+            let last_row = screen.height();
+            screen.move_to(last_row, 0);
+        } else {
+            self.emit_char('\n');
+        }
+    }
+
     /// Map ANSI color code (0-7) to CGA color code
     fn ansi_to_cga(ansi_color: u8) -> u8 {
         match ansi_color {
@@ -503,6 +1024,7 @@ impl Converter {
         let mut new_mode = self.color_mode;
         let mut new_ext_fg = self.ext_foreground;
         let mut new_ext_bg = self.ext_background;
+        let mut new_attrs = self.attributes;
 
         let mut i = 0;
         while i < params.len() {
@@ -514,6 +1036,7 @@ impl Converter {
                     new_mode = ColorMode::Cga;
                     new_ext_fg = ExtendedColor::Cga(7);
                     new_ext_bg = ExtendedColor::Cga(0);
+                    new_attrs = Attributes::default();
                 }
                 1 => {
                     // Bold/Bright - set high bit on foreground
@@ -529,7 +1052,23 @@ impl Converter {
                     if let ExtendedColor::Cga(c) = new_ext_fg {
                         new_ext_fg = ExtendedColor::Cga(c & 0x07);
                     }
+                    new_attrs.faint = params[i] == 2;
                 }
+                3 => new_attrs.italic = true,
+                23 => new_attrs.italic = false,
+                4 => new_attrs.underline = true,
+                21 => new_attrs.double_underline = true,
+                24 => {
+                    // Underline off (single and double)
+                    new_attrs.underline = false;
+                    new_attrs.double_underline = false;
+                }
+                8 => new_attrs.conceal = true,
+                28 => new_attrs.conceal = false,
+                9 => new_attrs.strikethrough = true,
+                29 => new_attrs.strikethrough = false,
+                53 => new_attrs.overline = true,
+                55 => new_attrs.overline = false,
                 5 | 6 => {
                     // Blink - set high bit on background (in CGA terms)
                     new_bg |= 0x08;
@@ -686,20 +1225,118 @@ impl Converter {
         }
 
         // Apply accumulated changes
-        if self.colors_changed(new_mode, new_bg, new_fg, new_ext_bg, new_ext_fg) {
-            self.close_tag();
-            self.color_mode = new_mode;
-            self.foreground = new_fg;
-            self.background = new_bg;
-            self.ext_foreground = new_ext_fg;
-            self.ext_background = new_ext_bg;
-            self.open_tag();
+        self.color_mode = new_mode;
+        self.foreground = new_fg;
+        self.background = new_bg;
+        self.ext_foreground = new_ext_fg;
+        self.ext_background = new_ext_bg;
+        self.attributes = new_attrs;
+    }
+
+    /// PabloDraw 24-bit color: ESC[0;R;G;Bt sets the background, ESC[1;R;G;Bt the foreground
+    ///
+    /// Other `t` sequences (xterm window operations) have a different parameter count
+    /// and are ignored. Components above 255 are clamped to 255.
+    fn process_pablodraw_color(&mut self, params: &str) {
+        if params.split(';').count() != 4 {
+            return;
+        }
+        let selector = match Self::csi_param(params, 0, 0) {
+            0 => 48,
+            1 => 38,
+            _ => return,
+        };
+        // Parameters hold only digits, so a non-empty one that does not fit is too large
+        let component = |index: usize| match params.split(';').nth(index) {
+            Some(value) if !value.is_empty() => value.parse().unwrap_or(u8::MAX),
+            _ => 0,
+        };
+        let (r, g, b) = (component(1), component(2), component(3));
+        // Same as the SGR true-color sequence
+        self.process_sgr(&format!("{};2;{};{};{}", selector, r, g, b));
+    }
+
+    /// Save the cursor position.
+    ///
+    /// Without a screen buffer the cursor cannot return, so text up to the matching
+    /// restore is collapsed instead.
+    fn save_cursor(&mut self) {
+        self.has_encountered_ansi = true;
+        match self.screen.as_mut() {
+            Some(screen) => screen.save_cursor(),
+            None => self.save_position_active = true,
+        }
+    }
+
+    fn restore_cursor(&mut self) {
+        self.has_encountered_ansi = true;
+        match self.screen.as_mut() {
+            Some(screen) => screen.restore_cursor(),
+            None => self.save_position_active = false,
+        }
+    }
+
+    /// Parse numeric CSI parameters, treating missing or empty values as `default`
+    fn csi_param(params: &str, index: usize, default: u16) -> u16 {
+        params
+            .split(';')
+            .nth(index)
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// Apply cursor movement and erase sequences to the screen buffer
+    fn process_screen_csi(&mut self, params: &str, command: char) {
+        let style = self.current_style();
+        let Some(screen) = self.screen.as_mut() else {
+            return;
+        };
+        // Movement counts of 0 are treated as 1, like the positions in CUP
+        let count = Self::csi_param(params, 0, 1).max(1) as usize;
+
+        match command {
+            'H' | 'f' => {
+                // CUP - Cursor Position (1-based row;column)
+                let row = Self::csi_param(params, 0, 1).max(1) as usize;
+                let col = Self::csi_param(params, 1, 1).max(1) as usize;
+                screen.move_to(row - 1, col - 1);
+            }
+            'A' => screen.cursor_up(count),
+            'B' => screen.cursor_down(count),
+            'C' => screen.cursor_forward(count),
+            'D' => screen.cursor_back(count),
+            'E' => {
+                // CNL - Cursor Next Line
+                screen.cursor_down(count);
+                screen.carriage_return();
+            }
+            'F' => {
+                // CPL - Cursor Previous Line
+                screen.cursor_up(count);
+                screen.carriage_return();
+            }
+            'G' => screen.move_to_column(count - 1),
+            'd' => screen.move_to_row(count - 1),
+            'J' => screen.erase_display(Self::csi_param(params, 0, 0), style),
+            'K' => screen.erase_line(Self::csi_param(params, 0, 0), style),
+            's' => screen.save_cursor(),
+            'u' => screen.restore_cursor(),
+            'm' => self.process_sgr(params),
+            't' => self.process_pablodraw_color(params),
+            _ => {
+                // Other CSI sequences - ignored
+            }
         }
     }
 
     fn process_csi(&mut self, params: &str, command: char) {
         self.has_encountered_ansi = true;
 
+        if self.screen.is_some() {
+            self.process_screen_csi(params, command);
+            return;
+        }
+
         match command {
             'm' => {
                 // SGR - Select Graphic Rendition
@@ -739,6 +1376,10 @@ impl Converter {
             'K' => {
                 // EL - Erase in Line - ignored
             }
+            't' => {
+                // PabloDraw 24-bit color
+                self.process_pablodraw_color(params);
+            }
             _ => {
                 // Other CSI sequences - ignored
             }
@@ -752,8 +1393,13 @@ impl Converter {
     /// - Uppercase letters (K,B,G,C,R,M,Y,W) for background colors (0-7)
     /// - h/H for high intensity modifier on foreground
     /// - i/I for blink/high intensity modifier on background
+    ///
+    /// Codes that are not colors are handled by [`process_synchronet_control`](Self::process_synchronet_control).
     fn process_synchronet_code(&mut self, code: u8) {
         self.has_encountered_ansi = true;
+        if self.process_synchronet_control(code) {
+            return;
+        }
         let mut new_fg = self.foreground;
         let mut new_bg = self.background;
 
@@ -808,6 +1454,260 @@ impl Converter {
         self.switch_color(new_bg, new_fg);
     }
 
+    /// Process a Synchronet Ctrl-A screen control code. Returns `false` for color codes.
+    ///
+    /// - `L` clears the screen, `'` homes the cursor, `J` clears to the end of the screen
+    ///   and `>` clears to the end of the line
+    /// - `<` moves the cursor left, `[` and `]` are carriage return and line feed, and `/`
+    ///   starts a new line unless the cursor is already at the start of one
+    /// - 0x7F-0xFF move the cursor right (code - 0x7F) columns
+    /// - `Z` ends the message: the rest of the input is ignored
+    /// - `"` displays a file, which cannot be shown here, so its name is skipped
+    /// - Pause and delay codes (`P`, `Q`, `,`, `;`, `.`) have nothing to render
+    fn process_synchronet_control(&mut self, code: u8) -> bool {
+        match code {
+            b'L' | b'l' => self.process_csi("2", 'J'),
+            b'\'' => self.process_csi("", 'H'),
+            b'J' | b'j' => self.process_csi("0", 'J'),
+            b'>' => self.process_csi("", 'K'),
+            b'<' => self.process_csi("1", 'D'),
+            b'[' => self.emit_char('\r'),
+            b']' => match self.screen.as_mut() {
+                // Line feed without carriage return
+                Some(screen) => screen.cursor_down(1),
+                None => self.emit_char('\n'),
+            },
+            b'/' => {
+                if self.cursor_column() > 0 {
+                    self.emit_char('\n');
+                }
+            }
+            0x7F..=0xFF => {
+                let columns = code - 0x7F;
+                if columns > 0 {
+                    self.process_csi(&columns.to_string(), 'C');
+                }
+            }
+            b'Z' | b'z' => self.parse_state = ParseState::EndOfMessage,
+            b'"' => self.parse_state = ParseState::SynchronetFileName,
+            b'P' | b'p' | b'Q' | b'q' | b',' | b';' | b'.' => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Continue a door game backtick code with `ch`.
+    ///
+    /// Returns `true` when the code turned out to be plain text: it has been emitted up to
+    /// `ch`, and the caller re-processes `ch` in the normal state.
+    fn process_door_state(&mut self, ch: char) -> bool {
+        let state = self.parse_state;
+        self.parse_state = ParseState::Normal;
+        let code = if ch.is_ascii() { ch as u8 } else { 0 };
+        match state {
+            ParseState::DoorBacktick => {
+                if let Some(color) = door_color(code) {
+                    self.has_encountered_ansi = true;
+                    self.switch_color(self.background, color);
+                } else if code == b'r' {
+                    self.parse_state = ParseState::DoorBackground;
+                } else {
+                    self.emit_char('`');
+                    return true;
+                }
+            }
+            ParseState::DoorBackground => {
+                if (b'0'..=b'7').contains(&code) {
+                    self.has_encountered_ansi = true;
+                    self.switch_color(code - b'0', self.foreground);
+                } else {
+                    self.emit_char('`');
+                    self.emit_char('r');
+                    return true;
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Start an ANSI music sequence after `ESC[M` or `ESC[N`
+    ///
+    /// The `M` of `ESC[M` is kept so that a following `F`, `B`, `N`, `L` or `S` reads as
+    /// the matching `PLAY` music mode command.
+    fn start_music(&mut self, command: char) {
+        self.music.clear();
+        if command == 'M' {
+            self.music.push('M');
+        }
+        self.parse_state = ParseState::Music;
+    }
+
+    /// Continue an ANSI music sequence with `ch`.
+    ///
+    /// Ctrl-N ends the sequence; any other control character also ends it and is returned
+    /// to the caller for re-processing (returns `true`).
+    fn process_music_char(&mut self, ch: char) -> bool {
+        if ch >= ' ' {
+            if self.music.len() < MAX_MUSIC_LEN {
+                self.music.push(ch);
+            }
+            return false;
+        }
+        self.finish_music();
+        ch != '\x0e'
+    }
+
+    /// Pass the notes of the ANSI music sequence that has been read to the sink
+    fn finish_music(&mut self) {
+        self.parse_state = ParseState::Normal;
+        let notes = parse_music(&std::mem::take(&mut self.music));
+        self.sink.music(&notes);
+    }
+
+    /// Start a control string after `ESC` and its introducer.
+    ///
+    /// Only DCS strings can hold Sixel images, and only they are kept when
+    /// `sixel_images` is enabled and the `sixel` feature is built.
+    fn start_control_string(&mut self, kind: u8) {
+        self.control_string.clear();
+        self.control_string_content =
+            if kind == b'P' && cfg!(feature = "sixel") && self.options.sixel_images {
+                ControlStringContent::SixelParameters
+            } else {
+                ControlStringContent::Skipped
+            };
+        self.parse_state = ParseState::ControlString(kind);
+    }
+
+    /// Continue a control string with `ch`.
+    ///
+    /// The string ends with `ESC \`, or BEL for OSC strings; CAN and SUB cancel it. An ESC
+    /// followed by anything else also ends the string and starts a new escape sequence,
+    /// so the caller re-processes `ch` in the escape state (returns `true`).
+    fn process_control_string_char(&mut self, ch: char) -> bool {
+        match self.parse_state {
+            ParseState::ControlString(kind) => match ch {
+                '\x1b' => self.parse_state = ParseState::ControlStringEscape(kind),
+                '\x07' if kind == b']' => self.finish_control_string(),
+                '\x18' | '\x1a' => {
+                    self.control_string.clear();
+                    self.parse_state = ParseState::Normal;
+                }
+                _ => self.push_control_string_char(ch),
+            },
+            ParseState::ControlStringEscape(_) => {
+                self.finish_control_string();
+                if ch != '\\' {
+                    self.parse_state = ParseState::Escape;
+                    return true;
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Keep `ch` of a Sixel control string; other strings are skipped without being stored
+    fn push_control_string_char(&mut self, ch: char) {
+        match self.control_string_content {
+            ControlStringContent::Skipped => return,
+            ControlStringContent::SixelParameters => {
+                if ch == 'q' {
+                    self.control_string_content = ControlStringContent::SixelData;
+                } else if !ch.is_ascii_digit() && ch != ';' {
+                    self.control_string.clear();
+                    self.control_string_content = ControlStringContent::Skipped;
+                    return;
+                }
+            }
+            ControlStringContent::SixelData => {}
+        }
+        if self.control_string.len() < MAX_CONTROL_STRING_LEN {
+            self.control_string.push(ch);
+        }
+    }
+
+    /// Handle a complete control string: DCS strings holding Sixel data are shown as
+    /// images, everything else is removed from the output
+    fn finish_control_string(&mut self) {
+        self.parse_state = ParseState::Normal;
+        let body = std::mem::take(&mut self.control_string);
+        if self.control_string_content != ControlStringContent::SixelData {
+            return;
+        }
+        if let Some(image) = decode_sixel_within(&body, self.sixel_pixels, &mut self.sixel_paint) {
+            self.sixel_pixels -= image.width as usize * image.height as usize;
+            match self.screen.as_mut() {
+                Some(screen) => screen.embed(Embed::Image(image)),
+                None => {
+                    self.sink.image(&image);
+                    // The image is shown as a block, so text continues on a new line
+                    self.current_column = 0;
+                }
+            }
+        }
+    }
+
+    /// Continue a RIPscrip line with `ch`.
+    ///
+    /// Returns `true` when `ch` is not part of the line: after a `!` that does not start
+    /// RIPscrip commands, the `!` has been emitted; a control character other than a line
+    /// break ends the line. The caller re-processes `ch` in the normal state.
+    fn process_rip_char(&mut self, ch: char) -> bool {
+        if self.parse_state == ParseState::RipStart {
+            self.parse_state = ParseState::Normal;
+            if ch == '|' {
+                self.rip_line.clear();
+                self.rip_line.push(ch);
+                self.parse_state = ParseState::RipLine;
+                return false;
+            }
+            self.emit_char('!');
+            return true;
+        }
+        match ch {
+            '\r' => false,
+            '\n' => {
+                // A line ending with an unescaped backslash continues on the next line
+                let backslashes = self
+                    .rip_line
+                    .chars()
+                    .rev()
+                    .take_while(|&c| c == '\\')
+                    .count();
+                if backslashes % 2 == 1 {
+                    self.rip_line.pop();
+                } else {
+                    self.finish_rip_line();
+                }
+                false
+            }
+            _ if ch < ' ' => {
+                self.finish_rip_line();
+                true
+            }
+            _ => {
+                if self.rip_line.len() < MAX_RIP_LINE_LEN {
+                    self.rip_line.push(ch);
+                }
+                false
+            }
+        }
+    }
+
+    /// Draw the commands of the RIPscrip line that has been read
+    fn finish_rip_line(&mut self) {
+        self.parse_state = ParseState::Normal;
+        let line = std::mem::take(&mut self.rip_line);
+        if let Some(svg) = self.rip.process_line(&line) {
+            match self.screen.as_mut() {
+                Some(screen) => screen.embed(Embed::Graphics(svg)),
+                None => self.sink.graphics(&svg),
+            }
+        }
+    }
+
     /// Process Renegade pipe color code (0-31)
     fn process_renegade_code(&mut self, code: u8) {
         self.has_encountered_ansi = true;
@@ -861,244 +1761,463 @@ impl Converter {
         self.switch_color(new_bg, new_fg);
     }
 
-    fn process_byte(&mut self, byte: u8) {
-        match self.parse_state {
-            ParseState::Normal => {
-                if byte == 0x1B {
-                    self.parse_state = ParseState::Escape;
-                } else if self.options.synchronet_ctrl_a && byte == 0x01 {
-                    // Ctrl-A for Synchronet codes
-                    self.parse_state = ParseState::SynchronetCtrlA;
-                } else if self.options.renegade_pipe && byte == b'|' {
-                    // Pipe for Renegade codes
-                    self.parse_state = ParseState::RenegadePipe1;
-                } else if byte == b'\n' {
-                    self.emit_char('\n');
-                } else if byte == b'\r' {
-                    // Suppress carriage returns
-                } else if byte < 0x20 || byte >= 0x7F {
-                    // Convert CP437 to Unicode
-                    let unicode_char = CP437_TO_UNICODE[byte as usize];
-                    self.emit_char(unicode_char);
+    /// Column of the cursor on the current line
+    fn cursor_column(&self) -> usize {
+        match &self.screen {
+            Some(screen) => screen.column(),
+            None => self.current_column as usize,
+        }
+    }
+
+    /// Whether any pipe code dialect is enabled
+    fn pipe_codes_enabled(&self) -> bool {
+        self.options.renegade_pipe || self.options.celerity_pipe || self.options.mystic_mci
+    }
+
+    /// Whether numeric pipe color codes (`|00`-`|31`) are enabled
+    fn numeric_pipe_codes_enabled(&self) -> bool {
+        self.options.renegade_pipe || self.options.mystic_mci
+    }
+
+    /// Handle a letter or symbol after a pipe.
+    ///
+    /// Applies Celerity color codes and starts Mystic codes. Returns `false` when the
+    /// character does not start a code of an enabled dialect.
+    fn process_pipe_letter(&mut self, code: u8) -> bool {
+        if self.options.mystic_mci && code.is_ascii_uppercase() {
+            // Celerity codes share the uppercase letters and are resolved with the
+            // second character
+            self.parse_state = ParseState::MysticMci(code);
+        } else if self.options.mystic_mci && (code == b'[' || code == b'@') {
+            self.parse_state = ParseState::MysticCommand(code);
+        } else if self.options.celerity_pipe && (code == b'S' || celerity_color(code).is_some()) {
+            self.process_celerity_code(code);
+            self.parse_state = ParseState::Normal;
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Process a Celerity pipe code
+    ///
+    /// Lowercase letters (k,b,g,c,r,m,y,w) select colors 0-7 and uppercase letters colors
+    /// 8-15. `S` switches between setting the foreground and the background.
+    fn process_celerity_code(&mut self, code: u8) {
+        self.has_encountered_ansi = true;
+        if code == b'S' {
+            self.celerity_background = !self.celerity_background;
+            return;
+        }
+        if let Some(color) = celerity_color(code) {
+            if self.celerity_background {
+                self.switch_color(color, self.foreground);
+            } else {
+                self.switch_color(self.background, color);
+            }
+        }
+    }
+
+    /// Continue a Mystic code with `ch`.
+    ///
+    /// Returns `true` when the code turned out to be plain text: it has been emitted up to
+    /// `ch`, and the caller re-processes `ch` in the normal state.
+    fn process_mystic_state(&mut self, ch: char) -> bool {
+        let state = self.parse_state;
+        self.parse_state = ParseState::Normal;
+        match state {
+            ParseState::MysticMci(first) => {
+                if ch.is_ascii() && self.process_mystic_code(first, ch as u8) {
+                    return false;
+                }
+                let code = [first as char, ch].iter().collect::<String>();
+                if let Some(text) = self.expand_macro(Macro::Mci(&code)) {
+                    self.emit_text(&text);
+                    return false;
+                }
+                // Not an MCI code: a Celerity color, or text
+                if self.options.celerity_pipe && (first == b'S' || celerity_color(first).is_some())
+                {
+                    self.process_celerity_code(first);
                 } else {
-                    self.emit_char(byte as char);
+                    self.emit_char('|');
+                    self.emit_char(first as char);
                 }
+                true
             }
-            ParseState::Escape => {
-                match byte {
-                    b'[' => {
-                        self.parse_state = ParseState::Csi;
-                        self.csi_params.clear();
-                    }
-                    b'7' => {
-                        // \e7 - Save cursor position (DEC)
-                        self.save_position_active = true;
-                        self.has_encountered_ansi = true;
-                        self.parse_state = ParseState::Normal;
-                    }
-                    b'8' => {
-                        // \e8 - Restore cursor position (DEC)
-                        self.save_position_active = false;
-                        self.has_encountered_ansi = true;
-                        self.parse_state = ParseState::Normal;
-                    }
-                    _ => {
-                        // Unknown escape sequence, return to normal
-                        self.parse_state = ParseState::Normal;
-                    }
+            ParseState::MysticCommand(prefix) => {
+                if ch.is_ascii_uppercase() {
+                    self.parse_state = ParseState::MysticArgument(prefix, ch as u8, None);
+                    return false;
                 }
+                self.emit_char('|');
+                self.emit_char(prefix as char);
+                true
             }
-            ParseState::Csi => {
-                if byte.is_ascii_digit() || byte == b';' {
-                    self.csi_params.push(byte as char);
-                } else if byte >= 0x40 && byte <= 0x7E {
-                    // Final byte of CSI sequence
-                    let params = std::mem::take(&mut self.csi_params);
-                    self.process_csi(&params, byte as char);
-                    self.parse_state = ParseState::Normal;
-                } else {
-                    // Invalid CSI sequence
-                    self.parse_state = ParseState::Normal;
+            ParseState::MysticArgument(prefix, command, first_digit) => {
+                if let Some(digit) = ch.to_digit(10) {
+                    match first_digit {
+                        None => {
+                            self.parse_state =
+                                ParseState::MysticArgument(prefix, command, Some(digit as u8))
+                        }
+                        Some(first) => {
+                            self.process_mystic_argument(prefix, command, first * 10 + digit as u8)
+                        }
+                    }
+                    return false;
                 }
-            }
-            ParseState::SynchronetCtrlA => {
-                self.process_synchronet_code(byte);
-                self.parse_state = ParseState::Normal;
-            }
-            ParseState::RenegadePipe1 => {
-                if byte.is_ascii_digit() {
-                    self.parse_state = ParseState::RenegadePipe2(byte - b'0');
-                } else if byte == b'|' {
-                    // Escaped pipe (||), emit literal pipe
-                    self.emit_char('|');
-                    self.parse_state = ParseState::Normal;
-                } else {
-                    // Not a valid pipe code, emit the pipe and this character
-                    self.emit_char('|');
-                    self.parse_state = ParseState::Normal;
-                    // Re-process this byte in normal state
-                    self.process_byte(byte);
+                self.emit_char('|');
+                self.emit_char(prefix as char);
+                self.emit_char(command as char);
+                if let Some(first) = first_digit {
+                    self.emit_char((b'0' + first) as char);
                 }
+                true
             }
-            ParseState::RenegadePipe2(first_digit) => {
-                if byte.is_ascii_digit() {
-                    let code = first_digit * 10 + (byte - b'0');
-                    if code <= 31 {
-                        self.process_renegade_code(code);
-                    }
-                    // If code > 31, just ignore the sequence
-                } else {
-                    // Not a valid second digit, emit pipe + first digit and re-process
-                    self.emit_char('|');
-                    self.emit_char((b'0' + first_digit) as char);
-                    self.parse_state = ParseState::Normal;
-                    self.process_byte(byte);
-                    return;
-                }
-                self.parse_state = ParseState::Normal;
+            _ => false,
+        }
+    }
+
+    /// Apply a two-letter Mystic MCI code. Returns `false` for codes that are not handled.
+    ///
+    /// `|CL` clears the screen, `|CR` starts a new line and `|BS` moves back one column.
+    /// Pause and delay codes (`|PA`, `|PN`, `|DE`) have nothing to render and are removed.
+    fn process_mystic_code(&mut self, first: u8, second: u8) -> bool {
+        match &[first, second] {
+            b"CL" => self.process_csi("2", 'J'),
+            b"CR" => {
+                self.emit_char('\r');
+                self.emit_char('\n');
             }
+            b"BS" => self.process_csi("1", 'D'),
+            b"PA" | b"PN" | b"DE" => {}
+            _ => return false,
         }
+        true
     }
 
-    fn convert(&mut self, input: &[u8]) -> String {
-        self.output.push_str("<pre class=\"ansi\">");
-        self.open_tag();
+    /// Apply a Mystic `|[X##` cursor code; `|@X##` formatting codes are removed
+    ///
+    /// `|[X##` and `|[Y##` move to column / row `##`, and `|[A##`-`|[D##` move the cursor
+    /// up, down, forward and back by `##`.
+    fn process_mystic_argument(&mut self, prefix: u8, command: u8, value: u8) {
+        if prefix != b'[' {
+            return;
+        }
+        let value = value.to_string();
+        match command {
+            b'X' => self.process_csi(&value, 'G'),
+            b'Y' => self.process_csi(&value, 'd'),
+            b'A' | b'B' | b'C' | b'D' => self.process_csi(&value, command as char),
+            _ => {}
+        }
+    }
 
-        // Find SUB marker and SAUCE positions
-        let sub_pos = input.iter().position(|&b| b == 0x1A);
-        let (sauce_pos, comnt_pos, after_sauce_pos) = find_sauce_positions(input);
+    /// Whether `@` starts PCBoard or Wildcat! codes or macros
+    fn at_codes_enabled(&self) -> bool {
+        self.options.pcboard_at_x || self.options.wildcat_at || self.options.macros.is_some()
+    }
 
-        // Determine content end position
-        let content_end = sub_pos
-            .or(comnt_pos)
-            .or(sauce_pos)
-            .unwrap_or(input.len());
+    /// Text supplied by the macro provider for `code`, if any
+    fn expand_macro(&self, code: Macro<'_>) -> Option<String> {
+        self.options.macros.as_ref()?.expand(code)
+    }
 
-        // Process content before SUB/SAUCE
-        for &byte in &input[..content_end] {
-            self.process_byte(byte);
+    /// Emit substituted text as plain characters
+    fn emit_text(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.emit_char(ch);
         }
+    }
 
-        // If SAUCE record exists, parse and output it
-        if let Some(sauce_start) = sauce_pos {
-            let comnt_data = comnt_pos.map(|cp| &input[cp..sauce_start]);
-            if let Some(sauce) = SauceRecord::parse(&input[sauce_start..], comnt_data) {
-                let sauce_output = sauce.format_output();
-                if !sauce_output.is_empty() {
-                    // Add newline before SAUCE metadata
-                    self.emit_char('\n');
-                    for ch in sauce_output.chars() {
-                        self.emit_char(ch);
-                    }
-                }
-            }
+    /// Start reading an `@NAME@` macro whose name begins with `prefix` followed by `ch`.
+    ///
+    /// Returns `false` when macros are disabled or the characters cannot be a macro name
+    /// (names start with an uppercase letter and contain uppercase letters, digits, `-`,
+    /// `_` and `:`).
+    fn start_at_macro(&mut self, prefix: &[u8], ch: char) -> bool {
+        let mut name: String = prefix.iter().map(|&b| b as char).collect();
+        name.push(ch);
+        if self.options.macros.is_none()
+            || !name.starts_with(|c: char| c.is_ascii_uppercase())
+            || !name.chars().all(is_macro_char)
+        {
+            return false;
+        }
+        self.macro_name = name;
+        self.parse_state = ParseState::AtMacro;
+        true
+    }
 
-            // Check for content after SAUCE record
-            if let Some(after_pos) = after_sauce_pos {
-                if after_pos < input.len() {
-                    let remaining = &input[after_pos..];
-                    if !remaining.is_empty() && remaining.iter().any(|&b| b != 0 && b != 0x1A) {
-                        // Add newline separator before continuing content
-                        self.emit_char('\n');
-                        for &byte in remaining {
-                            if byte == 0x1A {
-                                // Another SUB - recursively handle nested SAUCE
-                                break;
-                            }
-                            self.process_byte(byte);
-                        }
-                    }
-                }
+    /// Continue an `@NAME@` macro with `ch`.
+    ///
+    /// On the closing `@` the macro is replaced by the provider's text. Returns `true` when
+    /// the macro turned out to be plain text or has no substitution: it has been emitted
+    /// up to `ch`, and the caller re-processes `ch` (a closing `@` may start the next code).
+    fn process_at_macro(&mut self, ch: char) -> bool {
+        self.parse_state = ParseState::Normal;
+        if ch != '@' && is_macro_char(ch) && self.macro_name.len() < MAX_MACRO_NAME_LEN {
+            self.macro_name.push(ch);
+            self.parse_state = ParseState::AtMacro;
+            return false;
+        }
+        let name = std::mem::take(&mut self.macro_name);
+        if ch == '@'
+            && let Some(text) = self.expand_macro(Macro::AtCode(&name))
+        {
+            self.emit_text(&text);
+            return false;
+        }
+        self.emit_char('@');
+        self.emit_text(&name);
+        true
+    }
+
+    /// Process a PCBoard `@X` code (background and foreground nibbles)
+    ///
+    /// `@X00` saves the current colors and `@XFF` restores them.
+    fn process_pcboard_code(&mut self, background: u8, foreground: u8) {
+        self.has_encountered_ansi = true;
+        match (background, foreground) {
+            (0x0, 0x0) => self.pcboard_saved_colors = (self.background, self.foreground),
+            (0xF, 0xF) => {
+                let (background, foreground) = self.pcboard_saved_colors;
+                self.switch_color(background, foreground);
             }
+            _ => self.switch_color(background, foreground),
         }
+    }
 
-        self.close_tag();
-        self.output.push_str("</pre>");
+    /// Process a WWIV heart code, selecting a color from the WWIV color table
+    fn process_wwiv_code(&mut self, code: u8) {
+        if let Some(digit) = (code as char).to_digit(10) {
+            self.has_encountered_ansi = true;
+            let attribute = self.options.wwiv_colors.unwrap_or(WWIV_COLORS)[digit as usize];
+            self.switch_color(attribute >> 4, attribute & 0x0F);
+        }
+        // Other codes are not colors, ignore
+    }
 
-        std::mem::take(&mut self.output)
+    /// Start an AVATAR code with a control character. Returns `false` for other characters.
+    ///
+    /// `^L` clears the screen and resets the attribute to cyan, `^V` starts a command and
+    /// `^Y` a run-length repeat.
+    fn start_avatar_code(&mut self, code: u32) -> bool {
+        if !self.options.avatar {
+            return false;
+        }
+        match code {
+            0x0C => {
+                self.has_encountered_ansi = true;
+                self.process_csi("2", 'J');
+                self.switch_color(0, 3);
+            }
+            0x16 => self.parse_state = ParseState::AvatarCommand,
+            0x19 => self.parse_state = ParseState::AvatarArgument(0x19, None),
+            _ => return false,
+        }
+        true
     }
 
-    fn convert_utf8(&mut self, input: &[u8]) -> String {
-        self.output.push_str("<pre class=\"ansi\">");
-        self.open_tag();
+    /// Continue an AVATAR code with the next input character.
+    ///
+    /// `code` is the character's value, used for commands and numeric arguments, and `ch`
+    /// is how it is displayed when repeated by `^Y`.
+    fn process_avatar_state(&mut self, code: u32, ch: char) {
+        let state = self.parse_state;
+        self.parse_state = ParseState::Normal;
+        self.has_encountered_ansi = true;
+        match state {
+            ParseState::AvatarCommand => match code {
+                0x01 | 0x08 => self.parse_state = ParseState::AvatarArgument(code as u8, None),
+                // Blink on
+                0x02 => self.switch_color(self.background | 0x08, self.foreground),
+                0x03 => self.process_csi("1", 'A'),
+                0x04 => self.process_csi("1", 'B'),
+                0x05 => self.process_csi("1", 'D'),
+                0x06 => self.process_csi("1", 'C'),
+                0x07 => self.process_csi("0", 'K'),
+                // Unknown commands are ignored
+                _ => {}
+            },
+            // The blink bit is only set by ^V^B
+            ParseState::AvatarArgument(0x01, _) => {
+                let attribute = (code & 0x7F) as u8;
+                self.switch_color(attribute >> 4, attribute & 0x0F);
+            }
+            ParseState::AvatarArgument(0x08, None) => {
+                self.parse_state = ParseState::AvatarArgument(0x08, char::from_u32(code));
+            }
+            ParseState::AvatarArgument(0x08, Some(row)) => {
+                self.process_csi(&format!("{};{}", u32::from(row), code), 'H');
+            }
+            ParseState::AvatarArgument(command, None) => {
+                self.parse_state = ParseState::AvatarArgument(command, Some(ch));
+            }
+            ParseState::AvatarArgument(_, Some(repeated)) => {
+                for _ in 0..code.min(0xFF) {
+                    self.emit_char(repeated);
+                }
+            }
+            _ => {}
+        }
+    }
 
-        // Find SUB marker and SAUCE positions (work on raw bytes)
-        let sub_pos = input.iter().position(|&b| b == 0x1A);
-        let (sauce_pos, comnt_pos, after_sauce_pos) = find_sauce_positions(input);
+    /// Apply a mIRC formatting control character. Returns `false` for other characters.
+    ///
+    /// `^C` starts a color code; the other codes toggle bold, italic, underline,
+    /// strikethrough and reverse video, or reset all formatting (`^O`). Soft wrapping is
+    /// left off, since chat lines are not laid out for a terminal width.
+    fn start_mirc_code(&mut self, code: u32) -> bool {
+        if !self.options.mirc_colors {
+            return false;
+        }
+        match code {
+            0x03 => self.parse_state = ParseState::MircForeground(0, 0),
+            0x02 => {
+                self.mirc_bold = !self.mirc_bold;
+                self.process_sgr(&Self::mirc_sgr(self.mirc_foreground, false, self.mirc_bold));
+            }
+            0x1D => self.attributes.italic = !self.attributes.italic,
+            0x1F => self.attributes.underline = !self.attributes.underline,
+            0x1E => self.attributes.strikethrough = !self.attributes.strikethrough,
+            0x16 => self.process_sgr("7"),
+            0x0F => {
+                self.mirc_bold = false;
+                self.mirc_foreground = MIRC_DEFAULT_COLOR;
+                self.process_sgr("0");
+            }
+            _ => return false,
+        }
+        true
+    }
 
-        // Determine content end position
-        let content_end = sub_pos
-            .or(comnt_pos)
-            .or(sauce_pos)
-            .unwrap_or(input.len());
+    /// Continue a mIRC `^Cfg,bg` color code with `ch`.
+    ///
+    /// Returns `true` when `ch` is not part of the code: the code has been applied (a
+    /// comma without a background digit is emitted as text), and the caller re-processes
+    /// `ch` in the normal state.
+    fn process_mirc_state(&mut self, ch: char) -> bool {
+        let state = self.parse_state;
+        self.parse_state = ParseState::Normal;
+        let digit = ch.to_digit(10).map(|digit| digit as u8);
+        match (state, digit) {
+            (ParseState::MircForeground(value, count), Some(digit)) if count < 2 => {
+                self.parse_state = ParseState::MircForeground(value * 10 + digit, count + 1);
+                return false;
+            }
+            // ^C alone resets the colors
+            (ParseState::MircForeground(_, 0), _) => {
+                self.process_mirc_color(MIRC_DEFAULT_COLOR, Some(MIRC_DEFAULT_COLOR))
+            }
+            (ParseState::MircForeground(foreground, _), _) if ch == ',' => {
+                self.parse_state = ParseState::MircComma(foreground);
+                return false;
+            }
+            (ParseState::MircForeground(foreground, _), _) => {
+                self.process_mirc_color(foreground, None)
+            }
+            (ParseState::MircComma(foreground), Some(digit)) => {
+                self.parse_state = ParseState::MircBackground(foreground, digit);
+                return false;
+            }
+            (ParseState::MircComma(foreground), None) => {
+                self.process_mirc_color(foreground, None);
+                self.emit_char(',');
+            }
+            (ParseState::MircBackground(foreground, background), Some(digit)) => {
+                self.process_mirc_color(foreground, Some(background * 10 + digit));
+                return false;
+            }
+            (ParseState::MircBackground(foreground, background), None) => {
+                self.process_mirc_color(foreground, Some(background));
+            }
+            _ => return false,
+        }
+        true
+    }
 
-        // Parse content as UTF-8
-        let content = String::from_utf8_lossy(&input[..content_end]);
-        for ch in content.chars() {
-            self.process_utf8_char(ch);
+    /// Set the foreground and optionally the background to mIRC colors
+    fn process_mirc_color(&mut self, foreground: u8, background: Option<u8>) {
+        self.mirc_foreground = foreground;
+        let mut params = Self::mirc_sgr(foreground, false, self.mirc_bold);
+        if let Some(background) = background {
+            params.push(';');
+            params.push_str(&Self::mirc_sgr(background, true, false));
         }
+        self.process_sgr(&params);
+    }
 
-        // If SAUCE record exists, parse and output it
-        if let Some(sauce_start) = sauce_pos {
-            let comnt_data = comnt_pos.map(|cp| &input[cp..sauce_start]);
-            if let Some(sauce) = SauceRecord::parse(&input[sauce_start..], comnt_data) {
-                let sauce_output = sauce.format_output();
-                if !sauce_output.is_empty() {
-                    // Add newline before SAUCE metadata
-                    self.emit_char('\n');
-                    for ch in sauce_output.chars() {
-                        self.emit_char(ch);
-                    }
+    /// SGR parameters selecting mIRC color `code`
+    ///
+    /// Colors 0-15 map onto the CGA colors (bold adds high intensity), 16-98 onto the
+    /// 256-color palette, and 99 and above select the default color.
+    fn mirc_sgr(code: u8, background: bool, bold: bool) -> String {
+        let base = if background { 40 } else { 30 };
+        match code {
+            0..=15 => {
+                let mut color = MIRC_CGA_COLORS[code as usize];
+                if bold && !background {
+                    color |= 0x08;
                 }
-            }
-
-            // Check for content after SAUCE record
-            if let Some(after_pos) = after_sauce_pos {
-                if after_pos < input.len() {
-                    let remaining = &input[after_pos..];
-                    if !remaining.is_empty() && remaining.iter().any(|&b| b != 0 && b != 0x1A) {
-                        // Add newline separator before continuing content
-                        self.emit_char('\n');
-                        let remaining_text = String::from_utf8_lossy(remaining);
-                        for ch in remaining_text.chars() {
-                            if ch == '\x1A' {
-                                break;
-                            }
-                            self.process_utf8_char(ch);
-                        }
-                    }
+                // The CGA/ANSI color order swap is its own inverse
+                let ansi = Self::ansi_to_cga(color & 0x07);
+                match (color >= 8, background) {
+                    (true, _) => (base + 60 + ansi).to_string(),
+                    // Clear the intensity bit kept by SGR 30-37 / 40-47
+                    (false, false) => format!("22;{}", base + ansi),
+                    (false, true) => format!("25;{}", base + ansi),
                 }
             }
+            16..=98 => format!(
+                "{};5;{}",
+                base + 8,
+                MIRC_EXTENDED_COLORS[code as usize - 16]
+            ),
+            _ if bold && !background => "39;1".to_string(),
+            _ => (base + 9).to_string(),
         }
-
-        self.close_tag();
-        self.output.push_str("</pre>");
-
-        std::mem::take(&mut self.output)
     }
 
-    fn process_utf8_char(&mut self, ch: char) {
+    /// Process one character of input: a CP437 byte (as the character with the same code)
+    /// or, with [`ConvertOptions::utf8_input`], a decoded UTF-8 character
+    fn process_char(&mut self, ch: char) {
         let code = ch as u32;
 
         match self.parse_state {
             ParseState::Normal => {
                 if code == 0x1B {
                     self.parse_state = ParseState::Escape;
+                } else if self.start_avatar_code(code) {
+                    // AVATAR clear screen, command or repeat
                 } else if self.options.synchronet_ctrl_a && code == 0x01 {
+                    // Ctrl-A for Synchronet codes
                     self.parse_state = ParseState::SynchronetCtrlA;
-                } else if self.options.renegade_pipe && ch == '|' {
+                } else if self.options.wwiv_heart && code == 0x03 {
+                    // Ctrl-C (heart) for WWIV codes
+                    self.parse_state = ParseState::WwivHeart;
+                } else if self.start_mirc_code(code) {
+                    // mIRC color or formatting code
+                } else if self.options.door_backtick && ch == '`' {
+                    // Backtick for door game codes
+                    self.parse_state = ParseState::DoorBacktick;
+                } else if self.pipe_codes_enabled() && ch == '|' {
+                    // Pipe for Renegade codes
                     self.parse_state = ParseState::RenegadePipe1;
+                } else if self.at_codes_enabled() && ch == '@' {
+                    // At sign for PCBoard and Wildcat! codes and macros
+                    self.parse_state = ParseState::AtSign;
+                } else if self.options.ripscrip && ch == '!' && self.cursor_column() == 0 {
+                    // Exclamation mark starting a RIPscrip line
+                    self.parse_state = ParseState::RipStart;
                 } else if ch == '\n' {
                     self.emit_char('\n');
                 } else if ch == '\r' {
-                    // Suppress carriage returns
-                } else if code < 0x20 {
-                    // Convert low-byte control characters using CP437 mapping
-                    let unicode_char = CP437_TO_UNICODE[code as usize];
-                    self.emit_char(unicode_char);
+                    // Suppressed unless rendering into the screen buffer
+                    self.emit_char('\r');
                 } else {
-                    // Pass through all other UTF-8 characters as-is
-                    self.emit_char(ch);
+                    self.emit_char(self.display_char(ch));
                 }
             }
             ParseState::Escape => {
@@ -1108,16 +2227,21 @@ impl Converter {
                         self.csi_params.clear();
                     }
                     '7' => {
-                        self.save_position_active = true;
-                        self.has_encountered_ansi = true;
+                        // \e7 - Save cursor position (DEC)
+                        self.save_cursor();
                         self.parse_state = ParseState::Normal;
                     }
                     '8' => {
-                        self.save_position_active = false;
-                        self.has_encountered_ansi = true;
+                        // \e8 - Restore cursor position (DEC)
+                        self.restore_cursor();
                         self.parse_state = ParseState::Normal;
                     }
+                    'P' | ']' | 'X' | '^' | '_' => {
+                        // DCS, OSC, SOS, PM and APC control strings
+                        self.start_control_string(ch as u8);
+                    }
                     _ => {
+                        // Unknown escape sequence, return to normal
                         self.parse_state = ParseState::Normal;
                     }
                 }
@@ -1125,31 +2249,57 @@ impl Converter {
             ParseState::Csi => {
                 if ch.is_ascii_digit() || ch == ';' {
                     self.csi_params.push(ch);
-                } else if code >= 0x40 && code <= 0x7E {
+                } else if self.options.ansi_music
+                    && self.csi_params.is_empty()
+                    && (ch == 'M' || ch == 'N')
+                {
+                    // ANSI music
+                    self.start_music(ch);
+                } else if (0x40..=0x7E).contains(&code) {
+                    // Final byte of CSI sequence
                     let params = std::mem::take(&mut self.csi_params);
                     self.process_csi(&params, ch);
                     self.parse_state = ParseState::Normal;
                 } else {
+                    // Invalid CSI sequence
                     self.parse_state = ParseState::Normal;
                 }
             }
             ParseState::SynchronetCtrlA => {
-                if code <= 0xFF {
-                    self.process_synchronet_code(code as u8);
+                self.parse_state = ParseState::Normal;
+                if let Ok(byte) = u8::try_from(ch) {
+                    self.process_synchronet_code(byte);
+                }
+            }
+            ParseState::SynchronetFileName => {
+                if code <= 0x20 {
+                    // End of the file name, re-process the separator
+                    self.parse_state = ParseState::Normal;
+                    self.process_char(ch);
+                }
+            }
+            ParseState::EndOfMessage => {}
+            ParseState::WwivHeart => {
+                if let Ok(byte) = u8::try_from(ch) {
+                    self.process_wwiv_code(byte);
                 }
                 self.parse_state = ParseState::Normal;
             }
             ParseState::RenegadePipe1 => {
-                if ch.is_ascii_digit() {
+                if ch.is_ascii_digit() && self.numeric_pipe_codes_enabled() {
                     self.parse_state = ParseState::RenegadePipe2(ch as u8 - b'0');
                 } else if ch == '|' {
                     // Escaped pipe (||), emit literal pipe
                     self.emit_char('|');
                     self.parse_state = ParseState::Normal;
+                } else if ch.is_ascii() && self.process_pipe_letter(ch as u8) {
+                    // Celerity color or the start of a Mystic code
                 } else {
+                    // Not a valid pipe code, emit the pipe and this character
                     self.emit_char('|');
                     self.parse_state = ParseState::Normal;
-                    self.process_utf8_char(ch);
+                    // Re-process this character in normal state
+                    self.process_char(ch);
                 }
             }
             ParseState::RenegadePipe2(first_digit) => {
@@ -1158,1002 +2308,4486 @@ impl Converter {
                     if code <= 31 {
                         self.process_renegade_code(code);
                     }
+                    // If code > 31, just ignore the sequence
                 } else {
+                    // Not a valid second digit, emit pipe + first digit and re-process
                     self.emit_char('|');
                     self.emit_char((b'0' + first_digit) as char);
                     self.parse_state = ParseState::Normal;
-                    self.process_utf8_char(ch);
+                    self.process_char(ch);
                     return;
                 }
                 self.parse_state = ParseState::Normal;
             }
+            ParseState::MysticMci(_)
+            | ParseState::MysticCommand(_)
+            | ParseState::MysticArgument(..) => {
+                if self.process_mystic_state(ch) {
+                    self.process_char(ch);
+                }
+            }
+            ParseState::AtMacro => {
+                if self.process_at_macro(ch) {
+                    self.process_char(ch);
+                }
+            }
+            ParseState::AvatarCommand | ParseState::AvatarArgument(..) => {
+                self.process_avatar_state(code, self.display_char(ch));
+            }
+            ParseState::MircForeground(..)
+            | ParseState::MircComma(_)
+            | ParseState::MircBackground(..) => {
+                if self.process_mirc_state(ch) {
+                    self.process_char(ch);
+                }
+            }
+            ParseState::DoorBacktick | ParseState::DoorBackground => {
+                if self.process_door_state(ch) {
+                    self.process_char(ch);
+                }
+            }
+            ParseState::Music => {
+                if self.process_music_char(ch) {
+                    self.process_char(ch);
+                }
+            }
+            ParseState::ControlString(_) | ParseState::ControlStringEscape(_) => {
+                if self.process_control_string_char(ch) {
+                    self.process_char(ch);
+                }
+            }
+            ParseState::RipStart | ParseState::RipLine => {
+                // Text in CP437 input is decoded, control characters are kept
+                let rip_char = if self.options.utf8_input || code < 0x80 {
+                    ch
+                } else {
+                    CP437_TO_UNICODE[code as usize]
+                };
+                if self.process_rip_char(rip_char) {
+                    self.process_char(ch);
+                }
+            }
+            ParseState::AtSign => {
+                self.parse_state = ParseState::Normal;
+                if self.options.pcboard_at_x && ch == 'X' {
+                    self.parse_state = ParseState::PcboardColor1;
+                } else if self.options.pcboard_at_x && ch == '@' {
+                    // Escaped at sign (@@), emit literal at sign
+                    self.emit_char('@');
+                } else if self.options.wildcat_at && ch.is_ascii_hexdigit() {
+                    self.parse_state = ParseState::WildcatColor2(ch as u8);
+                } else if self.start_at_macro(&[], ch) {
+                    // Start of an @NAME@ macro
+                } else {
+                    // Not a color code, emit the at sign and re-process this character
+                    self.emit_char('@');
+                    self.process_char(ch);
+                }
+            }
+            ParseState::PcboardColor1 => {
+                self.parse_state = ParseState::Normal;
+                if ch.is_ascii_hexdigit() {
+                    self.parse_state = ParseState::PcboardColor2(ch as u8);
+                } else if self.start_at_macro(b"X", ch) {
+                    // An @NAME@ macro starting with X
+                } else {
+                    self.emit_char('@');
+                    self.emit_char('X');
+                    self.process_char(ch);
+                }
+            }
+            ParseState::PcboardColor2(background) => {
+                self.parse_state = ParseState::Normal;
+                if ch.is_ascii_hexdigit() {
+                    self.process_pcboard_code(hex_digit(background), hex_digit(ch as u8));
+                } else if self.start_at_macro(&[b'X', background], ch) {
+                    // An @NAME@ macro starting with X
+                } else {
+                    // Not a valid second digit, emit the code so far and re-process
+                    self.emit_char('@');
+                    self.emit_char('X');
+                    self.emit_char(background as char);
+                    self.process_char(ch);
+                }
+            }
+            ParseState::WildcatColor2(background) => {
+                self.parse_state = ParseState::Normal;
+                if ch.is_ascii_hexdigit() {
+                    self.parse_state = ParseState::WildcatEnd(background, ch as u8);
+                } else if self.start_at_macro(&[background], ch) {
+                    // An @NAME@ macro starting with a hex digit
+                } else {
+                    self.emit_char('@');
+                    self.emit_char(background as char);
+                    self.process_char(ch);
+                }
+            }
+            ParseState::WildcatEnd(background, foreground) => {
+                self.parse_state = ParseState::Normal;
+                if ch == '@' {
+                    self.has_encountered_ansi = true;
+                    self.switch_color(hex_digit(background), hex_digit(foreground));
+                } else if self.start_at_macro(&[background, foreground], ch) {
+                    // An @NAME@ macro starting with two hex digits
+                } else {
+                    // Missing closing at sign, emit the code so far and re-process
+                    self.emit_char('@');
+                    self.emit_char(background as char);
+                    self.emit_char(foreground as char);
+                    self.process_char(ch);
+                }
+            }
         }
     }
-}
 
-/// Convert a CP437 byte array with ANSI escape sequences to an HTML fragment.
-///
-/// This function uses default options (no BBS color code support).
-/// For BBS color code support, use [`convert_with_options`].
-///
-/// # Arguments
-/// * `input` - A byte slice representing CP437 encoded text with optional ANSI escape sequences
-///
-/// # Returns
-/// An HTML string wrapped in a `<pre class="ansi">` element with `<ans-KF>` custom elements
-/// for color styling.
-///
-/// # Example
-/// ```
-/// use ansi_to_html_rs::convert;
-///
-/// let input = b"Hello, World!";
-/// let html = convert(input);
-/// assert!(html.contains("<pre class=\"ansi\">"));
-/// assert!(html.contains("<ans-07>"));
-/// ```
-pub fn convert(input: &[u8]) -> String {
-    convert_with_options(input, &ConvertOptions::default())
-}
+    fn begin(&mut self) {
+        self.sink.begin(self.options.palette.as_ref());
+    }
 
-/// Convert a CP437 byte array with ANSI/BBS escape sequences to an HTML fragment.
-///
-/// # Arguments
-/// * `input` - A byte slice representing CP437 encoded text with optional escape sequences
-/// * `options` - Conversion options controlling which BBS color code formats to process
-///
-/// # Returns
-/// An HTML string wrapped in a `<pre class="ansi">` element with `<ans-KF>` custom elements
-/// for color styling.
-///
-/// # Example
-/// ```
-/// use ansi_to_html_rs::{convert_with_options, ConvertOptions};
-///
-/// let options = ConvertOptions {
-///     synchronet_ctrl_a: false,
-///     renegade_pipe: true,
-///     utf8_input: false,
-/// };
-/// let input = b"|04Red |02Green";
-/// let html = convert_with_options(input, &options);
-/// assert!(html.contains("<ans-04>")); // Red
-/// assert!(html.contains("<ans-02>")); // Green
-/// ```
-pub fn convert_with_options(input: &[u8], options: &ConvertOptions) -> String {
-    let mut converter = Converter::new(*options);
-    if options.utf8_input {
-        converter.convert_utf8(input)
-    } else {
-        converter.convert(input)
+    /// Pass the buffered screen, unfinished music and any RIPscrip graphics to the sink and
+    /// finish the output
+    fn end(&mut self) {
+        match self.parse_state {
+            ParseState::RipLine => self.finish_rip_line(),
+            ParseState::Music => self.finish_music(),
+            _ => {}
+        }
+        self.render_screen();
+        if !self.rip.is_empty() {
+            self.sink.graphics(&self.rip.to_svg());
+            self.rip.clear();
+        }
+        self.sink.end();
     }
-}
+
+    /// Run a full conversion of `input`.
+    ///
+    /// The sink is handed to `flush` whenever its buffered output grows past
+    /// `flush_threshold` bytes and once more at the end. `flush` is expected to drain it.
+    fn run<E>(
+        &mut self,
+        input: &[u8],
+        flush_threshold: usize,
+        flush: &mut dyn FnMut(&mut S) -> Result<(), E>,
+    ) -> Result<(), E> {
+        self.begin();
+
+        // Find SUB marker and SAUCE positions (work on raw bytes)
+        let sub_pos = input.iter().position(|&b| b == 0x1A);
+        let (sauce_pos, comnt_pos, after_sauce_pos) = find_sauce_positions(input);
+
+        // Determine content end position
+        let content_end = sub_pos
+            .or(comnt_pos)
+            .or(sauce_pos)
+            .unwrap_or(input.len());
+
+        let sauce = sauce_pos.and_then(|sauce_start| {
+            let comnt_data = comnt_pos.map(|cp| &input[cp..sauce_start]);
+            SauceRecord::parse(&input[sauce_start..], comnt_data)
+        });
+
+        // Without an explicit width, use the one declared by SAUCE
+        if self.options.columns.is_none()
+            && let Some(width) = sauce.as_ref().and_then(SauceRecord::character_width)
+        {
+            self.set_columns(width);
+        }
+
+        // Let SAUCE decide between iCE colors and blink
+        if self.options.blink_mode == BlinkMode::Auto
+            && let Some(ice_colors) = sauce.as_ref().and_then(SauceRecord::ice_colors)
+        {
+            self.blink_mode = if ice_colors {
+                BlinkMode::IceColors
+            } else {
+                BlinkMode::Blink
+            };
+        }
+
+        // Process content before SUB/SAUCE
+        self.process_input(&input[..content_end], flush_threshold, flush)?;
+
+        // If SAUCE record exists, parse and output it
+        if let Some(after_pos) = after_sauce_pos {
+            self.process_sauce(input, sauce.as_ref(), after_pos, flush_threshold, flush)?;
+        }
+
+        self.end();
+        flush(&mut self.sink)
+    }
+
+    /// Output the SAUCE metadata, followed by any content in `input` after the SAUCE
+    /// record ending at `after_pos`
+    fn process_sauce<E>(
+        &mut self,
+        input: &[u8],
+        sauce: Option<&SauceRecord>,
+        after_pos: usize,
+        flush_threshold: usize,
+        flush: &mut dyn FnMut(&mut S) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if let Some(sauce) = sauce {
+            let sauce_output = sauce.format_output();
+            if !sauce_output.is_empty() {
+                // Add newline before SAUCE metadata
+                self.begin_trailer_line();
+                for ch in sauce_output.chars() {
+                    self.emit_char(ch);
+                }
+            }
+        }
+
+        // Check for content after SAUCE record
+        if after_pos < input.len() {
+            let remaining = &input[after_pos..];
+            if !remaining.is_empty() && remaining.iter().any(|&b| b != 0 && b != 0x1A) {
+                // Add newline separator before continuing content
+                self.begin_trailer_line();
+                // Another SUB ends the content (nested SAUCE is not handled)
+                let remaining_end = remaining
+                    .iter()
+                    .position(|&b| b == 0x1A)
+                    .unwrap_or(remaining.len());
+                self.process_input(&remaining[..remaining_end], flush_threshold, flush)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Render a decoded character/attribute image, followed by its SAUCE metadata
+    fn run_image(&mut self, image: &TextImage) {
+        if self.options.blink_mode == BlinkMode::Auto {
+            self.blink_mode = if image.ice_colors {
+                BlinkMode::IceColors
+            } else {
+                BlinkMode::Blink
+            };
+        }
+        // Rows wrap at the image width, whatever the options say
+        self.screen = Some(ScreenBuffer::new(usize::from(image.width)));
+        self.begin();
+
+        for cell in &image.cells {
+            self.set_colors(cell.foreground, cell.background);
+            // NUL is the usual blank cell in binary art; glyphs past 255 come from a
+            // second font bank and are shown with their CP437 counterpart
+            let ch = match cell.ch & 0xFF {
+                0 => ' ',
+                byte => CP437_TO_UNICODE[usize::from(byte)],
+            };
+            self.emit_char(ch);
+        }
+        // Metadata lines are not wrapped at the image width
+        self.render_screen();
+
+        if let Some(sauce) = &image.sauce {
+            let sauce_output = sauce.format_output();
+            if !sauce_output.is_empty() {
+                self.switch_color(0, 7);
+                self.begin_trailer_line();
+                for ch in sauce_output.chars() {
+                    self.emit_char(ch);
+                }
+            }
+        }
+
+        self.end();
+    }
+
+    /// Process a run of content bytes as CP437 or UTF-8, depending on the options
+    fn process_input<E>(
+        &mut self,
+        input: &[u8],
+        flush_threshold: usize,
+        flush: &mut dyn FnMut(&mut S) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if self.options.utf8_input {
+            for ch in String::from_utf8_lossy(input).chars() {
+                self.process_char(ch);
+                if self.sink.buffered_len() >= flush_threshold {
+                    flush(&mut self.sink)?;
+                }
+            }
+        } else {
+            for &byte in input {
+                self.process_char(char::from(byte));
+                if self.sink.buffered_len() >= flush_threshold {
+                    flush(&mut self.sink)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Convert a CP437 byte array with ANSI escape sequences to an HTML fragment.
+///
+/// This function uses default options (no BBS color code support).
+/// For BBS color code support, use [`convert_with_options`].
+///
+/// # Arguments
+/// * `input` - A byte slice representing CP437 encoded text with optional ANSI escape sequences
+///
+/// # Returns
+/// An HTML string wrapped in a `<pre class="ansi">` element with `<ans-KF>` custom elements
+/// for color styling.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::convert;
+///
+/// let input = b"Hello, World!";
+/// let html = convert(input);
+/// assert!(html.contains("<pre class=\"ansi\">"));
+/// assert!(html.contains("<ans-07>"));
+/// ```
+pub fn convert(input: &[u8]) -> String {
+    convert_with_options(input, &ConvertOptions::default())
+}
+
+/// Convert a CP437 byte array with ANSI/BBS escape sequences to an HTML fragment.
+///
+/// # Arguments
+/// * `input` - A byte slice representing CP437 encoded text with optional escape sequences
+/// * `options` - Conversion options controlling which BBS color code formats to process
+///
+/// # Returns
+/// An HTML string wrapped in a `<pre class="ansi">` element with `<ans-KF>` custom elements
+/// for color styling.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::{convert_with_options, ConvertOptions};
+///
+/// let options = ConvertOptions {
+///     synchronet_ctrl_a: false,
+///     renegade_pipe: true,
+///     ..Default::default()
+/// };
+/// let input = b"|04Red |02Green";
+/// let html = convert_with_options(input, &options);
+/// assert!(html.contains("<ans-04>")); // Red
+/// assert!(html.contains("<ans-02>")); // Green
+/// ```
+pub fn convert_with_options(input: &[u8], options: &ConvertOptions) -> String {
+    let mut output = String::new();
+    convert_into(input, options, &mut output);
+    output
+}
+
+/// Convert a CP437 byte array with ANSI/BBS escape sequences, appending the HTML to `output`.
+///
+/// The HTML is appended after any existing content, so a buffer can be cleared and reused
+/// across many conversions without reallocating.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::{convert_into, ConvertOptions};
+///
+/// let mut html = String::new();
+/// for file in [&b"First"[..], &b"Second"[..]] {
+///     html.clear();
+///     convert_into(file, &ConvertOptions::default(), &mut html);
+///     assert!(html.starts_with("<pre class=\"ansi\">"));
+/// }
+/// ```
+pub fn convert_into(input: &[u8], options: &ConvertOptions, output: &mut String) {
+    let mut converter = Converter::new(
+        options.clone(),
+        HtmlWriter::new(std::mem::take(output), options.music_elements),
+    );
+    // Never flushes before the end, so the whole result stays in the caller's buffer
+    let result: Result<(), Infallible> = converter.run(input, usize::MAX, &mut |_| Ok(()));
+    let Ok(()) = result;
+    *output = converter.sink.output;
+}
+
+/// Parse a CP437 byte array with ANSI/BBS escape sequences into a [`Document`] of styled
+/// spans instead of HTML.
+///
+/// The document holds the same content as the HTML output (including SAUCE metadata
+/// lines, and [`Anchor`]s placing music, RIPscrip screens and Sixel images in the text),
+/// so it can be used to build other renderers. [`Document::to_html`] produces the same
+/// markup as [`convert_with_options`].
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::{convert_to_document, ConvertOptions, ExtendedColor};
+///
+/// let doc = convert_to_document(b"\x1b[31mRed\nNext line", &ConvertOptions::default());
+/// assert_eq!(doc.lines.len(), 2);
+/// assert_eq!(doc.lines[0].spans[0].text, "Red");
+/// assert_eq!(doc.lines[0].spans[0].style.foreground, ExtendedColor::Cga(4));
+/// assert_eq!(doc.lines[1].text(), "Next line");
+/// ```
+pub fn convert_to_document(input: &[u8], options: &ConvertOptions) -> Document {
+    let mut converter = Converter::new(options.clone(), DocumentBuilder::default());
+    let result: Result<(), Infallible> = converter.run(input, usize::MAX, &mut |_| Ok(()));
+    let Ok(()) = result;
+    converter.sink.finish()
+}
+
+/// Convert a CP437 byte array with ANSI/BBS escape sequences, streaming the HTML into an
+/// [`io::Write`] sink such as a file or socket.
+///
+/// Output is written in blocks as conversion progresses instead of being collected into
+/// a single string first.
+///
+/// # Errors
+/// Returns the first error reported by `writer`.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::{convert_to_writer, ConvertOptions};
+///
+/// let mut file = Vec::new();
+/// convert_to_writer(b"\x1b[31mRed", &ConvertOptions::default(), &mut file).unwrap();
+/// assert!(String::from_utf8(file).unwrap().contains("<ans-04>Red</ans-04>"));
+/// ```
+pub fn convert_to_writer<W: io::Write>(
+    input: &[u8],
+    options: &ConvertOptions,
+    mut writer: W,
+) -> io::Result<()> {
+    let mut converter = Converter::new(
+        options.clone(),
+        HtmlWriter::new(String::new(), options.music_elements),
+    );
+    converter.run(
+        input,
+        WRITER_FLUSH_THRESHOLD,
+        &mut |html: &mut HtmlWriter| {
+            writer.write_all(html.output.as_bytes())?;
+            html.output.clear();
+            Ok(())
+        },
+    )
+}
+
+/// Convert a CP437 byte array with ANSI/BBS escape sequences, streaming the HTML into a
+/// [`fmt::Write`] sink such as a `Formatter` or a template buffer.
+///
+/// # Errors
+/// Returns [`fmt::Error`] if `writer` fails.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::{convert_to_fmt_writer, ConvertOptions};
+///
+/// let mut page = String::from("<body>");
+/// convert_to_fmt_writer(b"Hello", &ConvertOptions::default(), &mut page).unwrap();
+/// assert!(page.starts_with("<body><pre class=\"ansi\">"));
+/// ```
+pub fn convert_to_fmt_writer<W: fmt::Write>(
+    input: &[u8],
+    options: &ConvertOptions,
+    mut writer: W,
+) -> fmt::Result {
+    let mut converter = Converter::new(
+        options.clone(),
+        HtmlWriter::new(String::new(), options.music_elements),
+    );
+    converter.run(
+        input,
+        WRITER_FLUSH_THRESHOLD,
+        &mut |html: &mut HtmlWriter| {
+            writer.write_str(&html.output)?;
+            html.output.clear();
+            Ok(())
+        },
+    )
+}
+
+/// Incremental converter for input that arrives in chunks, such as a live BBS session.
+///
+/// Parser state (partial escape sequences, pending BBS color codes and UTF-8 sequences
+/// split across chunks) is kept between calls to [`push`](StreamConverter::push), and
+/// each call returns the HTML produced so far. Concatenating every fragment with the
+/// result of [`finish`](StreamConverter::finish) yields a complete
+/// `<pre class="ansi">` element.
+///
+/// As with [`convert`], the content ends at the first Ctrl-Z (SUB). Everything after it is
+/// held back until `finish`, which writes the SAUCE metadata and any content following
+/// the record. Output returned before the SAUCE record arrived cannot follow its width
+/// or iCE colors flag, and only the last 1 MiB after the Ctrl-Z is kept. When
+/// [`ConvertOptions::screen_buffer`] is enabled, the screen can only be serialized once
+/// the stream ends, so all output is returned by `finish`.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::{ConvertOptions, StreamConverter};
+///
+/// let mut stream = StreamConverter::new(ConvertOptions::default());
+/// let mut html = stream.push(b"\x1b[3");
+/// html.push_str(&stream.push(b"1mRed"));
+/// html.push_str(&stream.finish());
+/// assert!(html.contains("<ans-04>Red</ans-04>"));
+/// ```
+pub struct StreamConverter {
+    converter: Converter<HtmlWriter>,
+    /// Bytes of an incomplete UTF-8 sequence carried over from the previous chunk
+    utf8_pending: Vec<u8>,
+    /// Input from the first Ctrl-Z on, where the SAUCE record is expected
+    trailer: Option<Vec<u8>>,
+}
+
+/// Number of bytes after the Ctrl-Z kept by [`StreamConverter`]
+const MAX_STREAM_TRAILER_LEN: usize = 1 << 20;
+
+impl StreamConverter {
+    /// Create a stream converter with the given options.
+    pub fn new(options: ConvertOptions) -> Self {
+        let music_elements = options.music_elements;
+        let mut converter = Converter::new(options, HtmlWriter::new(String::new(), music_elements));
+        converter.begin();
+        Self {
+            converter,
+            utf8_pending: Vec::new(),
+            trailer: None,
+        }
+    }
+
+    /// Process a chunk of input and return the HTML fragment it produced.
+    pub fn push(&mut self, chunk: &[u8]) -> String {
+        if let Some(trailer) = &mut self.trailer {
+            trailer.extend_from_slice(chunk);
+            // Trim in large steps so a long trailer is not shifted on every chunk
+            if trailer.len() >= 2 * MAX_STREAM_TRAILER_LEN {
+                trailer.drain(..trailer.len() - MAX_STREAM_TRAILER_LEN);
+            }
+            return String::new();
+        }
+
+        let (content, trailer) = match chunk.iter().position(|&b| b == 0x1A) {
+            Some(sub_pos) => chunk.split_at(sub_pos),
+            None => (chunk, &[][..]),
+        };
+        if self.converter.options.utf8_input {
+            self.push_utf8(content);
+        } else {
+            for &byte in content {
+                self.converter.process_char(char::from(byte));
+            }
+        }
+        if !trailer.is_empty() {
+            self.end_content();
+            self.trailer = Some(trailer.to_vec());
+        }
+        std::mem::take(&mut self.converter.sink.output)
+    }
+
+    /// Close any open tags and return the final HTML fragment.
+    ///
+    /// An incomplete UTF-8 sequence left at the end of the content is rendered as
+    /// U+FFFD, matching [`String::from_utf8_lossy`].
+    pub fn finish(mut self) -> String {
+        match self.trailer.take() {
+            Some(trailer) => {
+                let (sauce_pos, comnt_pos, after_sauce_pos) = find_sauce_positions(&trailer);
+                if let (Some(sauce_start), Some(after_pos)) = (sauce_pos, after_sauce_pos) {
+                    let comnt_data = comnt_pos.map(|cp| &trailer[cp..sauce_start]);
+                    let sauce = SauceRecord::parse(&trailer[sauce_start..], comnt_data);
+                    let result: Result<(), Infallible> = self.converter.process_sauce(
+                        &trailer,
+                        sauce.as_ref(),
+                        after_pos,
+                        usize::MAX,
+                        &mut |_| Ok(()),
+                    );
+                    let Ok(()) = result;
+                }
+            }
+            None => self.end_content(),
+        }
+        self.converter.end();
+        std::mem::take(&mut self.converter.sink.output)
+    }
+
+    /// Render an incomplete UTF-8 sequence left at the end of the content
+    fn end_content(&mut self) {
+        if !std::mem::take(&mut self.utf8_pending).is_empty() {
+            self.converter.process_char(char::REPLACEMENT_CHARACTER);
+        }
+    }
+
+    fn push_utf8(&mut self, chunk: &[u8]) {
+        let mut data = std::mem::take(&mut self.utf8_pending);
+        data.extend_from_slice(chunk);
+
+        let mut rest = data.as_slice();
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    for ch in text.chars() {
+                        self.converter.process_char(ch);
+                    }
+                    break;
+                }
+                Err(err) => {
+                    let (valid, invalid) = rest.split_at(err.valid_up_to());
+                    // The prefix up to `valid_up_to` is valid, so this never replaces anything
+                    for ch in String::from_utf8_lossy(valid).chars() {
+                        self.converter.process_char(ch);
+                    }
+                    match err.error_len() {
+                        Some(len) => {
+                            // Invalid sequence, replace it and keep decoding
+                            self.converter.process_char(char::REPLACEMENT_CHARACTER);
+                            rest = &invalid[len..];
+                        }
+                        None => {
+                            // Sequence continues in the next chunk
+                            self.utf8_pending = invalid.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
 
 /// Generate CSS for the ans-KF web components.
 ///
-/// This returns CSS custom property definitions for all 256 color combinations.
+/// This returns CSS custom property definitions for all 256 color combinations, and
+/// the rules for text attributes (`blink`, `underline`, `italic`, ...) on the elements.
+/// Colors come from the default [`Palette::VGA`].
 pub fn generate_css() -> String {
+    generate_css_with_palette(&Palette::default())
+}
+
+/// Generate CSS for the ans-KF web components using the given palette.
+///
+/// The sixteen palette colors are declared as `--ans-color-0` through `--ans-color-15`
+/// on `:root`, and every `<ans-KF>` rule refers to them, so output converted with
+/// [`ConvertOptions::palette`] can override them for its own `<pre>` element.
+pub fn generate_css_with_palette(palette: &Palette) -> String {
     let mut css = String::from(
         r#":root {
   --ans-font-family: "IBM VGA 8x16", "Perfect DOS VGA 437", "Px437 IBM VGA8", monospace;
   --ans-font-size: 16px;
   --ans-line-height: 1;
-}
+  --ans-blink-duration: 1s;
+"#,
+    );
+    for (index, &color) in palette.colors.iter().enumerate() {
+        css.push_str(&format!(
+            "  --ans-color-{}: {};\n",
+            index,
+            palette::hex(color)
+        ));
+    }
+    css.push_str(
+        r#"}
 
 pre.ansi {
   font-family: var(--ans-font-family);
   font-size: var(--ans-font-size);
   line-height: var(--ans-line-height);
-  background-color: #000000;
+  background-color: var(--ans-color-0);
   padding: 0;
   margin: 0;
   white-space: pre;
 }
 
+pre.ansi [blink] {
+  animation: ans-blink var(--ans-blink-duration) step-end infinite;
+}
+
+@keyframes ans-blink {
+  50% { color: transparent; }
+}
+
+pre.ansi [faint] { opacity: 0.6; }
+pre.ansi [italic] { font-style: italic; }
+pre.ansi [underline] { --ans-underline: underline; }
+pre.ansi [double-underline] { --ans-underline: underline; text-decoration-style: double; }
+pre.ansi [strike] { --ans-strike: line-through; }
+pre.ansi [overline] { --ans-overline: overline; }
+pre.ansi [underline], pre.ansi [double-underline], pre.ansi [strike], pre.ansi [overline] {
+  text-decoration-line: var(--ans-underline,) var(--ans-strike,) var(--ans-overline,);
+}
+pre.ansi [conceal] { color: transparent !important; }
+
 "#,
     );
 
-    // Generate styles for each color combination
-    for bg in 0..16u8 {
-        for fg in 0..16u8 {
-            let bg_hex = Converter::color_to_hex(bg);
-            let fg_hex = Converter::color_to_hex(fg);
-            css.push_str(&format!(
-                "ans-{}{} {{ background-color: {}; color: {}; }}\n",
-                bg_hex, fg_hex, CGA_COLORS[bg as usize], CGA_COLORS[fg as usize]
-            ));
-        }
+    // Generate styles for each color combination
+    for bg in 0..16u8 {
+        for fg in 0..16u8 {
+            let bg_hex = html::color_to_hex(bg);
+            let fg_hex = html::color_to_hex(fg);
+            css.push_str(&format!(
+                "ans-{}{} {{ background-color: var(--ans-color-{}); color: var(--ans-color-{}); }}\n",
+                bg_hex, fg_hex, bg, fg
+            ));
+        }
+    }
+
+    css
+}
+
+/// Generate JavaScript for defining ans-KF web components.
+///
+/// This returns JavaScript code that defines custom elements for all 256 color combinations
+/// and the `<ans-256>` / `<ans-rgb>` elements, and installs the rules for text attributes
+/// (`blink`, `underline`, `italic`, ...). Colors come from the default [`Palette::VGA`].
+pub fn generate_js() -> String {
+    generate_js_with_palette(&Palette::default())
+}
+
+/// Generate JavaScript for defining ans-KF web components using the given palette.
+///
+/// CGA colors resolve through the `--ans-color-N` custom properties first, falling back
+/// to `palette`, so scoped palettes from [`ConvertOptions::palette`] are honored. The
+/// palette's 256-color table (if any) is used for `<ans-256>` elements.
+pub fn generate_js_with_palette(palette: &Palette) -> String {
+    let colors = palette
+        .colors
+        .chunks(4)
+        .map(|row| {
+            let row: Vec<String> = row
+                .iter()
+                .map(|&c| format!("\"{}\"", palette::hex(c)))
+                .collect();
+            format!("    {}", row.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+    let extended = match &palette.extended {
+        Some(table) => {
+            let rows: Vec<String> = table
+                .chunks(8)
+                .map(|row| {
+                    let row: Vec<String> = row
+                        .iter()
+                        .map(|&c| format!("\"{}\"", palette::hex(c)))
+                        .collect();
+                    format!("    {}", row.join(", "))
+                })
+                .collect();
+            format!("[\n{}\n  ]", rows.join(",\n"))
+        }
+        None => String::from("null"),
+    };
+
+    String::from(
+        r##"// ANSI color web components
+(function() {
+  const colors = [
+__COLORS__
+  ];
+
+  // 256-color table, or null for the standard xterm layout
+  const extendedColors = __EXTENDED__;
+
+  const hexChars = "0123456789abcdef";
+
+  // CGA colors resolve through --ans-color-N so a <pre> can carry its own palette
+  function cgaColor(index) {
+    return `var(--ans-color-${index}, ${colors[index]})`;
+  }
+
+  // Text attributes: boolean attributes on any ans-* element, e.g. <ans-07 blink underline>
+  if (!document.getElementById("ans-attribute-style")) {
+    const style = document.createElement("style");
+    style.id = "ans-attribute-style";
+    style.textContent = [
+      "pre.ansi [blink] { animation: ans-blink var(--ans-blink-duration, 1s) step-end infinite; }",
+      "@keyframes ans-blink { 50% { color: transparent; } }",
+      "pre.ansi [faint] { opacity: 0.6; }",
+      "pre.ansi [italic] { font-style: italic; }",
+      "pre.ansi [underline] { --ans-underline: underline; }",
+      "pre.ansi [double-underline] { --ans-underline: underline; text-decoration-style: double; }",
+      "pre.ansi [strike] { --ans-strike: line-through; }",
+      "pre.ansi [overline] { --ans-overline: overline; }",
+      "pre.ansi [underline], pre.ansi [double-underline], pre.ansi [strike], pre.ansi [overline] {" +
+        " text-decoration-line: var(--ans-underline,) var(--ans-strike,) var(--ans-overline,); }",
+      "pre.ansi [conceal] { color: transparent !important; }"
+    ].join("\n");
+    document.head.appendChild(style);
+  }
+
+  // Standard 16-color CGA web components: <ans-KF>
+  for (let bg = 0; bg < 16; bg++) {
+    for (let fg = 0; fg < 16; fg++) {
+      const tagName = `ans-${hexChars[bg]}${hexChars[fg]}`;
+
+      if (!customElements.get(tagName)) {
+        const bgColor = cgaColor(bg);
+        const fgColor = cgaColor(fg);
+
+        class AnsElement extends HTMLElement {
+          constructor() {
+            super();
+          }
+
+          connectedCallback() {
+            this.style.backgroundColor = bgColor;
+            this.style.color = fgColor;
+            this.style.display = "inline";
+          }
+        }
+
+        customElements.define(tagName, AnsElement);
+      }
+    }
+  }
+
+  /**
+   * Parse a color attribute value.
+   * Supports:
+   * - "fg-#" or "bg-#" for CGA fallback (# is hex digit 0-f)
+   * - Numeric string for 256-color palette index
+   * - "R,G,B" for RGB values
+   *
+   * @param {string} value - The attribute value
+   * @param {boolean} isForeground - Whether this is a foreground color
+   * @returns {string} CSS color value
+   */
+  function parseColorAttribute(value, isForeground) {
+    const fallback = cgaColor(isForeground ? 7 : 0);
+    if (!value) {
+      // Default: light gray foreground, black background
+      return fallback;
+    }
+
+    // Check for CGA fallback: "fg-#" or "bg-#"
+    const cgaMatch = value.match(/^(fg|bg)-([0-9a-f])$/i);
+    if (cgaMatch) {
+      return cgaColor(parseInt(cgaMatch[2], 16));
+    }
+
+    // Check for RGB: "R,G,B"
+    const rgbMatch = value.match(/^(\d{1,3}),(\d{1,3}),(\d{1,3})$/);
+    if (rgbMatch) {
+      const r = Math.min(255, parseInt(rgbMatch[1], 10));
+      const g = Math.min(255, parseInt(rgbMatch[2], 10));
+      const b = Math.min(255, parseInt(rgbMatch[3], 10));
+      return `rgb(${r},${g},${b})`;
+    }
+
+    // Check for 256-color palette index
+    const paletteIndex = parseInt(value, 10);
+    if (!isNaN(paletteIndex) && paletteIndex >= 0 && paletteIndex <= 255) {
+      return palette256ToRgb(paletteIndex);
+    }
+
+    // Fallback to default
+    return fallback;
+  }
+
+  /**
+   * Convert a 256-color palette index to a CSS color value.
+   * A --ans-256-N property (declared on a <pre> with a custom 256-color table) takes
+   * precedence over the page-wide table.
+   * @param {number} index - Palette index (0-255)
+   * @returns {string} CSS color value
+   */
+  function palette256ToRgb(index) {
+    return `var(--ans-256-${index}, ${defaultPalette256(index)})`;
+  }
+
+  function defaultPalette256(index) {
+    if (extendedColors) {
+      return extendedColors[index];
+    }
+
+    // Colors 0-15: Standard CGA colors
+    if (index < 16) {
+      return cgaColor(index);
+    }
+
+    // Colors 16-231: 6x6x6 color cube
+    if (index < 232) {
+      const cubeIndex = index - 16;
+      const r = Math.floor(cubeIndex / 36);
+      const g = Math.floor((cubeIndex % 36) / 6);
+      const b = cubeIndex % 6;
+      // Each component: 0, 95, 135, 175, 215, 255
+      const toValue = (c) => c === 0 ? 0 : 55 + c * 40;
+      return `rgb(${toValue(r)},${toValue(g)},${toValue(b)})`;
+    }
+
+    // Colors 232-255: Grayscale ramp
+    const gray = (index - 232) * 10 + 8;
+    return `rgb(${gray},${gray},${gray})`;
+  }
+
+  // Extended color web components: <ans-256 fg="N" bg="N"> and <ans-rgb fg="R,G,B" bg="R,G,B">
+  for (const tagName of ["ans-256", "ans-rgb"]) {
+    if (!customElements.get(tagName)) {
+      class AnsExtendedElement extends HTMLElement {
+        constructor() {
+          super();
+        }
+
+        connectedCallback() {
+          this.style.color = parseColorAttribute(this.getAttribute("fg"), true);
+          this.style.backgroundColor = parseColorAttribute(this.getAttribute("bg"), false);
+          this.style.display = "inline";
+        }
+
+        static get observedAttributes() {
+          return ["fg", "bg"];
+        }
+
+        attributeChangedCallback(name, oldValue, newValue) {
+          if (name === "fg") {
+            this.style.color = parseColorAttribute(newValue, true);
+          } else if (name === "bg") {
+            this.style.backgroundColor = parseColorAttribute(newValue, false);
+          }
+        }
+      }
+
+      customElements.define(tagName, AnsExtendedElement);
+    }
+  }
+
+  // ANSI music: <ans-music notes="frequency,duration,sounding ..."> (Hz, ms), played as a
+  // PC speaker square wave when clicked. Positioned out of flow so the art keeps its layout.
+  if (!customElements.get("ans-music")) {
+    let audioContext = null;
+
+    class AnsMusicElement extends HTMLElement {
+      constructor() {
+        super();
+      }
+
+      connectedCallback() {
+        this.style.position = "absolute";
+        this.style.cursor = "pointer";
+        this.title = "Play music";
+        this.textContent = "\u266a";
+        this.addEventListener("click", () => this.play());
+      }
+
+      play() {
+        audioContext = audioContext || new AudioContext();
+        let time = audioContext.currentTime;
+        for (const note of (this.getAttribute("notes") || "").split(" ")) {
+          const [frequency, duration, sounding] = note.split(",").map(Number);
+          if (frequency > 0 && sounding > 0) {
+            const oscillator = audioContext.createOscillator();
+            const gain = audioContext.createGain();
+            oscillator.type = "square";
+            oscillator.frequency.value = frequency;
+            gain.gain.value = 0.1;
+            oscillator.connect(gain).connect(audioContext.destination);
+            oscillator.start(time);
+            oscillator.stop(time + sounding / 1000);
+          }
+          time += (duration || 0) / 1000;
+        }
+      }
+    }
+
+    customElements.define("ans-music", AnsMusicElement);
+  }
+})();
+"##,
+    )
+    .replace("__COLORS__", &colors)
+    .replace("__EXTENDED__", &extended)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic_text() {
+        let result = convert(b"Hello");
+        assert!(result.contains("<pre class=\"ansi\">"));
+        assert!(result.contains("<ans-07>"));
+        assert!(result.contains("Hello"));
+        assert!(result.contains("</ans-07>"));
+        assert!(result.contains("</pre>"));
+    }
+
+    #[test]
+    fn test_html_escaping() {
+        let result = convert(b"<script>&</script>");
+        assert!(result.contains("&lt;script&gt;&amp;&lt;/script&gt;"));
+        // Test double quote
+        let result = convert(b"\"quoted\"");
+        assert!(result.contains("&quot;quoted&quot;"));
+        // Test apostrophe
+        let result = convert(b"it's here");
+        assert!(result.contains("it&apos;s here"));
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&apos;s&lt;/a&gt;"
+        );
+        assert_eq!(escape_html("plain.ans"), "plain.ans");
+    }
+
+    #[test]
+    fn test_color_change() {
+        // ESC[31m sets red foreground
+        let input = b"\x1b[31mRed";
+        let result = convert(input);
+        assert!(result.contains("<ans-04>")); // Red foreground on black
+    }
+
+    #[test]
+    fn test_clear_screen() {
+        // ESC[2J clears screen
+        let input = b"Before\x1b[2JAfter";
+        let result = convert(input);
+        // Should have three newlines for clear screen
+        assert!(result.contains("\n\n\n"));
+    }
+
+    #[test]
+    fn test_newline_preserved() {
+        let result = convert(b"Line1\nLine2");
+        assert!(result.contains("Line1\nLine2"));
+    }
+
+    #[test]
+    fn test_carriage_return_suppressed() {
+        let result = convert(b"Line1\r\nLine2");
+        assert!(!result.contains('\r'));
+        assert!(result.contains("Line1\nLine2"));
+    }
+
+    #[test]
+    fn test_generate_css() {
+        let css = generate_css();
+        assert!(css.contains("ans-07"));
+        assert!(css.contains("#AAAAAA")); // Light gray
+    }
+
+    #[test]
+    fn test_generate_js() {
+        let js = generate_js();
+        assert!(js.contains("customElements.define"));
+    }
+
+    #[test]
+    fn test_soft_return_at_column_80() {
+        // Create a line with ANSI escape that's longer than 80 chars
+        let mut input = vec![0x1b, b'[', b'3', b'1', b'm']; // Red color
+        // Add 85 'X' characters - should trigger soft return after 80
+        for _ in 0..85 {
+            input.push(b'X');
+        }
+        let result = convert(&input);
+        // Should have a newline injected after column 80
+        let x_count_before_newline = result
+            .split('\n')
+            .find(|s| s.contains("XXXX"))
+            .map(|s| s.matches('X').count())
+            .unwrap_or(0);
+        assert_eq!(x_count_before_newline, 80);
+    }
+
+    #[test]
+    fn test_no_soft_return_without_ansi() {
+        // Without ANSI, no soft return should happen
+        let input: Vec<u8> = (0..85).map(|_| b'X').collect();
+        let result = convert(&input);
+        // Should NOT have a newline
+        assert!(!result.contains('\n'));
+    }
+
+    #[test]
+    fn test_save_restore_position_collapse() {
+        // ESC[s saves position, text should be collapsed, ESC[u restores
+        let input = b"Before\x1b[sHidden\x1b[uAfter";
+        let result = convert(input);
+        assert!(result.contains("Before"));
+        assert!(result.contains("After"));
+        assert!(!result.contains("Hidden"));
+    }
+
+    #[test]
+    fn test_dec_save_restore_position() {
+        // \e7 saves position, \e8 restores
+        let input = b"Start\x1b7Collapsed\x1b8End";
+        let result = convert(input);
+        assert!(result.contains("Start"));
+        assert!(result.contains("End"));
+        assert!(!result.contains("Collapsed"));
+    }
+
+    #[test]
+    fn test_cp437_box_drawing() {
+        // Test box drawing characters (0xDA = top-left corner)
+        let input = [0xDA, 0xC4, 0xC4, 0xBF]; // ┌──┐
+        let result = convert(&input);
+        assert!(result.contains('┌'));
+        assert!(result.contains('─'));
+        assert!(result.contains('┐'));
+    }
+
+    #[test]
+    fn test_cp437_special_chars() {
+        // Test smiley faces and hearts
+        let input = [0x01, 0x02, 0x03]; // ☺☻♥
+        let result = convert(&input);
+        assert!(result.contains('☺'));
+        assert!(result.contains('☻'));
+        assert!(result.contains('♥'));
+    }
+
+    #[test]
+    fn test_bright_foreground_colors() {
+        // ESC[91m = bright red
+        let input = b"\x1b[91mBright Red";
+        let result = convert(input);
+        assert!(result.contains("<ans-0c>")); // Light Red on black
+    }
+
+    #[test]
+    fn test_bright_background_colors() {
+        // ESC[101m = bright red background
+        let input = b"\x1b[101mBright BG";
+        let result = convert(input);
+        assert!(result.contains("<ans-c7>")); // Light Red bg, Light Gray fg
+    }
+
+    #[test]
+    fn test_bold_makes_bright() {
+        // ESC[1m makes foreground bright, ESC[34m blue -> light blue
+        let input = b"\x1b[1;34mBold Blue";
+        let result = convert(input);
+        assert!(result.contains("<ans-09>")); // Light Blue (9) on black
+    }
+
+    #[test]
+    fn test_reset_colors() {
+        // ESC[31m red, then ESC[0m reset
+        let input = b"\x1b[31mRed\x1b[0mNormal";
+        let result = convert(input);
+        assert!(result.contains("<ans-04>Red</ans-04>"));
+        assert!(result.contains("<ans-07>Normal"));
+    }
+
+    #[test]
+    fn test_multiple_sgr_params() {
+        // ESC[1;31;44m = bold red on blue
+        let input = b"\x1b[1;31;44mStyled";
+        let result = convert(input);
+        assert!(result.contains("<ans-1c>")); // Blue bg (1), Light Red fg (C)
+    }
+
+    #[test]
+    fn test_full_block_character() {
+        // 0xDB = full block
+        let input = [0xDB];
+        let result = convert(&input);
+        assert!(result.contains('█'));
+    }
+
+    #[test]
+    fn test_shade_characters() {
+        // Test shade blocks
+        let input = [0xB0, 0xB1, 0xB2]; // ░▒▓
+        let result = convert(&input);
+        assert!(result.contains('░'));
+        assert!(result.contains('▒'));
+        assert!(result.contains('▓'));
+    }
+
+    #[test]
+    fn test_cursor_forward_default() {
+        // ESC[C moves cursor forward 1 position (emits 1 space)
+        let input = b"A\x1b[CB";
+        let result = convert(input);
+        assert!(result.contains("A B"));
+    }
+
+    #[test]
+    fn test_cursor_forward_explicit_one() {
+        // ESC[1C moves cursor forward 1 position
+        let input = b"A\x1b[1CB";
+        let result = convert(input);
+        assert!(result.contains("A B"));
+    }
+
+    #[test]
+    fn test_cursor_forward_multiple() {
+        // ESC[5C moves cursor forward 5 positions (emits 5 spaces)
+        let input = b"A\x1b[5CB";
+        let result = convert(input);
+        assert!(result.contains("A     B"));
+    }
+
+    #[test]
+    fn test_cursor_forward_large() {
+        // ESC[10C moves cursor forward 10 positions
+        let input = b"X\x1b[10CY";
+        let result = convert(input);
+        assert!(result.contains("X          Y"));
+    }
+
+    #[test]
+    fn test_cursor_forward_zero_treated_as_one() {
+        // ESC[0C should be treated as ESC[1C per ANSI spec
+        let input = b"A\x1b[0CB";
+        let result = convert(input);
+        assert!(result.contains("A B"));
+    }
+
+    // ========== Synchronet Ctrl-A tests ==========
+
+    #[test]
+    fn test_synchronet_foreground_colors() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            ..Default::default()
+        };
+        // Ctrl-A + r (lowercase) = red foreground
+        let input = b"\x01rRed Text";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-04>")); // Red on black
+    }
+
+    #[test]
+    fn test_synchronet_background_color_uppercase() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            ..Default::default()
+        };
+        // Ctrl-A + R (uppercase) = red background
+        let input = b"\x01RRed BG";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-47>")); // Red bg (4), Light Gray fg (7)
+    }
+
+    #[test]
+    fn test_synchronet_background_color_digit() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            ..Default::default()
+        };
+        // Ctrl-A + 1 = blue background
+        let input = b"\x011Blue BG";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-17>")); // Blue bg, Light Gray fg
+    }
+
+    #[test]
+    fn test_synchronet_high_intensity_foreground() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            ..Default::default()
+        };
+        // Ctrl-A + b (blue fg) + Ctrl-A + h (high intensity) = bright blue
+        let input = b"\x01b\x01hBright Blue";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-09>")); // Light Blue on black
+    }
+
+    #[test]
+    fn test_synchronet_high_intensity_background() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            ..Default::default()
+        };
+        // Ctrl-A + B (blue bg) + Ctrl-A + i (blink/high intensity bg) = bright blue bg
+        let input = b"\x01B\x01iBright Blue BG";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-97>")); // Light Blue bg (9), Light Gray fg (7)
+    }
+
+    #[test]
+    fn test_synchronet_normal_reset() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            ..Default::default()
+        };
+        // Ctrl-A + r (red fg) then Ctrl-A + n = reset to normal
+        let input = b"\x01rRed\x01nNormal";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-04>Red</ans-04>"));
+        assert!(result.contains("<ans-07>Normal"));
+    }
+
+    #[test]
+    fn test_synchronet_disabled_by_default() {
+        // Without option, Ctrl-A should be treated as CP437 character (smiley)
+        let input = b"\x01rText";
+        let result = convert(input);
+        assert!(result.contains('☺')); // CP437 0x01 = smiley face
+        assert!(result.contains("rText"));
+    }
+
+    #[test]
+    fn test_synchronet_preserves_intensity() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            ..Default::default()
+        };
+        // Set high intensity first, then change color - intensity should be preserved
+        let input = b"\x01h\x01bBright Blue";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-09>")); // Light Blue (high intensity preserved)
+    }
+
+    #[test]
+    fn test_synchronet_combined_fg_bg() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            ..Default::default()
+        };
+        // Ctrl-A + w (white fg) + Ctrl-A + B (blue bg)
+        let input = b"\x01w\x01BWhite on Blue";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-17>")); // Blue bg (1), Light Gray fg (7)
+    }
+
+    #[test]
+    fn test_synchronet_intensity_idempotent() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            ..Default::default()
+        };
+        // Applying high intensity multiple times should have same effect as once
+        let input = b"\x01b\x01h\x01hDouble High";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-09>")); // Light Blue (9), not something weird
+    }
+
+    #[test]
+    fn test_synchronet_blink_idempotent() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            ..Default::default()
+        };
+        // Applying blink/high bg multiple times should have same effect as once
+        let input = b"\x01B\x01i\x01iDouble Blink BG";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-97>")); // Light Blue bg (9), Light Gray fg (7)
+    }
+
+    // ========== Renegade pipe code tests ==========
+
+    #[test]
+    fn test_renegade_foreground_colors() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // |04 = red foreground
+        let input = b"|04Red Text";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-04>")); // Red on black
+    }
+
+    #[test]
+    fn test_renegade_bright_foreground() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // |12 = bright red (Light Red)
+        let input = b"|12Bright Red";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-0c>")); // Light Red on black
+    }
+
+    #[test]
+    fn test_renegade_background_color() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // |17 = blue background
+        let input = b"|17Blue BG";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-17>")); // Blue bg, Light Gray fg
+    }
+
+    #[test]
+    fn test_renegade_combined_colors() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // |15 = white fg, |20 = red bg
+        let input = b"|15|20White on Red";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-4f>")); // Red bg, White fg
+    }
+
+    #[test]
+    fn test_renegade_disabled_by_default() {
+        // Without option, pipe should be passed through
+        let input = b"|04Text";
+        let result = convert(input);
+        assert!(result.contains("|04Text"));
+    }
+
+    #[test]
+    fn test_renegade_invalid_code_passthrough() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // |99 is invalid (>23), should be ignored but not crash
+        let input = b"|99Text";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("Text"));
+    }
+
+    #[test]
+    fn test_renegade_incomplete_code_passthrough() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // |0X is not a valid code (X is not a digit)
+        let input = b"|0XText";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("|0XText"));
+    }
+
+    #[test]
+    fn test_renegade_pipe_literal() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // Single | followed by non-digit should be passed through
+        let input = b"|Hello";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("|Hello"));
+    }
+
+    // ========== Combined options tests ==========
+
+    #[test]
+    fn test_both_formats_enabled() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // Mix of both formats
+        let input = b"\x01rSync |09Renegade";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-04>")); // Red from Synchronet
+        assert!(result.contains("<ans-09>")); // Light Blue from Renegade
+    }
+
+    // ========== UTF-8 input mode tests ==========
+
+    #[test]
+    fn test_utf8_input_basic() {
+        let options = ConvertOptions {
+            utf8_input: true,
+            ..Default::default()
+        };
+        // UTF-8 text with Unicode characters should pass through
+        let input = "Hello, 世界!".as_bytes();
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("Hello, 世界!"));
+    }
+
+    #[test]
+    fn test_utf8_input_control_chars() {
+        let options = ConvertOptions {
+            utf8_input: true,
+            ..Default::default()
+        };
+        // Control char 0x01 (smiley in CP437) should still be converted
+        let input = b"\x01 Hello";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains('☺')); // CP437 0x01 = smiley
+        assert!(result.contains("Hello"));
+    }
+
+    #[test]
+    fn test_utf8_input_ansi_codes() {
+        let options = ConvertOptions {
+            utf8_input: true,
+            ..Default::default()
+        };
+        // ANSI codes should still work in UTF-8 mode
+        let input = "\x1b[31mRed 日本語\x1b[0m".as_bytes();
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-04>")); // Red
+        assert!(result.contains("日本語"));
+    }
+
+    #[test]
+    fn test_utf8_input_with_renegade() {
+        let options = ConvertOptions {
+            utf8_input: true,
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // Renegade codes with UTF-8 text
+        let input = "|04Red |02Grün".as_bytes();
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-04>")); // Red
+        assert!(result.contains("<ans-02>")); // Green
+        assert!(result.contains("Grün")); // German umlaut preserved
+    }
+
+    #[test]
+    fn test_utf8_input_matches_cp437_for_ascii() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            pcboard_at_x: true,
+            wildcat_at: true,
+            mystic_mci: true,
+            wwiv_heart: true,
+            avatar: true,
+            door_backtick: true,
+            ansi_music: true,
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let input = b"\x01r|12A|[X05B|CR@X1FC@0E@D\x033E\x16\x01\x1fF\x19G\x03`2H`r1I\x1b[MCDE\x0eJ\x1b[31mK";
+        let utf8_options = ConvertOptions {
+            utf8_input: true,
+            ..options.clone()
+        };
+        assert_eq!(
+            convert_with_options(input, &utf8_options),
+            convert_with_options(input, &options)
+        );
+    }
+
+    // ========== SAUCE metadata parsing tests ==========
+
+    #[test]
+    fn test_sub_without_sauce_stops_processing() {
+        // SUB without valid SAUCE record - content after SUB is ignored
+        let input = b"Visible\x1aRandom garbage after SUB";
+        let result = convert(input);
+        assert!(result.contains("Visible"));
+        assert!(!result.contains("Random"));
+        assert!(!result.contains("garbage"));
+    }
+
+    #[test]
+    fn test_sauce_record_parsed_and_displayed() {
+        // Create a minimal valid SAUCE record (128 bytes)
+        let mut input = b"Content before SAUCE\x1a".to_vec();
+        // SAUCE00 header
+        input.extend_from_slice(b"SAUCE00");
+        // Title (35 bytes) - "Test Title" padded with spaces
+        input.extend_from_slice(b"Test Title                         ");
+        // Author (20 bytes)
+        input.extend_from_slice(b"Test Author         ");
+        // Group (20 bytes)
+        input.extend_from_slice(b"Test Group          ");
+        // Date (8 bytes) - CCYYMMDD
+        input.extend_from_slice(b"20240115");
+        // FileSize (4 bytes) - little endian
+        input.extend_from_slice(&[0, 0, 0, 0]);
+        // DataType (1 byte)
+        input.push(1);
+        // FileType (1 byte)
+        input.push(1);
+        // TInfo1-4 (8 bytes) - width=80, height=25
+        input.extend_from_slice(&[80, 0, 25, 0, 0, 0, 0, 0]);
+        // Comments (1 byte)
+        input.push(0);
+        // TFlags (1 byte)
+        input.push(0);
+        // TInfoS (22 bytes) - font name
+        input.extend_from_slice(b"IBM VGA\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+
+        let result = convert(&input);
+        assert!(result.contains("Content before SAUCE"));
+        assert!(result.contains("Title: Test Title"));
+        assert!(result.contains("Author: Test Author"));
+        assert!(result.contains("Group: Test Group"));
+        assert!(result.contains("Date: 2024-01-15"));
+        assert!(result.contains("Size: 80x25"));
+        assert!(result.contains("Font: IBM VGA"));
+    }
+
+    #[test]
+    fn test_sauce_with_comnt_block() {
+        // Create input with COMNT block before SAUCE
+        let mut input = b"Art content\x1a".to_vec();
+        // COMNT header + one 64-byte comment line
+        input.extend_from_slice(b"COMNT");
+        input.extend_from_slice(b"This is a comment line for the ANSI art.                       ");
+        // SAUCE00 header
+        input.extend_from_slice(b"SAUCE00");
+        // Title (35 bytes)
+        input.extend_from_slice(b"Artwork Title                      ");
+        // Author (20 bytes)
+        input.extend_from_slice(b"Artist              ");
+        // Group (20 bytes)
+        input.extend_from_slice(b"                    ");
+        // Date (8 bytes)
+        input.extend_from_slice(b"20230701");
+        // FileSize (4 bytes)
+        input.extend_from_slice(&[0, 0, 0, 0]);
+        // DataType, FileType
+        input.extend_from_slice(&[1, 1]);
+        // TInfo1-4 (8 bytes)
+        input.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+        // Comments count (1 byte) - 1 comment
+        input.push(1);
+        // TFlags (1 byte)
+        input.push(0);
+        // TInfoS (22 bytes)
+        input.extend_from_slice(b"\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+
+        let result = convert(&input);
+        assert!(result.contains("Art content"));
+        assert!(result.contains("Title: Artwork Title"));
+        assert!(result.contains("Author: Artist"));
+        assert!(result.contains("Comment: This is a comment line for the ANSI art."));
+    }
+
+    #[test]
+    fn test_content_after_sauce_continues() {
+        // Create input with content after SAUCE record
+        let mut input = b"Before SAUCE\x1a".to_vec();
+        // Minimal SAUCE record (128 bytes)
+        input.extend_from_slice(b"SAUCE00");
+        input.extend_from_slice(b"Title                              "); // 35
+        input.extend_from_slice(b"                    "); // 20 author
+        input.extend_from_slice(b"                    "); // 20 group
+        input.extend_from_slice(b"        "); // 8 date
+        input.extend_from_slice(&[0u8; 4]); // filesize
+        input.extend_from_slice(&[0, 0]); // datatype, filetype
+        input.extend_from_slice(&[0u8; 8]); // tinfo1-4
+        input.push(0); // comments
+        input.push(0); // tflags
+        input.extend_from_slice(&[0u8; 22]); // tinfos
+        // Content after SAUCE
+        input.extend_from_slice(b"Content after SAUCE record");
+
+        let result = convert(&input);
+        assert!(result.contains("Before SAUCE"));
+        assert!(result.contains("Title: Title"));
+        assert!(result.contains("Content after SAUCE record"));
+    }
+
+    #[test]
+    fn test_sauce_utf8_mode() {
+        let options = ConvertOptions {
+            utf8_input: true,
+            ..Default::default()
+        };
+        // Create input with UTF-8 content and SAUCE
+        let mut input = b"Hello UTF-8 \xc3\xa9\x1a".to_vec(); // é in UTF-8
+        // Full SAUCE record (128 bytes total)
+        // SAUCE00 (7) + Title (35) + Author (20) + Group (20) + Date (8) +
+        // FileSize (4) + DataType (1) + FileType (1) + TInfo1-4 (8) +
+        // Comments (1) + TFlags (1) + TInfoS (22) = 128
+        input.extend_from_slice(b"SAUCE00");                           // 7 bytes
+        input.extend_from_slice(b"UTF-8 Test                         "); // 35 bytes
+        input.extend_from_slice(&[b' '; 20]);                          // 20 bytes author
+        input.extend_from_slice(&[b' '; 20]);                          // 20 bytes group
+        input.extend_from_slice(b"        ");                          // 8 bytes date
+        input.extend_from_slice(&[0u8; 4]);                            // 4 bytes filesize
+        input.extend_from_slice(&[1, 1]);                              // 2 bytes datatype, filetype
+        input.extend_from_slice(&[0u8; 8]);                            // 8 bytes tinfo1-4
+        input.push(0);                                                 // 1 byte comments
+        input.push(0);                                                 // 1 byte tflags
+        input.extend_from_slice(&[0u8; 22]);                           // 22 bytes tinfos
+
+        let result = convert_with_options(&input, &options);
+        assert!(result.contains("Hello UTF-8 é"));
+        assert!(result.contains("Title: UTF-8 Test"));
+    }
+
+    // ========== 256-color and RGB support tests ==========
+
+    #[test]
+    fn test_256_color_foreground() {
+        // ESC[38;5;196m = 256-color foreground, color 196 (bright red in cube)
+        let input = b"\x1b[38;5;196mRed 256";
+        let result = convert(input);
+        assert!(result.contains("<ans-256 fg=\"196\" bg=\"bg-0\">"));
+        assert!(result.contains("Red 256"));
+        assert!(result.contains("</ans-256>"));
+    }
+
+    #[test]
+    fn test_256_color_background() {
+        // ESC[48;5;21m = 256-color background, color 21 (blue in cube)
+        let input = b"\x1b[48;5;21mBlue BG";
+        let result = convert(input);
+        assert!(result.contains("<ans-256 fg=\"fg-7\" bg=\"21\">"));
+        assert!(result.contains("Blue BG"));
+    }
+
+    #[test]
+    fn test_256_color_both() {
+        // ESC[38;5;226;48;5;21m = yellow fg (226) on blue bg (21)
+        let input = b"\x1b[38;5;226;48;5;21mYellow on Blue";
+        let result = convert(input);
+        assert!(result.contains("<ans-256 fg=\"226\" bg=\"21\">"));
+    }
+
+    #[test]
+    fn test_rgb_foreground() {
+        // ESC[38;2;255;128;0m = RGB foreground (orange)
+        let input = b"\x1b[38;2;255;128;0mOrange";
+        let result = convert(input);
+        assert!(result.contains("<ans-rgb fg=\"255,128,0\" bg=\"bg-0\">"));
+        assert!(result.contains("Orange"));
+        assert!(result.contains("</ans-rgb>"));
+    }
+
+    #[test]
+    fn test_rgb_background() {
+        // ESC[48;2;0;64;128m = RGB background (dark blue)
+        let input = b"\x1b[48;2;0;64;128mDark Blue BG";
+        let result = convert(input);
+        assert!(result.contains("<ans-rgb fg=\"fg-7\" bg=\"0,64,128\">"));
+        assert!(result.contains("Dark Blue BG"));
+    }
+
+    #[test]
+    fn test_rgb_both() {
+        // ESC[38;2;255;255;0;48;2;128;0;128m = yellow fg on purple bg
+        let input = b"\x1b[38;2;255;255;0;48;2;128;0;128mYellow on Purple";
+        let result = convert(input);
+        assert!(result.contains("<ans-rgb fg=\"255,255,0\" bg=\"128,0,128\">"));
+    }
+
+    #[test]
+    fn test_extended_color_reset() {
+        // Start with 256-color, then reset to default
+        let input = b"\x1b[38;5;196mRed\x1b[0mNormal";
+        let result = convert(input);
+        assert!(result.contains("<ans-256 fg=\"196\""));
+        assert!(result.contains("Red"));
+        assert!(result.contains("</ans-256>"));
+        assert!(result.contains("<ans-07>Normal"));
+    }
+
+    #[test]
+    fn test_switch_cga_to_256() {
+        // Start with CGA red, then switch to 256-color
+        let input = b"\x1b[31mCGA Red\x1b[38;5;196m256 Red";
+        let result = convert(input);
+        assert!(result.contains("<ans-04>CGA Red</ans-04>"));
+        assert!(result.contains("<ans-256 fg=\"196\""));
+        assert!(result.contains("256 Red"));
+    }
+
+    #[test]
+    fn test_switch_256_to_rgb() {
+        // Start with 256-color, then switch to RGB
+        let input = b"\x1b[38;5;196m256\x1b[38;2;255;0;0mRGB";
+        let result = convert(input);
+        assert!(result.contains("<ans-256"));
+        assert!(result.contains("256"));
+        assert!(result.contains("<ans-rgb fg=\"255,0,0\""));
+        assert!(result.contains("RGB"));
+    }
+
+    #[test]
+    fn test_256_color_cga_range() {
+        // 256-color palette indices 0-15 are the standard CGA colors
+        // Test index 4 (red in 256-color, which maps to CGA red)
+        let input = b"\x1b[38;5;4mBlue";
+        let result = convert(input);
+        assert!(result.contains("<ans-256 fg=\"4\""));
+    }
+
+    #[test]
+    fn test_256_color_grayscale() {
+        // Test grayscale colors (232-255)
+        let input = b"\x1b[38;5;240mGray";
+        let result = convert(input);
+        assert!(result.contains("<ans-256 fg=\"240\""));
+    }
+
+    // ========== Renegade escaped pipe tests ==========
+
+    #[test]
+    fn test_renegade_escaped_pipe() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // || should output a single | and continue
+        let input = b"||Hello";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("|Hello"));
+    }
+
+    #[test]
+    fn test_renegade_escaped_pipe_followed_by_digits() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // ||04 should output |04 (literal pipe followed by 04)
+        let input = b"||04Red";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("|04Red"));
+    }
+
+    #[test]
+    fn test_renegade_high_intensity_background() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // |24 = dark gray background (high intensity black)
+        let input = b"|24Dark Gray BG";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-87>")); // Dark Gray bg (8), Light Gray fg (7)
+    }
+
+    #[test]
+    fn test_renegade_high_intensity_background_range() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // |31 = white background (high intensity)
+        let input = b"|31White BG";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-f7>")); // White bg (f), Light Gray fg (7)
+    }
+
+    #[test]
+    fn test_renegade_combined_high_intensity_bg_with_fg() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        // |00 = black fg, |28 = light red background
+        let input = b"|00|28Black on Light Red";
+        let result = convert_with_options(input, &options);
+        assert!(result.contains("<ans-c0>")); // Light Red bg (c), Black fg (0)
+    }
+
+    // ========== Virtual screen buffer tests ==========
+
+    #[test]
+    fn test_screen_cursor_position() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        // ESC[2;5H moves to row 2, column 5
+        let result = convert_with_options(b"Top\x1b[2;5HX", &options);
+        assert!(result.contains("Top\n    X"));
+    }
+
+    #[test]
+    fn test_screen_cursor_up_overwrites() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        // Move back up to the first row and overwrite the third character
+        let result = convert_with_options(b"AAAA\n\x1b[1A\x1b[2CB", &options);
+        assert!(result.contains("AABA"));
+    }
+
+    #[test]
+    fn test_screen_cursor_back() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"ABC\x1b[2DX", &options);
+        assert!(result.contains("AXC"));
+    }
+
+    #[test]
+    fn test_screen_erase_line() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        // Move back three columns and erase to end of line
+        let result = convert_with_options(b"Hello\x1b[3D\x1b[K", &options);
+        assert!(result.contains("<ans-07>He</ans-07>"));
+    }
+
+    #[test]
+    fn test_screen_clear_screen() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"Old\x1b[2JNew", &options);
+        assert!(result.contains("New"));
+        assert!(!result.contains("Old"));
+    }
+
+    #[test]
+    fn test_screen_save_restore_cursor() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        // Restoring the cursor returns to the saved cell instead of collapsing text
+        let result = convert_with_options(b"A\x1b[sBBB\x1b[uC", &options);
+        assert!(result.contains("ACBB"));
+    }
+
+    #[test]
+    fn test_screen_cell_colors() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"XY\x1b[1;2H\x1b[31mZ", &options);
+        assert_eq!(
+            result,
+            "<pre class=\"ansi\"><ans-07>X</ans-07><ans-04>Z</ans-04></pre>"
+        );
+    }
+
+    #[test]
+    fn test_screen_wraps_at_column_80() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let input = vec![b'X'; 85];
+        let result = convert_with_options(&input, &options);
+        let lines: Vec<usize> = result.split('\n').map(|s| s.matches('X').count()).collect();
+        assert_eq!(lines, vec![80, 5]);
+    }
+
+    #[test]
+    fn test_screen_carriage_return_overwrites() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"Hello\rJ", &options);
+        assert!(result.contains("Jello"));
+    }
+
+    #[test]
+    fn test_screen_rows_are_bounded() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let input = b"\x1b[65535B".repeat(4);
+        let result = convert_with_options(&[&input[..], b"X\nY"].concat(), &options);
+        assert_eq!(result.matches('\n').count(), screen::MAX_ROWS - 1);
+        assert!(result.contains("\nY</ans-07>"));
+    }
+
+    #[test]
+    fn test_screen_cells_are_bounded() {
+        let options = ConvertOptions {
+            columns: Some(1000),
+            screen_buffer: true,
+            ..Default::default()
+        };
+        // Colored erases of 6000 full rows would need 6 million cells
+        let input = b"\x1b[44m\x1b[2K\x1b[B".repeat(6000);
+        let doc = convert_to_document(&[&input[..], b"\x1b[HX"].concat(), &options);
+        let cells: usize = doc
+            .lines
+            .iter()
+            .map(|line| line.text().chars().count())
+            .sum();
+        assert!(cells <= screen::MAX_CELLS);
+        assert_eq!(doc.lines[0].text().len(), 1000);
+        assert!(doc.lines[0].text().starts_with('X'));
+        assert_eq!(doc.lines[5000].text(), "");
+
+        // Clearing the screen gives the cells back
+        let input = [&input[..], b"\x1b[2J\x1b[5000BY"].concat();
+        let doc = convert_to_document(&input, &options);
+        assert_eq!(doc.lines[5000].text(), "Y");
+    }
+
+    // ========== Streaming converter tests ==========
+
+    /// Feed input one byte at a time and collect all fragments
+    fn stream_bytewise(input: &[u8], options: ConvertOptions) -> String {
+        let mut stream = StreamConverter::new(options);
+        let mut html = String::new();
+        for byte in input {
+            html.push_str(&stream.push(std::slice::from_ref(byte)));
+        }
+        html.push_str(&stream.finish());
+        html
+    }
+
+    #[test]
+    fn test_stream_matches_convert() {
+        let input = b"\x1b[1;31mRed\x1b[0m Normal\r\n\x1b[44mBlue BG";
+        assert_eq!(
+            stream_bytewise(input, ConvertOptions::default()),
+            convert(input)
+        );
+    }
+
+    #[test]
+    fn test_stream_returns_fragments_incrementally() {
+        let mut stream = StreamConverter::new(ConvertOptions::default());
+        let first = stream.push(b"Hello ");
+        assert_eq!(first, "<pre class=\"ansi\"><ans-07>Hello ");
+        // Half-received CSI produces no output yet
+        assert_eq!(stream.push(b"\x1b[3"), "");
+        assert_eq!(stream.push(b"2mWorld"), "</ans-07><ans-02>World");
+        assert_eq!(stream.finish(), "</ans-02></pre>");
+    }
+
+    #[test]
+    fn test_stream_renegade_split_across_chunks() {
+        let options = ConvertOptions {
+            renegade_pipe: true,
+            ..Default::default()
+        };
+        let input = b"|04Red |1|02Green";
+        assert_eq!(
+            stream_bytewise(input, options.clone()),
+            convert_with_options(input, &options)
+        );
+    }
+
+    #[test]
+    fn test_stream_utf8_split_sequence() {
+        let options = ConvertOptions {
+            utf8_input: true,
+            ..Default::default()
+        };
+        let input = "Box ─┐ é 🎨".as_bytes();
+        let result = stream_bytewise(input, options);
+        assert!(result.contains("Box ─┐ é 🎨"));
+        assert!(!result.contains('\u{FFFD}'));
+    }
+
+    #[test]
+    fn test_stream_utf8_invalid_and_truncated() {
+        let options = ConvertOptions {
+            utf8_input: true,
+            ..Default::default()
+        };
+        let mut stream = StreamConverter::new(options);
+        let mut html = stream.push(b"A\xffB\xe2\x94");
+        html.push_str(&stream.finish());
+        assert!(html.contains("A\u{FFFD}B\u{FFFD}"));
+    }
+
+    #[test]
+    fn test_stream_screen_buffer_renders_on_finish() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let mut stream = StreamConverter::new(options);
+        assert_eq!(stream.push(b"ABC\x1b[2DX"), "<pre class=\"ansi\"><ans-07>");
+        assert_eq!(stream.finish(), "AXC</ans-07></pre>");
+    }
+
+    #[test]
+    fn test_stream_stops_at_sub_and_writes_sauce() {
+        let mut sauce = sauce_with_width(80);
+        sauce[7..18].copy_from_slice(b"Stream Test");
+        let input = [
+            &b"\x1b[31mArt\r\nMore\x1aHidden"[..],
+            &sauce,
+            b"After SAUCE",
+        ]
+        .concat();
+        let expected = convert(&input);
+        assert!(expected.contains("Title: Stream Test"));
+        assert!(!expected.contains("Hidden"));
+        assert_eq!(stream_bytewise(&input, ConvertOptions::default()), expected);
+
+        // Nothing after the SUB is returned before the end of the stream
+        let mut stream = StreamConverter::new(ConvertOptions::default());
+        let mut html = stream.push(&input[..20]);
+        assert!(html.ends_with("More"));
+        assert_eq!(stream.push(&input[20..]), "");
+        html.push_str(&stream.finish());
+        assert_eq!(html, expected);
+    }
+
+    // ========== Writer output tests ==========
+
+    #[test]
+    fn test_convert_to_writer_matches_convert() {
+        // Large enough to be written in several blocks
+        let input = b"\x1b[1;33mYellow \x1b[0mplain <text>\r\n".repeat(2000);
+        let mut out = Vec::new();
+        convert_to_writer(&input, &ConvertOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), convert(&input));
+    }
+
+    #[test]
+    fn test_convert_to_writer_writes_in_blocks() {
+        struct CountingWriter(usize);
+        impl io::Write for CountingWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0 += 1;
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let input = vec![b'X'; 50_000];
+        let mut writer = CountingWriter(0);
+        convert_to_writer(&input, &ConvertOptions::default(), &mut writer).unwrap();
+        assert!(writer.0 > 1);
+    }
+
+    #[test]
+    fn test_convert_to_writer_propagates_errors() {
+        struct FailingWriter;
+        impl io::Write for FailingWriter {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk full"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let result = convert_to_writer(b"Hello", &ConvertOptions::default(), FailingWriter);
+        assert_eq!(result.unwrap_err().to_string(), "disk full");
+    }
+
+    #[test]
+    fn test_convert_to_fmt_writer() {
+        let mut page = String::from("<body>");
+        convert_to_fmt_writer(b"\x1b[32mGreen", &ConvertOptions::default(), &mut page).unwrap();
+        assert_eq!(page, format!("<body>{}", convert(b"\x1b[32mGreen")));
+    }
+
+    #[test]
+    fn test_convert_into_appends_and_reuses_buffer() {
+        let mut html = String::from("prefix");
+        convert_into(b"One", &ConvertOptions::default(), &mut html);
+        assert_eq!(html, format!("prefix{}", convert(b"One")));
+
+        html.clear();
+        let capacity = html.capacity();
+        convert_into(b"Two", &ConvertOptions::default(), &mut html);
+        assert_eq!(html, convert(b"Two"));
+        assert_eq!(html.capacity(), capacity);
+    }
+
+    // ========== Document model tests ==========
+
+    #[test]
+    fn test_document_spans_and_lines() {
+        let doc = convert_to_document(
+            b"Plain \x1b[1;34mBlue\r\n\x1b[0mNext",
+            &ConvertOptions::default(),
+        );
+        assert_eq!(doc.lines.len(), 2);
+        assert_eq!(
+            doc.lines[0].spans,
+            vec![
+                Span {
+                    text: "Plain ".to_string(),
+                    style: Style::default(),
+                },
+                Span {
+                    text: "Blue".to_string(),
+                    style: Style {
+                        foreground: ExtendedColor::Cga(9),
+                        background: ExtendedColor::Cga(0),
+                        ..Style::default()
+                    },
+                },
+            ]
+        );
+        assert_eq!(doc.lines[1].text(), "Next");
+    }
+
+    #[test]
+    fn test_document_merges_redundant_style_changes() {
+        // Switching colors without text in between must not produce empty spans
+        let doc = convert_to_document(b"\x1b[31m\x1b[32mA\x1b[32mB", &ConvertOptions::default());
+        assert_eq!(doc.lines[0].spans.len(), 1);
+        assert_eq!(doc.lines[0].spans[0].text, "AB");
+    }
+
+    #[test]
+    fn test_document_extended_colors() {
+        let doc = convert_to_document(b"\x1b[38;5;196;48;2;0;0;128mX", &ConvertOptions::default());
+        let style = doc.lines[0].spans[0].style;
+        assert_eq!(style.foreground, ExtendedColor::Palette(196));
+        assert_eq!(style.background, ExtendedColor::Rgb(0, 0, 128));
+    }
+
+    #[test]
+    fn test_document_to_html_matches_convert() {
+        let inputs: [&[u8]; 4] = [
+            b"Hello <World> & \"friends\"",
+            b"\x1b[1;31mRed\x1b[0m\r\n\x1b[44mBlue BG\x1b[38;5;100m256",
+            b"\x1b[38;2;255;0;0mRGB\x1b[0mPlain\n\n",
+            b"Art\x1aSAUCE00",
+        ];
+        for input in inputs {
+            let options = ConvertOptions::default();
+            assert_eq!(
+                convert_to_document(input, &options).to_html(),
+                convert_with_options(input, &options)
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "sixel")]
+    fn test_document_to_html_places_embedded_elements() {
+        let options = ConvertOptions {
+            ansi_music: true,
+            music_elements: true,
+            ripscrip: true,
+            sixel_images: true,
+            ..Default::default()
+        };
+        let inputs: [&[u8]; 3] = [
+            b"\x1b[31mAB\x1bPq#1~\x1b\\CD\x1b[MCDE\x0e\r\n\x1bPq#2~\x1b\\\x1b[44mNext",
+            b"Hello\r\n!|c0E|L00000A0A\r\nWorld",
+            b"\x1b[MC\x0e\x1bPq#1~\x1b\\",
+        ];
+        for input in inputs {
+            let doc = convert_to_document(input, &options);
+            assert_eq!(doc.to_html(), convert_with_options(input, &options));
+        }
+        assert!(
+            convert_to_document(inputs[1], &options)
+                .to_html()
+                .contains("<svg class=\"rip\"")
+        );
+
+        let doc = convert_to_document(inputs[0], &options);
+        let html = doc.to_html();
+        assert!(html.contains("AB<img"));
+        assert!(html.contains("CD<ans-music"));
+        assert_eq!(
+            doc.anchors,
+            [
+                Anchor {
+                    line: 0,
+                    column: 2,
+                    kind: AnchorKind::Image(0)
+                },
+                Anchor {
+                    line: 0,
+                    column: 4,
+                    kind: AnchorKind::Music(0)
+                },
+                Anchor {
+                    line: 1,
+                    column: 0,
+                    kind: AnchorKind::Image(1)
+                },
+            ]
+        );
+
+        let screen_options = ConvertOptions {
+            screen_buffer: true,
+            ..options
+        };
+        let input = b"AB\x1bPq#1~\x1b\\CD\x1b[1;1HX";
+        let doc = convert_to_document(input, &screen_options);
+        assert_eq!(doc.to_html(), convert_with_options(input, &screen_options));
+    }
+
+    #[test]
+    fn test_document_from_screen_buffer() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let doc = convert_to_document(b"ABC\x1b[2;3HX", &options);
+        let text: Vec<String> = doc.lines.iter().map(Line::text).collect();
+        assert_eq!(text, vec!["ABC", "  X"]);
+    }
+
+    // ========== Terminal width tests ==========
+
+    /// Build a minimal SAUCE record for a character file with the given width
+    fn sauce_with_width(width: u16) -> Vec<u8> {
+        sauce_with_width_and_flags(width, 0)
+    }
+
+    fn sauce_with_width_and_flags(width: u16, flags: u8) -> Vec<u8> {
+        let mut sauce = b"SAUCE00".to_vec();
+        sauce.extend_from_slice(&[b' '; 35 + 20 + 20]); // title, author, group
+        sauce.extend_from_slice(b"        "); // date
+        sauce.extend_from_slice(&[0u8; 4]); // filesize
+        sauce.extend_from_slice(&[1, 1]); // datatype (Character), filetype (ANSi)
+        sauce.extend_from_slice(&width.to_le_bytes()); // tinfo1
+        sauce.extend_from_slice(&[0u8; 6]); // tinfo2-4
+        sauce.push(0); // comments
+        sauce.push(flags); // tflags
+        sauce.extend_from_slice(&[0u8; 22]); // tinfos
+        sauce
+    }
+
+    fn x_counts_per_line(result: &str) -> Vec<usize> {
+        result
+            .split('\n')
+            .map(|s| s.matches('X').count())
+            .filter(|&n| n > 0)
+            .collect()
+    }
+
+    #[test]
+    fn test_columns_option_soft_wrap() {
+        let options = ConvertOptions {
+            columns: Some(40),
+            ..Default::default()
+        };
+        let mut input = b"\x1b[31m".to_vec();
+        input.extend(std::iter::repeat_n(b'X', 100));
+        let result = convert_with_options(&input, &options);
+        assert_eq!(x_counts_per_line(&result), vec![40, 40, 20]);
+    }
+
+    #[test]
+    fn test_columns_option_screen_buffer() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            columns: Some(132),
+            ..Default::default()
+        };
+        let input = vec![b'X'; 140];
+        let result = convert_with_options(&input, &options);
+        assert_eq!(x_counts_per_line(&result), vec![132, 8]);
+    }
+
+    #[test]
+    fn test_columns_from_sauce_width() {
+        let mut input = b"\x1b[31m".to_vec();
+        input.extend(std::iter::repeat_n(b'X', 100));
+        input.push(0x1A);
+        input.extend(sauce_with_width(40));
+        let result = convert(&input);
+        assert_eq!(x_counts_per_line(&result), vec![40, 40, 20]);
+    }
+
+    #[test]
+    fn test_columns_option_overrides_sauce_width() {
+        let options = ConvertOptions {
+            columns: Some(80),
+            ..Default::default()
+        };
+        let mut input = b"\x1b[31m".to_vec();
+        input.extend(std::iter::repeat_n(b'X', 100));
+        input.push(0x1A);
+        input.extend(sauce_with_width(40));
+        let result = convert_with_options(&input, &options);
+        assert_eq!(x_counts_per_line(&result), vec![80, 20]);
+    }
+
+    #[test]
+    fn test_columns_option_zero_uses_default() {
+        let options = ConvertOptions {
+            columns: Some(0),
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x1b[31mABC", &options);
+        assert!(result.contains("<ans-04>ABC</ans-04>"));
+
+        let mut input = b"\x1b[31m".to_vec();
+        input.extend(std::iter::repeat_n(b'X', 100));
+        input.push(0x1A);
+        input.extend(sauce_with_width(40));
+        let result = convert_with_options(&input, &options);
+        assert_eq!(x_counts_per_line(&result), vec![40, 40, 20]);
+    }
+
+    #[test]
+    fn test_columns_clamped_to_maximum() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            columns: Some(u16::MAX),
+            ..Default::default()
+        };
+        let input = vec![b'X'; 1100];
+        let result = convert_with_options(&input, &options);
+        assert_eq!(x_counts_per_line(&result), vec![1000, 100]);
+
+        // SAUCE widths are clamped as well
+        let mut input = b"\x1b[44m\x1b[2K".to_vec();
+        input.push(0x1A);
+        input.extend(sauce_with_width(u16::MAX));
+        let options = ConvertOptions {
+            columns: None,
+            ..options
+        };
+        let doc = convert_to_document(&input, &options);
+        assert_eq!(doc.lines[0].text().len(), 1000);
+    }
+
+    // ========== Blink mode tests ==========
+
+    #[test]
+    fn test_blink_mode_ice_colors_by_default() {
+        let result = convert(b"\x1b[5;44;33mX");
+        assert!(result.contains("<ans-96>X</ans-96>"));
+        assert!(!result.contains("blink"));
+    }
+
+    #[test]
+    fn test_blink_mode_blink() {
+        let options = ConvertOptions {
+            blink_mode: BlinkMode::Blink,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x1b[5;44;33mX\x1b[25mY", &options);
+        assert!(result.contains("<ans-16 blink>X</ans-16><ans-16>Y</ans-16>"));
+    }
+
+    #[test]
+    fn test_blink_mode_ignore() {
+        let options = ConvertOptions {
+            blink_mode: BlinkMode::Ignore,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x1b[5;44;33mX", &options);
+        assert!(result.contains("<ans-16>X</ans-16>"));
+        assert!(!result.contains("blink"));
+    }
+
+    #[test]
+    fn test_blink_mode_synchronet_and_renegade() {
+        let options = ConvertOptions {
+            synchronet_ctrl_a: true,
+            renegade_pipe: true,
+            blink_mode: BlinkMode::Blink,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x01I\x01rA|25B", &options);
+        assert!(result.contains("<ans-04 blink>A</ans-04><ans-14 blink>B</ans-14>"));
+    }
+
+    #[test]
+    fn test_blink_mode_screen_buffer() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            blink_mode: BlinkMode::Blink,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x1b[5mX", &options);
+        assert!(result.contains("<ans-07 blink>X</ans-07>"));
+    }
+
+    #[test]
+    fn test_blink_mode_from_sauce_flags() {
+        let mut input = b"\x1b[5mX".to_vec();
+        input.push(0x1A);
+        input.extend(sauce_with_width_and_flags(80, 0));
+        assert!(convert(&input).contains("<ans-07 blink>X\n"));
+
+        let mut input = b"\x1b[5mX".to_vec();
+        input.push(0x1A);
+        input.extend(sauce_with_width_and_flags(80, 0x01));
+        assert!(convert(&input).contains("<ans-87>X\n"));
+    }
+
+    #[test]
+    fn test_blink_mode_option_overrides_sauce_flags() {
+        let options = ConvertOptions {
+            blink_mode: BlinkMode::IceColors,
+            ..Default::default()
+        };
+        let mut input = b"\x1b[5mX".to_vec();
+        input.push(0x1A);
+        input.extend(sauce_with_width_and_flags(80, 0));
+        let result = convert_with_options(&input, &options);
+        assert!(result.contains("<ans-87>X\n"));
+    }
+
+    #[test]
+    fn test_blink_document_style() {
+        let options = ConvertOptions {
+            blink_mode: BlinkMode::Blink,
+            ..Default::default()
+        };
+        let doc = convert_to_document(b"\x1b[5mX", &options);
+        assert!(doc.lines[0].spans[0].style.attributes.blink);
+        assert_eq!(
+            doc.lines[0].spans[0].style.background,
+            ExtendedColor::Cga(0)
+        );
+    }
+
+    #[test]
+    fn test_blink_with_extended_background() {
+        let options = ConvertOptions {
+            blink_mode: BlinkMode::Blink,
+            ..Default::default()
+        };
+        let doc = convert_to_document(b"\x1b[5;48;5;196mX\x1b[48;2;0;0;128mY\x1b[25mZ", &options);
+        let styles: Vec<Style> = doc.lines[0].spans.iter().map(|span| span.style).collect();
+        assert_eq!(styles[0].background, ExtendedColor::Palette(196));
+        assert!(styles[0].attributes.blink);
+        assert_eq!(styles[1].background, ExtendedColor::Rgb(0, 0, 128));
+        assert!(styles[1].attributes.blink);
+        assert!(!styles[2].attributes.blink);
+
+        let result = convert_with_options(b"\x1b[5;48;5;196mX", &options);
+        assert!(result.contains(" blink>X"));
+    }
+
+    #[test]
+    fn test_blink_css_and_js() {
+        assert!(generate_css().contains("pre.ansi [blink]"));
+        assert!(generate_js().contains("ans-blink"));
+    }
+
+    // ========== SGR attribute tests ==========
+
+    #[test]
+    fn test_sgr_attributes_emitted_on_tags() {
+        let result = convert(b"\x1b[3;4mA\x1b[9;53mB");
+        assert!(result.contains("<ans-07 italic underline>A</ans-07>"));
+        assert!(result.contains("<ans-07 italic underline strike overline>B</ans-07>"));
+    }
+
+    #[test]
+    fn test_sgr_attributes_turned_off() {
+        let result = convert(b"\x1b[3;4;8;9;53mA\x1b[23;24;28;29;55mB");
+        assert!(result.contains(
+            "<ans-07 italic underline strike conceal overline>A</ans-07><ans-07>B</ans-07>"
+        ));
+    }
+
+    #[test]
+    fn test_sgr_double_underline() {
+        let result = convert(b"\x1b[21mA\x1b[24mB");
+        assert!(result.contains("<ans-07 double-underline>A</ans-07><ans-07>B</ans-07>"));
+    }
+
+    #[test]
+    fn test_sgr_faint_and_normal_intensity() {
+        let result = convert(b"\x1b[1;2mA\x1b[22mB");
+        // Faint still clears the high intensity bit, as before
+        assert!(result.contains("<ans-07 faint>A</ans-07><ans-07>B</ans-07>"));
+    }
+
+    #[test]
+    fn test_sgr_reset_clears_attributes() {
+        let result = convert(b"\x1b[4;31mA\x1b[0mB");
+        assert!(result.contains("<ans-04 underline>A</ans-04><ans-07>B</ans-07>"));
+    }
+
+    #[test]
+    fn test_sgr_attributes_with_extended_colors() {
+        let result = convert(b"\x1b[3;38;5;196mA\x1b[38;2;1;2;3mB");
+        assert!(result.contains("<ans-256 fg=\"196\" bg=\"bg-0\" italic>A</ans-256>"));
+        assert!(result.contains("<ans-rgb fg=\"1,2,3\" bg=\"bg-0\" italic>B</ans-rgb>"));
+    }
+
+    #[test]
+    fn test_sgr_attributes_in_document() {
+        let doc = convert_to_document(b"\x1b[4mA", &ConvertOptions::default());
+        let attributes = doc.lines[0].spans[0].style.attributes;
+        assert!(attributes.underline);
+        assert!(!attributes.italic);
+    }
+
+    #[test]
+    fn test_sgr_attributes_screen_buffer_keeps_underlined_spaces() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"A\x1b[4m  ", &options);
+        assert!(result.contains("<ans-07>A</ans-07><ans-07 underline>  </ans-07>"));
+    }
+
+    #[test]
+    fn test_sgr_attribute_css() {
+        let css = generate_css();
+        for name in [
+            "faint",
+            "italic",
+            "underline",
+            "double-underline",
+            "strike",
+            "conceal",
+            "overline",
+        ] {
+            assert!(
+                css.contains(&format!("pre.ansi [{}]", name)),
+                "missing rule for {}",
+                name
+            );
+        }
+    }
+
+    // ========== Palette tests ==========
+
+    #[test]
+    fn test_vga_palette_matches_cga_colors() {
+        for (index, hex) in CGA_COLORS.iter().enumerate() {
+            assert_eq!(palette::hex(Palette::VGA.color(index as u8)), *hex);
+        }
+        assert_eq!(Palette::default(), Palette::VGA);
+    }
+
+    #[test]
+    fn test_palette_color_256() {
+        let palette = Palette::XTERM;
+        assert_eq!(palette.color_256(1), palette.colors[1]);
+        assert_eq!(palette.color_256(196), (255, 0, 0));
+        assert_eq!(palette.color_256(232), (8, 8, 8));
+        // EGA has no 256-color table, so extended colors snap to the sixteen colors
+        assert_eq!(Palette::EGA.color_256(196), (0xAA, 0x00, 0x00));
+        assert_eq!(Palette::EGA.color_256(4), Palette::VGA.colors[4]);
+    }
+
+    #[test]
+    fn test_palette_option_scopes_colors_to_pre() {
+        let options = ConvertOptions {
+            palette: Some(Palette::XTERM),
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x1b[31mRed", &options);
+        assert!(result.starts_with(
+            "<pre class=\"ansi\" style=\"--ans-color-0: #000000; --ans-color-1: #0000EE;"
+        ));
+        assert!(
+            result.contains("--ans-color-15: #FFFFFF;\"><ans-07></ans-07><ans-04>Red</ans-04>")
+        );
+    }
+
+    #[test]
+    fn test_no_palette_option_leaves_pre_unstyled() {
+        assert!(convert(b"Text").starts_with("<pre class=\"ansi\"><ans-07>"));
+    }
+
+    #[test]
+    fn test_palette_in_document() {
+        let options = ConvertOptions {
+            palette: Some(Palette::MODERN),
+            ..Default::default()
+        };
+        let doc = convert_to_document(b"\x1b[32mGreen", &options);
+        assert_eq!(doc.palette, Some(Palette::MODERN));
+        assert_eq!(
+            doc.to_html(),
+            convert_with_options(b"\x1b[32mGreen", &options)
+        );
+    }
+
+    #[test]
+    fn test_generate_css_with_palette() {
+        let css = generate_css_with_palette(&Palette::AMIGA_WORKBENCH);
+        assert!(css.contains("--ans-color-0: #AAAAAA;"));
+        assert!(css.contains(
+            "ans-1e { background-color: var(--ans-color-1); color: var(--ans-color-14); }"
+        ));
+        assert!(css.contains("background-color: var(--ans-color-0);"));
+    }
+
+    #[test]
+    fn test_generate_js_with_palette() {
+        let js = generate_js_with_palette(&Palette::MODERN);
+        assert!(js.contains("\"#0C0C0C\", \"#0037DA\", \"#13A10E\", \"#3A96DD\""));
+        assert!(js.contains("const extendedColors = null;"));
+        assert!(js.contains("customElements.define(tagName, AnsExtendedElement)"));
+
+        let js = generate_js_with_palette(&Palette::EGA);
+        assert!(js.contains("const extendedColors = [\n    \"#000000\""));
+        assert!(js.contains("return `var(--ans-256-${index}, ${defaultPalette256(index)})`;"));
+    }
+
+    #[test]
+    fn test_palette_extended_table_scoped() {
+        let options = ConvertOptions {
+            palette: Some(Palette::EGA),
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x1b[38;5;196mRed", &options);
+        assert!(result.contains("--ans-color-15: #FFFFFF; --ans-256-0: #000000;"));
+        assert!(result.contains("--ans-256-196: #AA0000;"));
+        assert!(result.contains("<ans-256 fg=\"196\""));
+
+        // Palettes without a 256-color table only declare the sixteen colors
+        let options = ConvertOptions {
+            palette: Some(Palette::XTERM),
+            ..Default::default()
+        };
+        assert!(!convert_with_options(b"Text", &options).contains("--ans-256-"));
+    }
+
+    // ========== PabloDraw 24-bit color tests ==========
+
+    #[test]
+    fn test_pablodraw_rgb_foreground() {
+        let result = convert(b"\x1b[1;255;128;0tA");
+        assert!(result.contains("<ans-rgb fg=\"255,128,0\" bg=\"bg-0\">A</ans-rgb>"));
+    }
+
+    #[test]
+    fn test_pablodraw_rgb_background() {
+        let result = convert(b"\x1b[0;10;20;30tA");
+        assert!(result.contains("<ans-rgb fg=\"fg-7\" bg=\"10,20,30\">A</ans-rgb>"));
+    }
+
+    #[test]
+    fn test_pablodraw_rgb_both_and_reset() {
+        let result = convert(b"\x1b[1;1;2;3t\x1b[0;4;5;6tA\x1b[0mB");
+        assert!(
+            result.contains("<ans-rgb fg=\"1,2,3\" bg=\"4,5,6\">A</ans-rgb><ans-07>B</ans-07>")
+        );
+    }
+
+    #[test]
+    fn test_pablodraw_rgb_components_clamped() {
+        let result = convert(b"\x1b[1;256;0;0tA\x1b[0;1000;99999;tB");
+        assert!(result.contains("<ans-rgb fg=\"255,0,0\" bg=\"bg-0\">A</ans-rgb>"));
+        assert!(result.contains("<ans-rgb fg=\"255,0,0\" bg=\"255,255,0\">B</ans-rgb>"));
+    }
+
+    #[test]
+    fn test_pablodraw_rgb_screen_buffer() {
+        let options = ConvertOptions {
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x1b[1;9;8;7tA", &options);
+        assert!(result.contains("<ans-rgb fg=\"9,8,7\" bg=\"bg-0\">A</ans-rgb>"));
+    }
+
+    #[test]
+    fn test_other_t_sequences_ignored() {
+        // xterm window operations share the final byte
+        let result = convert(b"\x1b[8;25;80t\x1b[2;1;2;3tA");
+        assert!(result.contains("<ans-07>A</ans-07>"));
+        assert!(!result.contains("ans-rgb"));
+    }
+
+    // ========== XBin decoder tests ==========
+
+    fn xbin_header(width: u16, height: u16, font_height: u8, flags: u8) -> Vec<u8> {
+        let mut data = b"XBIN\x1a".to_vec();
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.push(font_height);
+        data.push(flags);
+        data
+    }
+
+    #[test]
+    fn test_xbin_uncompressed_rows() {
+        let mut data = xbin_header(2, 2, 16, 0);
+        data.extend_from_slice(b"A\x07B\x07C\x1eD\x1e");
+        let image = decode_xbin(&data).unwrap();
+        assert_eq!(image.cells.len(), 4);
+        assert_eq!(image.cells[2].background, ExtendedColor::Cga(1));
+        assert!(image.palette.is_none() && image.font.is_none());
+        let html = image.to_html(&ConvertOptions::default());
+        assert_eq!(
+            html,
+            "<pre class=\"ansi\"><ans-07>AB\n</ans-07><ans-1e>CD</ans-1e></pre>"
+        );
+    }
+
+    #[test]
+    fn test_xbin_palette_and_font() {
+        let mut data = xbin_header(1, 1, 2, 0x01 | 0x02);
+        let mut dac = vec![0u8; 48];
+        dac[3..6].copy_from_slice(&[63, 32, 0]); // color 1
+        data.extend_from_slice(&dac);
+        let font: Vec<u8> = (0..512).map(|i| i as u8).collect();
+        data.extend_from_slice(&font);
+        data.extend_from_slice(b"X\x01");
+        let image = decode_xbin(&data).unwrap();
+
+        let palette = image.palette.unwrap();
+        assert_eq!(palette.colors[1], (255, 130, 0));
+        let embedded = image.font.as_ref().unwrap();
+        assert_eq!((embedded.height, embedded.glyphs), (2, 256));
+        assert_eq!(embedded.data, font);
+
+        // The file's palette is scoped to the output
+        let html = image.to_html(&ConvertOptions::default());
+        assert!(html.starts_with(
+            "<pre class=\"ansi\" style=\"--ans-color-0: #000000; --ans-color-1: #FF8200;"
+        ));
+        assert!(html.contains("<ans-01>X</ans-01>"));
+        // ...unless the options choose a palette
+        let options = ConvertOptions {
+            palette: Some(Palette::XTERM),
+            ..Default::default()
+        };
+        assert!(image.to_html(&options).contains("--ans-color-1: #0000EE;"));
+    }
+
+    #[test]
+    fn test_xbin_compressed_runs() {
+        let mut data = xbin_header(8, 1, 16, 0x04);
+        data.extend_from_slice(&[0x00, b'A', 0x07]); // 1 uncompressed pair
+        data.extend_from_slice(&[0x41, b'B', 0x01, 0x02]); // char run of 2
+        data.extend_from_slice(&[0x81, 0x03, b'C', b'D']); // attribute run of 2
+        data.extend_from_slice(&[0xC2, b'E', 0x04]); // pair run of 3
+        let image = decode_xbin(&data).unwrap();
+        let text: String = image.cells.iter().map(|c| c.ch as u8 as char).collect();
+        assert_eq!(text, "ABBCDEEE");
+        assert_eq!(image.cells[2].foreground, ExtendedColor::Cga(2));
+        assert_eq!(image.cells[4].foreground, ExtendedColor::Cga(3));
+        assert_eq!(image.cells[7].foreground, ExtendedColor::Cga(4));
+    }
+
+    #[test]
+    fn test_xbin_compressed_size_not_trusted() {
+        // 80x13107 header with no data fails instead of allocating the whole image
+        assert_eq!(
+            decode_xbin(b"XBIN\x1a\x50\x00\x33\x33\x10\x04").unwrap_err(),
+            DecodeError::Truncated
+        );
+
+        // Headers past the cell limit are rejected before any run is expanded
+        let mut data = xbin_header(0xffff, 0xffff, 16, 0x04);
+        data.extend(std::iter::repeat_n([0xFF, b'X', 0x1F], 1000).flatten());
+        assert_eq!(
+            decode_xbin(&data).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+        assert_eq!(
+            decode_xbin(&xbin_header(80, 13108, 16, 0x04)).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+
+        // A run past the declared size is an error
+        let mut data = xbin_header(2, 1, 16, 0x04);
+        data.extend_from_slice(&[0xC2, b'E', 0x04]); // pair run of 3
+        assert_eq!(
+            decode_xbin(&data).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+    }
+
+    #[test]
+    fn test_xbin_512_character_mode() {
+        let mut data = xbin_header(1, 1, 16, 0x10);
+        data.extend_from_slice(b"A\x0c");
+        let image = decode_xbin(&data).unwrap();
+        assert_eq!(image.cells[0].ch, 256 + u16::from(b'A'));
+        assert_eq!(image.cells[0].foreground, ExtendedColor::Cga(4));
+    }
+
+    #[test]
+    fn test_xbin_blink_follows_non_blink_flag() {
+        let mut blink = xbin_header(1, 1, 16, 0);
+        blink.extend_from_slice(b"A\x87");
+        let html = decode_xbin(&blink)
+            .unwrap()
+            .to_html(&ConvertOptions::default());
+        assert!(html.contains("<ans-07 blink>A</ans-07>"));
+
+        let mut ice = xbin_header(1, 1, 16, 0x08);
+        ice.extend_from_slice(b"A\x87");
+        let html = decode_xbin(&ice)
+            .unwrap()
+            .to_html(&ConvertOptions::default());
+        assert!(html.contains("<ans-87>A</ans-87>"));
+    }
+
+    #[test]
+    fn test_xbin_sauce_shown_after_image() {
+        let mut data = xbin_header(1, 1, 16, 0);
+        data.extend_from_slice(b"A\x07\x1a");
+        data.extend(sauce_with_width(80));
+        let doc = decode_xbin(&data)
+            .unwrap()
+            .to_document(&ConvertOptions::default());
+        assert_eq!(doc.lines[0].text(), "A");
+        assert_eq!(doc.lines[1].text(), "Size: 80x0");
+    }
+
+    #[test]
+    fn test_xbin_errors() {
+        assert_eq!(
+            decode_xbin(b"XBIM\x1a").unwrap_err(),
+            DecodeError::InvalidSignature
+        );
+        assert_eq!(
+            decode_xbin(b"XBIN\x1a\x01\x00").unwrap_err(),
+            DecodeError::Truncated
+        );
+        assert_eq!(
+            decode_xbin(&xbin_header(0, 5, 16, 0)).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+        let mut data = xbin_header(4, 1, 16, 0);
+        data.extend_from_slice(b"A\x07");
+        assert_eq!(decode_xbin(&data).unwrap_err(), DecodeError::Truncated);
+        let mut data = xbin_header(4, 1, 16, 0x04);
+        data.extend_from_slice(&[0xC1, b'A', 0x07]);
+        assert_eq!(decode_xbin(&data).unwrap_err(), DecodeError::Truncated);
+    }
+
+    // ========== BinaryText decoder tests ==========
+
+    fn binary_text_sauce(width: u16, flags: u8) -> Vec<u8> {
+        let mut sauce = sauce_with_width_and_flags(0, flags);
+        sauce[94] = 5; // datatype (BinaryText)
+        sauce[95] = (width / 2) as u8; // filetype (half the width)
+        sauce
+    }
+
+    #[test]
+    fn test_binary_text_default_width() {
+        let data: Vec<u8> = (0..161)
+            .flat_map(|i| [if i < 160 { b'A' } else { b'B' }, 0x1F])
+            .collect();
+        let image = decode_binary_text(&data, None).unwrap();
+        assert_eq!((image.width, image.height), (160, 2));
+        assert_eq!(image.cells[160], ImageCell::from_attribute(b'B', 0x1F));
+        // The partial last row is padded with blanks
+        assert_eq!(image.cells[161], ImageCell::from_attribute(b' ', 0x07));
+    }
+
+    #[test]
+    fn test_binary_text_width_from_sauce() {
+        let mut data = b"A\x1fB\x4eC\x07D\x70".to_vec();
+        data.push(0x1A);
+        data.extend(binary_text_sauce(2, 0));
+        let image = decode_binary_text(&data, None).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        let doc = image.to_document(&ConvertOptions::default());
+        assert_eq!(doc.lines[0].text(), "AB");
+        assert_eq!(doc.lines[1].text(), "CD");
+        assert_eq!(
+            doc.lines[1].spans[1].style.background,
+            ExtendedColor::Cga(7)
+        );
+
+        // An explicit width overrides SAUCE
+        let image = decode_binary_text(&data, Some(4)).unwrap();
+        assert_eq!((image.width, image.height), (4, 1));
+    }
+
+    #[test]
+    fn test_binary_text_keeps_ctrl_z_cells() {
+        // Ctrl-Z as a character is image data, not an end-of-file marker
+        let mut data = b"\x1a\x07A\x07".to_vec();
+        data.extend(binary_text_sauce(2, 0));
+        let image = decode_binary_text(&data, None).unwrap();
+        assert_eq!(image.cells[0], ImageCell::from_attribute(0x1A, 0x07));
+        assert_eq!(image.cells.len(), 2);
+    }
+
+    #[test]
+    fn test_binary_text_ice_colors() {
+        let html = decode_binary_text(b"A\x87", Some(1))
+            .unwrap()
+            .to_html(&ConvertOptions::default());
+        assert!(html.contains("<ans-87>A</ans-87>"));
+
+        let mut data = b"A\x87".to_vec();
+        data.extend(binary_text_sauce(2, 0));
+        let html = decode_binary_text(&data, Some(1))
+            .unwrap()
+            .to_html(&ConvertOptions::default());
+        assert!(html.contains("<ans-07 blink>A</ans-07>"));
+
+        let mut data = b"A\x87".to_vec();
+        data.extend(binary_text_sauce(2, 0x01));
+        let html = decode_binary_text(&data, Some(1))
+            .unwrap()
+            .to_html(&ConvertOptions::default());
+        assert!(html.contains("<ans-87>A</ans-87>"));
+    }
+
+    #[test]
+    fn test_binary_text_too_many_rows() {
+        let data = b"A\x07".repeat(65536);
+        assert_eq!(
+            decode_binary_text(&data[2..], Some(1)).unwrap().height,
+            65535
+        );
+        assert_eq!(
+            decode_binary_text(&data, Some(1)).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+    }
+
+    #[test]
+    fn test_binary_text_too_many_cells() {
+        let data = b"A\x07".repeat(formats::MAX_CELLS);
+        assert_eq!(
+            decode_binary_text(&data, Some(256)).unwrap().cells.len(),
+            formats::MAX_CELLS
+        );
+        // One more cell needs another row of 256
+        let data = b"A\x07".repeat(formats::MAX_CELLS + 1);
+        assert_eq!(
+            decode_binary_text(&data, Some(256)).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+    }
+
+    #[test]
+    fn test_binary_text_errors() {
+        assert_eq!(
+            decode_binary_text(b"", None).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+        assert_eq!(
+            decode_binary_text(b"A\x07", Some(0)).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
     }
 
-    css
-}
+    // ========== Artworx / iCE Draw decoder tests ==========
+
+    fn artworx_file(cells: &[u8]) -> Vec<u8> {
+        let mut data = vec![1];
+        let mut registers = [0u8; 192];
+        registers[20 * 3..20 * 3 + 3].copy_from_slice(&[0x3F, 0x20, 0x00]); // brown
+        registers[63 * 3..63 * 3 + 3].copy_from_slice(&[0x3F, 0x3F, 0x3F]); // white
+        data.extend_from_slice(&registers);
+        data.extend((0..4096).map(|i| (i % 251) as u8));
+        data.extend_from_slice(cells);
+        data
+    }
 
-/// Generate JavaScript for defining ans-KF web components.
-///
-/// This returns JavaScript code that defines custom elements for all 256 color combinations.
-pub fn generate_js() -> String {
-    let js = String::from(
-        r##"// ANSI color web components
-(function() {
-  const colors = [
-    "#000000", "#0000AA", "#00AA00", "#00AAAA",
-    "#AA0000", "#AA00AA", "#AA5500", "#AAAAAA",
-    "#555555", "#5555FF", "#55FF55", "#55FFFF",
-    "#FF5555", "#FF55FF", "#FFFF55", "#FFFFFF"
-  ];
+    fn ice_draw_file(x2: u16, y2: u16, image: &[u8]) -> Vec<u8> {
+        let mut data = b"\x041.4\x00\x00\x00\x00".to_vec();
+        data.extend_from_slice(&x2.to_le_bytes());
+        data.extend_from_slice(&y2.to_le_bytes());
+        data.extend_from_slice(image);
+        data.extend(std::iter::repeat_n(0xAA, 4096));
+        let mut palette = [0u8; 48];
+        palette[3..6].copy_from_slice(&[0x00, 0x00, 0x3F]); // blue
+        data.extend_from_slice(&palette);
+        data
+    }
 
-  const hexChars = "0123456789ABCDEF";
+    #[test]
+    fn test_artworx_palette_font_and_cells() {
+        let image = decode_artworx(&artworx_file(b"A\x06B\x8f")).unwrap();
+        assert_eq!((image.width, image.height), (80, 1));
+        let palette = image.palette.unwrap();
+        assert_eq!(palette.colors[6], (0xFF, 0x82, 0x00));
+        assert_eq!(palette.colors[15], (0xFF, 0xFF, 0xFF));
+        let font = image.font.as_ref().unwrap();
+        assert_eq!((font.height, font.glyphs, font.data[300]), (16, 256, 49));
+
+        let html = image.to_html(&ConvertOptions::default());
+        assert!(html.starts_with("<pre class=\"ansi\" style=\"--ans-color-0: #000000;"));
+        assert!(html.contains("--ans-color-6: #FF8200;"));
+        assert!(html.contains("<ans-06>A</ans-06><ans-8f>B</ans-8f>"));
+    }
 
-  for (let bg = 0; bg < 16; bg++) {
-    for (let fg = 0; fg < 16; fg++) {
-      const tagName = `ans-${hexChars[bg]}${hexChars[fg]}`;
+    #[test]
+    fn test_artworx_sauce_and_errors() {
+        let mut data = artworx_file(b"A\x07");
+        data.push(0x1A);
+        data.extend(sauce_with_width(80));
+        let image = decode_artworx(&data).unwrap();
+        assert_eq!(image.cells.len(), 80);
+        let doc = image.to_document(&ConvertOptions::default());
+        assert_eq!(doc.lines[0].text().trim_end(), "A");
+        assert_eq!(doc.lines[1].text(), "Size: 80x0");
+
+        assert_eq!(
+            decode_artworx(b"\x02").unwrap_err(),
+            DecodeError::InvalidSignature
+        );
+        assert_eq!(
+            decode_artworx(&[1; 100]).unwrap_err(),
+            DecodeError::Truncated
+        );
+        assert_eq!(
+            decode_artworx(&artworx_file(b"")).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+    }
 
-      if (!customElements.get(tagName.toLowerCase())) {
-        const bgColor = colors[bg];
-        const fgColor = colors[fg];
+    #[test]
+    fn test_ice_draw_runs_palette_and_font() {
+        // "AB", then a run of four "-" on blue, then "C"
+        let image = decode_ice_draw(&ice_draw_file(
+            1,
+            3,
+            b"A\x07B\x07\x01\x00\x04\x00-\x10C\x0e",
+        ))
+        .unwrap();
+        assert_eq!((image.width, image.height), (2, 4));
+        let text: String = image
+            .cells
+            .iter()
+            .map(|cell| cell.ch as u8 as char)
+            .collect();
+        assert_eq!(text, "AB----C ");
+        assert_eq!(image.cells[2].background, ExtendedColor::Cga(1));
+        assert_eq!(image.palette.unwrap().colors[1], (0x00, 0x00, 0xFF));
+        assert!(image.font.unwrap().data.iter().all(|&b| b == 0xAA));
+    }
 
-        class AnsElement extends HTMLElement {
-          constructor() {
-            super();
-          }
+    #[test]
+    fn test_ice_draw_sauce_and_errors() {
+        let mut data = ice_draw_file(0, 0, b"A\x9f");
+        data.push(0x1A);
+        data.extend(sauce_with_width(1));
+        let image = decode_ice_draw(&data).unwrap();
+        assert_eq!(image.cells, vec![ImageCell::from_attribute(b'A', 0x9F)]);
+        let html = image.to_html(&ConvertOptions::default());
+        assert!(html.contains("<ans-9f>A\n</ans-9f>"));
+        assert!(html.contains("Size: 1x0"));
+
+        assert_eq!(
+            decode_ice_draw(b"XBIN").unwrap_err(),
+            DecodeError::InvalidSignature
+        );
+        assert_eq!(
+            decode_ice_draw(b"\x041.4\x00\x00").unwrap_err(),
+            DecodeError::Truncated
+        );
+        assert_eq!(
+            decode_ice_draw(b"\x041.").unwrap_err(),
+            DecodeError::Truncated
+        );
+        let mut data = b"\x041.".to_vec();
+        data.extend(sauce_with_width(1));
+        assert_eq!(decode_ice_draw(&data).unwrap_err(), DecodeError::Truncated);
+        assert_eq!(
+            decode_ice_draw(&b"\x041.4\x00\x00\x00\x00"[..]).unwrap_err(),
+            DecodeError::Truncated
+        );
+        let mut data = ice_draw_file(0, 0, b"A\x07");
+        data[4] = 5; // x1 past x2
+        assert_eq!(
+            decode_ice_draw(&data).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+        assert_eq!(
+            decode_ice_draw(&ice_draw_file(0, 0, b"")).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+
+        // y1 past y2, and runs past the rows declared by the y coordinates
+        let mut data = ice_draw_file(0, 0, b"A\x07");
+        data[6] = 1;
+        assert_eq!(
+            decode_ice_draw(&data).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+        let data = ice_draw_file(1, 1, b"\x01\x00\x05\x00-\x10");
+        assert_eq!(
+            decode_ice_draw(&data).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+        let data = ice_draw_file(1, 1, b"\x01\x00\x04\x00-\x10A\x07");
+        assert_eq!(
+            decode_ice_draw(&data).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+        let data = ice_draw_file(1, 1, b"\x01\x00\x04\x00-\x10");
+        assert_eq!(decode_ice_draw(&data).unwrap().height, 2);
+    }
 
-          connectedCallback() {
-            this.style.backgroundColor = bgColor;
-            this.style.color = fgColor;
-            this.style.display = "inline";
-          }
-        }
+    #[test]
+    fn test_ice_draw_cell_count_is_bounded() {
+        // A 65535x65535 header is rejected before any run is expanded
+        let data = ice_draw_file(0xfffe, 0xfffe, b"\x01\x00\xff\xffX\x1f");
+        assert_eq!(
+            decode_ice_draw(&data).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+
+        // 80 columns by 13107 rows is the largest image
+        assert_eq!(
+            decode_ice_draw(&ice_draw_file(79, 13107, b"A\x07")).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+        let image = decode_ice_draw(&ice_draw_file(79, 13106, b"\x01\x00\xff\xffX\x1f")).unwrap();
+        assert_eq!(image.cells.len(), 65600);
+    }
 
-        customElements.define(tagName.toLowerCase(), AnsElement);
-      }
+    // ========== TundraDraw decoder tests ==========
+
+    fn tundra_file(body: &[u8]) -> Vec<u8> {
+        let mut data = b"\x18TUNDRA24".to_vec();
+        data.extend_from_slice(body);
+        data
     }
-  }
-})();
-"##,
-    );
 
-    js
-}
+    #[test]
+    fn test_tundra_colors_and_plain_text() {
+        // Plain text, then foreground, background and both-color commands
+        let image = decode_tundra(&tundra_file(
+            b"a\x02b\x00\x10\x20\x30\x04c\x00\x40\x50\x60\x06d\x00\x01\x02\x03\x00\x04\x05\x06e",
+        ))
+        .unwrap();
+        assert_eq!((image.width, image.height), (80, 1));
+        let cells = &image.cells;
+        assert_eq!(
+            (cells[0].foreground, cells[0].background),
+            (ExtendedColor::Cga(7), ExtendedColor::Cga(0))
+        );
+        assert_eq!(cells[1].foreground, ExtendedColor::Rgb(0x10, 0x20, 0x30));
+        assert_eq!(cells[2].background, ExtendedColor::Rgb(0x40, 0x50, 0x60));
+        assert_eq!(cells[2].foreground, ExtendedColor::Rgb(0x10, 0x20, 0x30));
+        assert_eq!(
+            cells[4],
+            ImageCell {
+                ch: u16::from(b'e'),
+                foreground: ExtendedColor::Rgb(1, 2, 3),
+                background: ExtendedColor::Rgb(4, 5, 6),
+            }
+        );
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let html = image.to_html(&ConvertOptions::default());
+        assert!(html.contains("<ans-rgb fg=\"1,2,3\" bg=\"4,5,6\">de</ans-rgb>"));
+    }
 
     #[test]
-    fn test_basic_text() {
-        let result = convert(b"Hello");
-        assert!(result.contains("<pre class=\"ansi\">"));
-        assert!(result.contains("<ans-07>"));
-        assert!(result.contains("Hello"));
-        assert!(result.contains("</ans-07>"));
-        assert!(result.contains("</pre>"));
+    fn test_tundra_position_and_wrapping() {
+        let mut body = b"\x01\x00\x00\x00\x02\x00\x00\x00\x05X".to_vec();
+        body.extend(std::iter::repeat_n(b'-', 76));
+        let image = decode_tundra(&tundra_file(&body)).unwrap();
+        assert_eq!(image.height, 4);
+        let doc = image.to_document(&ConvertOptions::default());
+        assert_eq!(doc.lines[2].text(), format!("     X{}", "-".repeat(74)));
+        assert_eq!(doc.lines[3].text(), "--");
     }
 
     #[test]
-    fn test_html_escaping() {
-        let result = convert(b"<script>&</script>");
-        assert!(result.contains("&lt;script&gt;&amp;&lt;/script&gt;"));
-        // Test double quote
-        let result = convert(b"\"quoted\"");
-        assert!(result.contains("&quot;quoted&quot;"));
-        // Test apostrophe
-        let result = convert(b"it's here");
-        assert!(result.contains("it&apos;s here"));
+    fn test_tundra_sauce_width() {
+        let mut data = tundra_file(b"ABCD");
+        data.push(0x1A);
+        data.extend(sauce_with_width(2));
+        let image = decode_tundra(&data).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        let doc = image.to_document(&ConvertOptions::default());
+        assert_eq!(doc.lines[1].text(), "CD");
+        assert_eq!(doc.lines[2].text(), "Size: 2x0");
     }
 
     #[test]
-    fn test_color_change() {
-        // ESC[31m sets red foreground
-        let input = b"\x1b[31mRed";
-        let result = convert(input);
-        assert!(result.contains("<ans-04>")); // Red foreground on black
+    fn test_tundra_errors() {
+        assert_eq!(
+            decode_tundra(b"TUNDRA24").unwrap_err(),
+            DecodeError::InvalidSignature
+        );
+        assert_eq!(
+            decode_tundra(&tundra_file(b"\x02A\x00\xff")).unwrap_err(),
+            DecodeError::Truncated
+        );
+        assert_eq!(
+            decode_tundra(&tundra_file(b"\x01\x00\x00")).unwrap_err(),
+            DecodeError::Truncated
+        );
+        assert_eq!(
+            decode_tundra(&tundra_file(b"\x01\x7f\x00\x00\x00\x00\x00\x00\x00X")).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+        assert_eq!(
+            decode_tundra(&tundra_file(b"")).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
     }
 
     #[test]
-    fn test_clear_screen() {
-        // ESC[2J clears screen
-        let input = b"Before\x1b[2JAfter";
-        let result = convert(input);
-        // Should have three newlines for clear screen
-        assert!(result.contains("\n\n\n"));
+    fn test_tundra_cell_count_is_bounded() {
+        // Row 13107 of 80 columns is past the cell limit
+        let body = b"\x01\x00\x00\x33\x33\x00\x00\x00\x00X";
+        assert_eq!(
+            decode_tundra(&tundra_file(body)).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
+        let body = b"\x01\x00\x00\x33\x32\x00\x00\x00\x00X";
+        assert_eq!(decode_tundra(&tundra_file(body)).unwrap().height, 13107);
+
+        // A wide SAUCE width leaves fewer rows
+        let mut data = tundra_file(b"\x01\x00\x00\xff\xf0\x00\x00\x00\x00X");
+        data.push(0x1A);
+        data.extend(sauce_with_width(65535));
+        assert_eq!(
+            decode_tundra(&data).unwrap_err(),
+            DecodeError::InvalidDimensions
+        );
     }
 
+    // ========== PCBoard @X code tests ==========
+
     #[test]
-    fn test_newline_preserved() {
-        let result = convert(b"Line1\nLine2");
-        assert!(result.contains("Line1\nLine2"));
+    fn test_pcboard_colors() {
+        let options = ConvertOptions {
+            pcboard_at_x: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"@X1FWhite on blue@X0eYellow", &options);
+        assert!(result.contains("<ans-1f>White on blue</ans-1f><ans-0e>Yellow</ans-0e>"));
     }
 
     #[test]
-    fn test_carriage_return_suppressed() {
-        let result = convert(b"Line1\r\nLine2");
-        assert!(!result.contains('\r'));
-        assert!(result.contains("Line1\nLine2"));
+    fn test_pcboard_disabled_by_default() {
+        let result = convert(b"@X1FText");
+        assert!(result.contains("@X1FText"));
     }
 
     #[test]
-    fn test_generate_css() {
-        let css = generate_css();
-        assert!(css.contains("ans-07"));
-        assert!(css.contains("#AAAAAA")); // Light gray
+    fn test_pcboard_at_sign_literal() {
+        let options = ConvertOptions {
+            pcboard_at_x: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"@@X1F user@example.com", &options);
+        assert!(result.contains("@X1F user@example.com"));
     }
 
     #[test]
-    fn test_generate_js() {
-        let js = generate_js();
-        assert!(js.contains("customElements.define"));
+    fn test_pcboard_invalid_code_passthrough() {
+        let options = ConvertOptions {
+            pcboard_at_x: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"@XZ1 @x1F @X1G @Xa", &options);
+        assert!(result.contains("@XZ1 @x1F @X1G "));
+        assert!(!result.contains("<ans-1"));
     }
 
     #[test]
-    fn test_soft_return_at_column_80() {
-        // Create a line with ANSI escape that's longer than 80 chars
-        let mut input = vec![0x1b, b'[', b'3', b'1', b'm']; // Red color
-        // Add 85 'X' characters - should trigger soft return after 80
-        for _ in 0..85 {
-            input.push(b'X');
-        }
-        let result = convert(&input);
-        // Should have a newline injected after column 80
-        let x_count_before_newline = result
-            .split('\n')
-            .find(|s| s.contains("XXXX"))
-            .map(|s| s.matches('X').count())
-            .unwrap_or(0);
-        assert_eq!(x_count_before_newline, 80);
+    fn test_pcboard_save_and_restore() {
+        let options = ConvertOptions {
+            pcboard_at_x: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"@X4EA@X00@X1FB@XFFC", &options);
+        assert!(result.contains("<ans-4e>A</ans-4e><ans-1f>B</ans-1f><ans-4e>C</ans-4e>"));
     }
 
     #[test]
-    fn test_no_soft_return_without_ansi() {
-        // Without ANSI, no soft return should happen
-        let input: Vec<u8> = (0..85).map(|_| b'X').collect();
-        let result = convert(&input);
-        // Should NOT have a newline
-        assert!(!result.contains('\n'));
+    fn test_pcboard_utf8_and_stream() {
+        let options = ConvertOptions {
+            pcboard_at_x: true,
+            ..Default::default()
+        };
+        let utf8_options = ConvertOptions {
+            utf8_input: true,
+            ..options.clone()
+        };
+        let result = convert_with_options("@X1F═@X1G".as_bytes(), &utf8_options);
+        assert!(result.contains("<ans-1f>═@X1G</ans-1f>"));
+
+        let mut stream = StreamConverter::new(options);
+        let mut html = stream.push(b"@");
+        html.push_str(&stream.push(b"X1"));
+        html.push_str(&stream.push(b"FHi"));
+        html.push_str(&stream.finish());
+        assert!(html.contains("<ans-1f>Hi</ans-1f>"));
     }
 
+    // ========== Wildcat! code tests ==========
+
     #[test]
-    fn test_save_restore_position_collapse() {
-        // ESC[s saves position, text should be collapsed, ESC[u restores
-        let input = b"Before\x1b[sHidden\x1b[uAfter";
-        let result = convert(input);
-        assert!(result.contains("Before"));
-        assert!(result.contains("After"));
-        assert!(!result.contains("Hidden"));
+    fn test_wildcat_colors() {
+        let options = ConvertOptions {
+            wildcat_at: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"@1F@White on blue@0e@Yellow", &options);
+        assert!(result.contains("<ans-1f>White on blue</ans-1f><ans-0e>Yellow</ans-0e>"));
     }
 
     #[test]
-    fn test_dec_save_restore_position() {
-        // \e7 saves position, \e8 restores
-        let input = b"Start\x1b7Collapsed\x1b8End";
-        let result = convert(input);
-        assert!(result.contains("Start"));
-        assert!(result.contains("End"));
-        assert!(!result.contains("Collapsed"));
+    fn test_wildcat_disabled_by_default() {
+        let result = convert(b"@1F@Text");
+        assert!(result.contains("@1F@Text"));
     }
 
     #[test]
-    fn test_cp437_box_drawing() {
-        // Test box drawing characters (0xDA = top-left corner)
-        let input = [0xDA, 0xC4, 0xC4, 0xBF]; // ┌──┐
-        let result = convert(&input);
-        assert!(result.contains('┌'));
-        assert!(result.contains('─'));
-        assert!(result.contains('┐'));
+    fn test_wildcat_malformed_passthrough() {
+        let options = ConvertOptions {
+            wildcat_at: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"@1FText @1G@ @Z @ user@example.com", &options);
+        assert!(result.contains("@1FText @1G@ @Z @ user@example.com"));
+        assert!(!result.contains("<ans-1f>"));
+
+        // The at sign after a malformed code can start a new code
+        let result = convert_with_options(b"@4@1F@Hi", &options);
+        assert!(result.contains("@4</ans-07><ans-1f>Hi"));
     }
 
     #[test]
-    fn test_cp437_special_chars() {
-        // Test smiley faces and hearts
-        let input = [0x01, 0x02, 0x03]; // ☺☻♥
-        let result = convert(&input);
-        assert!(result.contains('☺'));
-        assert!(result.contains('☻'));
-        assert!(result.contains('♥'));
+    fn test_wildcat_with_pcboard() {
+        let options = ConvertOptions {
+            pcboard_at_x: true,
+            wildcat_at: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"@X1FA@4E@B@@", &options);
+        assert!(result.contains("<ans-1f>A</ans-1f><ans-4e>B@</ans-4e>"));
     }
 
     #[test]
-    fn test_bright_foreground_colors() {
-        // ESC[91m = bright red
-        let input = b"\x1b[91mBright Red";
-        let result = convert(input);
-        assert!(result.contains("<ans-0c>")); // Light Red on black
+    fn test_wildcat_utf8_input() {
+        let options = ConvertOptions {
+            utf8_input: true,
+            wildcat_at: true,
+            ..Default::default()
+        };
+        let result = convert_with_options("@1F@═@1F═".as_bytes(), &options);
+        assert!(result.contains("<ans-1f>═@1F═</ans-1f>"));
     }
 
+    // ========== WWIV heart code tests ==========
+
     #[test]
-    fn test_bright_background_colors() {
-        // ESC[101m = bright red background
-        let input = b"\x1b[101mBright BG";
-        let result = convert(input);
-        assert!(result.contains("<ans-c7>")); // Light Red bg, Light Gray fg
+    fn test_wwiv_default_colors() {
+        let options = ConvertOptions {
+            wwiv_heart: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x032Yellow\x034Blue bar\x030Normal", &options);
+        assert!(
+            result.contains(
+                "<ans-0e>Yellow</ans-0e><ans-1f>Blue bar</ans-1f><ans-07>Normal</ans-07>"
+            )
+        );
     }
 
     #[test]
-    fn test_bold_makes_bright() {
-        // ESC[1m makes foreground bright, ESC[34m blue -> light blue
-        let input = b"\x1b[1;34mBold Blue";
-        let result = convert(input);
-        assert!(result.contains("<ans-09>")); // Light Blue (9) on black
+    fn test_wwiv_blinking_color() {
+        let options = ConvertOptions {
+            blink_mode: BlinkMode::Blink,
+            wwiv_heart: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x036Alert", &options);
+        assert!(result.contains("<ans-0c blink>Alert</ans-0c>"));
     }
 
     #[test]
-    fn test_reset_colors() {
-        // ESC[31m red, then ESC[0m reset
-        let input = b"\x1b[31mRed\x1b[0mNormal";
-        let result = convert(input);
-        assert!(result.contains("<ans-04>Red</ans-04>"));
-        assert!(result.contains("<ans-07>Normal"));
+    fn test_wwiv_custom_color_table() {
+        let mut colors = WWIV_COLORS;
+        colors[1] = 0x4E;
+        let options = ConvertOptions {
+            wwiv_colors: Some(colors),
+            wwiv_heart: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x031Custom\x039Cyan", &options);
+        assert!(result.contains("<ans-4e>Custom</ans-4e><ans-03>Cyan</ans-03>"));
     }
 
     #[test]
-    fn test_multiple_sgr_params() {
-        // ESC[1;31;44m = bold red on blue
-        let input = b"\x1b[1;31;44mStyled";
-        let result = convert(input);
-        assert!(result.contains("<ans-1c>")); // Blue bg (1), Light Red fg (C)
+    fn test_wwiv_non_digit_code_ignored() {
+        let options = ConvertOptions {
+            wwiv_heart: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"A\x03xB", &options);
+        assert!(result.contains("<ans-07>AB</ans-07>"));
     }
 
     #[test]
-    fn test_full_block_character() {
-        // 0xDB = full block
-        let input = [0xDB];
-        let result = convert(&input);
-        assert!(result.contains('█'));
+    fn test_wwiv_disabled_by_default() {
+        let doc = convert_to_document(b"\x032Text", &ConvertOptions::default());
+        assert_eq!(doc.lines[0].text(), "\u{2665}2Text");
     }
 
     #[test]
-    fn test_shade_characters() {
-        // Test shade blocks
-        let input = [0xB0, 0xB1, 0xB2]; // ░▒▓
-        let result = convert(&input);
-        assert!(result.contains('░'));
-        assert!(result.contains('▒'));
-        assert!(result.contains('▓'));
+    fn test_wwiv_utf8_input() {
+        let options = ConvertOptions {
+            utf8_input: true,
+            wwiv_heart: true,
+            ..Default::default()
+        };
+        let result = convert_with_options("\x035═".as_bytes(), &options);
+        assert!(result.contains("<ans-02>═</ans-02>"));
     }
 
+    // ========== Celerity and Mystic pipe code tests ==========
+
     #[test]
-    fn test_cursor_forward_default() {
-        // ESC[C moves cursor forward 1 position (emits 1 space)
-        let input = b"A\x1b[CB";
-        let result = convert(input);
-        assert!(result.contains("A B"));
+    fn test_celerity_foreground_colors() {
+        let options = ConvertOptions {
+            celerity_pipe: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"|rRed|BBright blue|wGray", &options);
+        assert!(
+            result
+                .contains("<ans-04>Red</ans-04><ans-09>Bright blue</ans-09><ans-07>Gray</ans-07>")
+        );
     }
 
     #[test]
-    fn test_cursor_forward_explicit_one() {
-        // ESC[1C moves cursor forward 1 position
-        let input = b"A\x1b[1CB";
-        let result = convert(input);
-        assert!(result.contains("A B"));
+    fn test_celerity_background_switch() {
+        let options = ConvertOptions {
+            celerity_pipe: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"|S|b|SW|YText", &options);
+        assert!(result.contains("<ans-17>W</ans-17><ans-1e>Text</ans-1e>"));
+    }
+
+    #[test]
+    fn test_celerity_passthrough() {
+        let options = ConvertOptions {
+            celerity_pipe: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"a|x b|07 c||", &options);
+        assert!(result.contains("a|x b|07 c|"));
     }
 
     #[test]
-    fn test_cursor_forward_multiple() {
-        // ESC[5C moves cursor forward 5 positions (emits 5 spaces)
-        let input = b"A\x1b[5CB";
-        let result = convert(input);
-        assert!(result.contains("A     B"));
+    fn test_mystic_colors_and_newline() {
+        let options = ConvertOptions {
+            mystic_mci: true,
+            ..Default::default()
+        };
+        let doc = convert_to_document(b"|12Red|CRNext|16|15Line", &options);
+        assert_eq!(doc.lines[0].text(), "Red");
+        assert_eq!(
+            doc.lines[0].spans[0].style.foreground,
+            ExtendedColor::Cga(12)
+        );
+        assert_eq!(doc.lines[1].text(), "NextLine");
     }
 
     #[test]
-    fn test_cursor_forward_large() {
-        // ESC[10C moves cursor forward 10 positions
-        let input = b"X\x1b[10CY";
-        let result = convert(input);
-        assert!(result.contains("X          Y"));
+    fn test_mystic_clear_screen_and_pause_removed() {
+        let options = ConvertOptions {
+            mystic_mci: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"A|CLB|PA|PN|DEC", &options);
+        assert!(result.contains("A\n\n\nBC"));
     }
 
     #[test]
-    fn test_cursor_forward_zero_treated_as_one() {
-        // ESC[0C should be treated as ESC[1C per ANSI spec
-        let input = b"A\x1b[0CB";
-        let result = convert(input);
-        assert!(result.contains("A B"));
+    fn test_mystic_cursor_positioning() {
+        let options = ConvertOptions {
+            mystic_mci: true,
+            ..Default::default()
+        };
+        let screen_options = ConvertOptions {
+            screen_buffer: true,
+            ..options.clone()
+        };
+        let doc = convert_to_document(b"|[Y03|[X05X|[A01|[D02Y|BSZ", &screen_options);
+        assert_eq!(doc.lines.len(), 3);
+        assert_eq!(doc.lines[1].text(), "   Z");
+        assert_eq!(doc.lines[2].text(), "    X");
+
+        // Formatting codes are removed
+        let result = convert_with_options(b"|@X10A", &options);
+        assert!(result.contains("<ans-07>A</ans-07>"));
     }
 
-    // ========== Synchronet Ctrl-A tests ==========
-
     #[test]
-    fn test_synchronet_foreground_colors() {
+    fn test_mystic_unknown_codes_passthrough() {
         let options = ConvertOptions {
-            synchronet_ctrl_a: true,
+            mystic_mci: true,
             ..Default::default()
         };
-        // Ctrl-A + r (lowercase) = red foreground
-        let input = b"\x01rRed Text";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-04>")); // Red on black
+        let result = convert_with_options(b"|ZZ |Ab |[x |[X1y |@ ", &options);
+        assert!(result.contains("|ZZ |Ab |[x |[X1y |@ "));
     }
 
     #[test]
-    fn test_synchronet_background_color_uppercase() {
+    fn test_mystic_with_celerity() {
         let options = ConvertOptions {
-            synchronet_ctrl_a: true,
+            celerity_pipe: true,
+            mystic_mci: true,
             ..Default::default()
         };
-        // Ctrl-A + R (uppercase) = red background
-        let input = b"\x01RRed BG";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-47>")); // Red bg (4), Light Gray fg (7)
+        let result = convert_with_options(b"|CLx|Cy|cz", &options);
+        assert!(result.contains("<ans-07>\n\n\nx</ans-07><ans-0b>y</ans-0b><ans-03>z</ans-03>"));
     }
 
     #[test]
-    fn test_synchronet_background_color_digit() {
+    fn test_mystic_utf8_input() {
         let options = ConvertOptions {
-            synchronet_ctrl_a: true,
+            utf8_input: true,
+            celerity_pipe: true,
+            mystic_mci: true,
             ..Default::default()
         };
-        // Ctrl-A + 1 = blue background
-        let input = b"\x011Blue BG";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-17>")); // Blue bg, Light Gray fg
+        let doc = convert_to_document("═|CR|G═|Z═".as_bytes(), &options);
+        assert_eq!(doc.lines[0].text(), "═");
+        assert_eq!(doc.lines[1].text(), "═|Z═");
+        assert_eq!(
+            doc.lines[1].spans[0].style.foreground,
+            ExtendedColor::Cga(10)
+        );
     }
 
+    // ========== Synchronet control code tests ==========
+
     #[test]
-    fn test_synchronet_high_intensity_foreground() {
+    fn test_synchronet_clear_screen() {
         let options = ConvertOptions {
             synchronet_ctrl_a: true,
             ..Default::default()
         };
-        // Ctrl-A + b (blue fg) + Ctrl-A + h (high intensity) = bright blue
-        let input = b"\x01b\x01hBright Blue";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-09>")); // Light Blue on black
+        let screen_options = ConvertOptions {
+            screen_buffer: true,
+            ..options.clone()
+        };
+        let result = convert_with_options(b"Old\x01LNew", &options);
+        assert!(result.contains("Old\n\n\nNew"));
+
+        let doc = convert_to_document(b"Old\r\nText\x01LNew", &screen_options);
+        assert_eq!(doc.lines.len(), 1);
+        assert_eq!(doc.lines[0].text(), "New");
     }
 
     #[test]
-    fn test_synchronet_high_intensity_background() {
+    fn test_synchronet_cursor_codes() {
         let options = ConvertOptions {
             synchronet_ctrl_a: true,
+            screen_buffer: true,
             ..Default::default()
         };
-        // Ctrl-A + B (blue bg) + Ctrl-A + i (blink/high intensity bg) = bright blue bg
-        let input = b"\x01B\x01iBright Blue BG";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-97>")); // Light Blue bg (9), Light Gray fg (7)
+        // Carriage return, cursor left, clear to end of line and line feed
+        let doc = convert_to_document(b"ABCDEF\x01[X\x01>\x01]Y\x01<Z\x01'H", &options);
+        assert_eq!(doc.lines[0].text(), "H");
+        // Line feed keeps the column
+        assert_eq!(doc.lines[1].text(), " Z");
     }
 
     #[test]
-    fn test_synchronet_normal_reset() {
+    fn test_synchronet_conditional_newline() {
         let options = ConvertOptions {
             synchronet_ctrl_a: true,
             ..Default::default()
         };
-        // Ctrl-A + r (red fg) then Ctrl-A + n = reset to normal
-        let input = b"\x01rRed\x01nNormal";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-04>Red</ans-04>"));
-        assert!(result.contains("<ans-07>Normal"));
-    }
+        let screen_options = ConvertOptions {
+            screen_buffer: true,
+            ..options.clone()
+        };
+        let doc = convert_to_document(b"\x01/A\x01/\x01/B", &options);
+        let lines: Vec<String> = doc.lines.iter().map(|line| line.text()).collect();
+        assert_eq!(lines, ["A", "B"]);
 
-    #[test]
-    fn test_synchronet_disabled_by_default() {
-        // Without option, Ctrl-A should be treated as CP437 character (smiley)
-        let input = b"\x01rText";
-        let result = convert(input);
-        assert!(result.contains('☺')); // CP437 0x01 = smiley face
-        assert!(result.contains("rText"));
+        let doc = convert_to_document(b"\x01/A\x01/\x01/B", &screen_options);
+        let lines: Vec<String> = doc.lines.iter().map(|line| line.text()).collect();
+        assert_eq!(lines, ["A", "B"]);
     }
 
     #[test]
-    fn test_synchronet_preserves_intensity() {
+    fn test_synchronet_move_right() {
         let options = ConvertOptions {
             synchronet_ctrl_a: true,
             ..Default::default()
         };
-        // Set high intensity first, then change color - intensity should be preserved
-        let input = b"\x01h\x01bBright Blue";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-09>")); // Light Blue (high intensity preserved)
+        let screen_options = ConvertOptions {
+            screen_buffer: true,
+            ..options.clone()
+        };
+        let result = convert_with_options(b"A\x01\x83B\x01\x7fC", &options);
+        assert!(result.contains("A    BC"));
+
+        let doc = convert_to_document(b"ABCDEF\r\x01\x82X", &screen_options);
+        assert_eq!(doc.lines[0].text(), "ABCXEF");
     }
 
     #[test]
-    fn test_synchronet_combined_fg_bg() {
+    fn test_synchronet_end_of_message() {
         let options = ConvertOptions {
             synchronet_ctrl_a: true,
             ..Default::default()
         };
-        // Ctrl-A + w (white fg) + Ctrl-A + B (blue bg)
-        let input = b"\x01w\x01BWhite on Blue";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-17>")); // Blue bg (1), Light Gray fg (7)
+        let result = convert_with_options(b"Shown\x01ZHidden\x01rRed", &options);
+        assert!(result.contains("Shown"));
+        assert!(!result.contains("Hidden"));
+        assert!(!result.contains("Red"));
     }
 
     #[test]
-    fn test_synchronet_intensity_idempotent() {
+    fn test_synchronet_file_display_and_pauses_removed() {
         let options = ConvertOptions {
             synchronet_ctrl_a: true,
             ..Default::default()
         };
-        // Applying high intensity multiple times should have same effect as once
-        let input = b"\x01b\x01h\x01hDouble High";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-09>")); // Light Blue (9), not something weird
+        let result = convert_with_options(b"A\x01\"MENU.ASC\r\nB\x01P\x01,C", &options);
+        assert!(result.contains("A\nBC"));
+        assert!(!result.contains("MENU"));
     }
 
     #[test]
-    fn test_synchronet_blink_idempotent() {
+    fn test_synchronet_controls_keep_colors() {
         let options = ConvertOptions {
             synchronet_ctrl_a: true,
             ..Default::default()
         };
-        // Applying blink/high bg multiple times should have same effect as once
-        let input = b"\x01B\x01i\x01iDouble Blink BG";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-97>")); // Light Blue bg (9), Light Gray fg (7)
+        let result = convert_with_options(b"\x01r\x01hRed\x01/\x01>More", &options);
+        assert!(result.contains("<ans-0c>Red\nMore</ans-0c>"));
     }
 
-    // ========== Renegade pipe code tests ==========
+    // ========== Macro substitution tests ==========
 
     #[test]
-    fn test_renegade_foreground_colors() {
+    fn test_macro_substitution() {
         let options = ConvertOptions {
-            renegade_pipe: true,
+            macros: Some(Arc::new(|code: Macro<'_>| match code {
+                Macro::AtCode("ALIAS") => Some("Sysop".to_string()),
+                Macro::AtCode("BBS") => Some("Land".to_string()),
+                _ => None,
+            })),
             ..Default::default()
         };
-        // |04 = red foreground
-        let input = b"|04Red Text";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-04>")); // Red on black
+        let result = convert_with_options(b"Hi @ALIAS@ on @BBS@", &options);
+        assert!(result.contains("Hi Sysop on Land"));
     }
 
     #[test]
-    fn test_renegade_bright_foreground() {
+    fn test_macro_unknown_and_text_left_verbatim() {
         let options = ConvertOptions {
-            renegade_pipe: true,
+            macros: Some(Arc::new(|code: Macro<'_>| match code {
+                Macro::AtCode("ALIAS") => Some("Sysop".to_string()),
+                _ => None,
+            })),
             ..Default::default()
         };
-        // |12 = bright red (Light Red)
-        let input = b"|12Bright Red";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-0c>")); // Light Red on black
+        let result = convert_with_options(b"@NOPE@ a@b.com @ALIAS 50@ x", &options);
+        assert!(result.contains("@NOPE@ a@b.com @ALIAS 50@ x"));
+
+        // Without a provider, macros are plain text
+        let result = convert_with_options(b"Hi @ALIAS@", &ConvertOptions::default());
+        assert!(result.contains("Hi @ALIAS@"));
     }
 
     #[test]
-    fn test_renegade_background_color() {
+    fn test_macro_closing_at_starts_next_code() {
         let options = ConvertOptions {
-            renegade_pipe: true,
+            macros: Some(Arc::new(|code: Macro<'_>| match code {
+                Macro::AtCode("ALIAS") => Some("Sysop".to_string()),
+                _ => None,
+            })),
             ..Default::default()
         };
-        // |17 = blue background
-        let input = b"|17Blue BG";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-17>")); // Blue bg, Light Gray fg
+        let result = convert_with_options(b"@NOPE@ALIAS@", &options);
+        assert!(result.contains("@NOPESysop"));
     }
 
     #[test]
-    fn test_renegade_combined_colors() {
+    fn test_macro_with_color_codes() {
         let options = ConvertOptions {
-            renegade_pipe: true,
+            pcboard_at_x: true,
+            wildcat_at: true,
+            macros: Some(Arc::new(|code: Macro<'_>| match code {
+                Macro::AtCode("ALIAS") => Some("Sysop".to_string()),
+                Macro::AtCode("BBS") => Some("Land".to_string()),
+                Macro::AtCode("XTIME") => Some("12:00".to_string()),
+                _ => None,
+            })),
             ..Default::default()
         };
-        // |15 = white fg, |20 = red bg
-        let input = b"|15|20White on Red";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-4f>")); // Red bg, White fg
+        let result = convert_with_options(b"@X1F@XTIME@ @BBS@ @0E@@ALIAS@", &options);
+        assert!(result.contains("<ans-1f>12:00 Land </ans-1f>"));
+        assert!(result.contains("<ans-0e>Sysop</ans-0e>"));
     }
 
     #[test]
-    fn test_renegade_disabled_by_default() {
-        // Without option, pipe should be passed through
-        let input = b"|04Text";
-        let result = convert(input);
-        assert!(result.contains("|04Text"));
+    fn test_macro_mystic_mci() {
+        let options = ConvertOptions {
+            mystic_mci: true,
+            macros: Some(Arc::new(|code: Macro<'_>| match code {
+                Macro::Mci("UN") => Some("Sysop".to_string()),
+                _ => None,
+            })),
+            ..Default::default()
+        };
+        let result = convert_with_options(b"Hi |UN|XY", &options);
+        assert!(result.contains("Hi Sysop|XY"));
     }
 
     #[test]
-    fn test_renegade_invalid_code_passthrough() {
+    fn test_macro_utf8_input() {
         let options = ConvertOptions {
-            renegade_pipe: true,
+            utf8_input: true,
+            macros: Some(Arc::new(|code: Macro<'_>| match code {
+                Macro::AtCode("ALIAS") => Some("Sysop".to_string()),
+                _ => None,
+            })),
             ..Default::default()
         };
-        // |99 is invalid (>23), should be ignored but not crash
-        let input = b"|99Text";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("Text"));
+        let result = convert_with_options("Héllo @ALIAS@ @ÉTÉ@!".as_bytes(), &options);
+        assert!(result.contains("Héllo Sysop @ÉTÉ@!"));
     }
 
+    // ========== AVATAR code tests ==========
+
     #[test]
-    fn test_renegade_incomplete_code_passthrough() {
+    fn test_avatar_attribute() {
         let options = ConvertOptions {
-            renegade_pipe: true,
+            avatar: true,
             ..Default::default()
         };
-        // |0X is not a valid code (X is not a digit)
-        let input = b"|0XText";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("|0XText"));
+        let result = convert_with_options(b"\x16\x01\x1fWhite on blue\x16\x01\x0eYellow", &options);
+        assert!(result.contains("<ans-1f>White on blue</ans-1f>"));
+        assert!(result.contains("<ans-0e>Yellow</ans-0e>"));
+
+        // The high bit is masked; blink comes from ^V^B
+        let result = convert_with_options(b"\x16\x01\x9eA\x16\x02B", &options);
+        assert!(result.contains("<ans-1e>A</ans-1e><ans-9e>B</ans-9e>"));
     }
 
     #[test]
-    fn test_renegade_pipe_literal() {
+    fn test_avatar_repeat() {
         let options = ConvertOptions {
-            renegade_pipe: true,
+            avatar: true,
             ..Default::default()
         };
-        // Single | followed by non-digit should be passed through
-        let input = b"|Hello";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("|Hello"));
+        let result = convert_with_options(b"[\x19-\x05]\x19\xc4\x03", &options);
+        assert!(result.contains("[-----]───"));
+
+        let options = ConvertOptions {
+            utf8_input: true,
+            ..options
+        };
+        let result = convert_with_options("\x19é\x03".as_bytes(), &options);
+        assert!(result.contains("ééé"));
     }
 
-    // ========== Combined options tests ==========
+    #[test]
+    fn test_avatar_clear_screen() {
+        let options = ConvertOptions {
+            avatar: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"Old\x0cNew", &options);
+        assert!(result.contains("Old\n\n\n"));
+        assert!(result.contains("<ans-03>New</ans-03>"));
+
+        // Without the option, ^L is the CP437 female sign
+        let result = convert(b"A\x0cB");
+        assert!(result.contains("A\u{2640}B"));
+    }
 
     #[test]
-    fn test_both_formats_enabled() {
+    fn test_avatar_cursor_codes() {
         let options = ConvertOptions {
-            synchronet_ctrl_a: true,
-            renegade_pipe: true,
+            screen_buffer: true,
+            avatar: true,
             ..Default::default()
         };
-        // Mix of both formats
-        let input = b"\x01rSync |09Renegade";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-04>")); // Red from Synchronet
-        assert!(result.contains("<ans-09>")); // Light Blue from Renegade
+        // Position to row 2, column 3, then up, left and right
+        let doc = convert_to_document(
+            b"ABCDEF\x16\x08\x02\x03X\x16\x03\x16\x05\x16\x05Y\x16\x06Z",
+            &options,
+        );
+        assert_eq!(doc.lines[0].text(), "AYCZEF");
+        assert_eq!(doc.lines[1].text(), "  X");
+
+        // Clear to end of line
+        let doc = convert_to_document(b"ABCDEF\r\x16\x06\x16\x07", &options);
+        assert_eq!(doc.lines[0].text(), "A");
     }
 
-    // ========== UTF-8 input mode tests ==========
+    // ========== mIRC color code tests ==========
 
     #[test]
-    fn test_utf8_input_basic() {
+    fn test_mirc_colors() {
         let options = ConvertOptions {
-            utf8_input: true,
+            mirc_colors: true,
             ..Default::default()
         };
-        // UTF-8 text with Unicode characters should pass through
-        let input = "Hello, 世界!".as_bytes();
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("Hello, 世界!"));
+        let result = convert_with_options(b"\x034Red\x0308,02Yellow on blue\x03Plain", &options);
+        assert!(result.contains("<ans-0c>Red</ans-0c>"));
+        assert!(result.contains("<ans-1e>Yellow on blue</ans-1e>"));
+        assert!(result.contains("<ans-07>Plain</ans-07>"));
+
+        // Light backgrounds use the high-intensity bit, which later colors clear
+        let result = convert_with_options(b"\x031,4A\x031,2B", &options);
+        assert!(result.contains("<ans-c0>A</ans-c0><ans-10>B</ans-10>"));
     }
 
     #[test]
-    fn test_utf8_input_control_chars() {
+    fn test_mirc_digits_and_commas() {
         let options = ConvertOptions {
-            utf8_input: true,
+            mirc_colors: true,
             ..Default::default()
         };
-        // Control char 0x01 (smiley in CP437) should still be converted
-        let input = b"\x01 Hello";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains('☺')); // CP437 0x01 = smiley
-        assert!(result.contains("Hello"));
+        // At most two digits per color; a comma without a background digit is text
+        let result = convert_with_options(b"\x03123 apples\x033, pears", &options);
+        assert!(result.contains("<ans-09>3 apples</ans-09><ans-02>, pears</ans-02>"));
     }
 
     #[test]
-    fn test_utf8_input_ansi_codes() {
+    fn test_mirc_extended_colors() {
         let options = ConvertOptions {
-            utf8_input: true,
+            mirc_colors: true,
             ..Default::default()
         };
-        // ANSI codes should still work in UTF-8 mode
-        let input = "\x1b[31mRed 日本語\x1b[0m".as_bytes();
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-04>")); // Red
-        assert!(result.contains("日本語"));
+        let doc = convert_to_document(b"\x0352,88X\x0399,99Y", &options);
+        let spans = &doc.lines[0].spans;
+        assert_eq!(spans[0].style.foreground, ExtendedColor::Palette(196));
+        assert_eq!(spans[0].style.background, ExtendedColor::Palette(16));
+        assert_eq!(spans[1].style.foreground, ExtendedColor::Cga(7));
+        assert_eq!(spans[1].style.background, ExtendedColor::Cga(0));
     }
 
     #[test]
-    fn test_utf8_input_with_renegade() {
+    fn test_mirc_formatting() {
         let options = ConvertOptions {
-            utf8_input: true,
-            renegade_pipe: true,
+            mirc_colors: true,
             ..Default::default()
         };
-        // Renegade codes with UTF-8 text
-        let input = "|04Red |02Grün".as_bytes();
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-04>")); // Red
-        assert!(result.contains("<ans-02>")); // Green
-        assert!(result.contains("Grün")); // German umlaut preserved
+        let doc = convert_to_document(b"\x02\x032B\x02N\x1fU\x1d\x1eI\x0fR\x16V", &options);
+        let spans = &doc.lines[0].spans;
+        let texts: Vec<&str> = spans.iter().map(|span| span.text.as_str()).collect();
+        assert_eq!(texts, ["B", "N", "U", "I", "R", "V"]);
+        assert_eq!(spans[0].style.foreground, ExtendedColor::Cga(9));
+        assert_eq!(spans[1].style.foreground, ExtendedColor::Cga(1));
+        assert!(spans[2].style.attributes.underline);
+        assert!(spans[3].style.attributes.italic && spans[3].style.attributes.strikethrough);
+        assert_eq!(spans[4].style.attributes, Attributes::default());
+        assert_eq!(spans[4].style.foreground, ExtendedColor::Cga(7));
+        assert_eq!(spans[5].style.foreground, ExtendedColor::Cga(0));
+        assert_eq!(spans[5].style.background, ExtendedColor::Cga(7));
     }
 
-    // ========== SAUCE metadata parsing tests ==========
+    #[test]
+    fn test_mirc_disabled() {
+        let result = convert(b"\x034Red");
+        assert!(result.contains("\u{2665}4Red"));
+    }
+
+    // ========== Door game backtick code tests ==========
 
     #[test]
-    fn test_sub_without_sauce_stops_processing() {
-        // SUB without valid SAUCE record - content after SUB is ignored
-        let input = b"Visible\x1aRandom garbage after SUB";
-        let result = convert(input);
-        assert!(result.contains("Visible"));
-        assert!(!result.contains("Random"));
-        assert!(!result.contains("garbage"));
+    fn test_door_colors() {
+        let options = ConvertOptions {
+            door_backtick: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"`2Green `0Bright `%White", &options);
+        assert!(result.contains("<ans-02>Green </ans-02>"));
+        assert!(result.contains("<ans-0a>Bright </ans-0a>"));
+        assert!(result.contains("<ans-0f>White</ans-0f>"));
     }
 
     #[test]
-    fn test_sauce_record_parsed_and_displayed() {
-        // Create a minimal valid SAUCE record (128 bytes)
-        let mut input = b"Content before SAUCE\x1a".to_vec();
-        // SAUCE00 header
-        input.extend_from_slice(b"SAUCE00");
-        // Title (35 bytes) - "Test Title" padded with spaces
-        input.extend_from_slice(b"Test Title                         ");
-        // Author (20 bytes)
-        input.extend_from_slice(b"Test Author         ");
-        // Group (20 bytes)
-        input.extend_from_slice(b"Test Group          ");
-        // Date (8 bytes) - CCYYMMDD
-        input.extend_from_slice(b"20240115");
-        // FileSize (4 bytes) - little endian
-        input.extend_from_slice(&[0, 0, 0, 0]);
-        // DataType (1 byte)
-        input.push(1);
-        // FileType (1 byte)
-        input.push(1);
-        // TInfo1-4 (8 bytes) - width=80, height=25
-        input.extend_from_slice(&[80, 0, 25, 0, 0, 0, 0, 0]);
-        // Comments (1 byte)
-        input.push(0);
-        // TFlags (1 byte)
-        input.push(0);
-        // TInfoS (22 bytes) - font name
-        input.extend_from_slice(b"IBM VGA\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+    fn test_door_background() {
+        let options = ConvertOptions {
+            door_backtick: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"`$`r1Yellow on blue`r0Black", &options);
+        assert!(result.contains("<ans-1e>Yellow on blue</ans-1e><ans-0e>Black</ans-0e>"));
+    }
 
-        let result = convert(&input);
-        assert!(result.contains("Content before SAUCE"));
-        assert!(result.contains("Title: Test Title"));
-        assert!(result.contains("Author: Test Author"));
-        assert!(result.contains("Group: Test Group"));
-        assert!(result.contains("Date: 2024-01-15"));
-        assert!(result.contains("Size: 80x25"));
-        assert!(result.contains("Font: IBM VGA"));
+    #[test]
+    fn test_door_invalid_codes_pass_through() {
+        let options = ConvertOptions {
+            door_backtick: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"It`s `r9 ``1Red", &options);
+        assert!(result.contains("It`s `r9 `"));
+        assert!(result.contains("<ans-01>Red</ans-01>"));
+
+        let result = convert(b"`2Green");
+        assert!(result.contains("`2Green"));
     }
 
-    #[test]
-    fn test_sauce_with_comnt_block() {
-        // Create input with COMNT block before SAUCE
-        let mut input = b"Art content\x1a".to_vec();
-        // COMNT header + one 64-byte comment line
-        input.extend_from_slice(b"COMNT");
-        input.extend_from_slice(b"This is a comment line for the ANSI art.                       ");
-        // SAUCE00 header
-        input.extend_from_slice(b"SAUCE00");
-        // Title (35 bytes)
-        input.extend_from_slice(b"Artwork Title                      ");
-        // Author (20 bytes)
-        input.extend_from_slice(b"Artist              ");
-        // Group (20 bytes)
-        input.extend_from_slice(b"                    ");
-        // Date (8 bytes)
-        input.extend_from_slice(b"20230701");
-        // FileSize (4 bytes)
-        input.extend_from_slice(&[0, 0, 0, 0]);
-        // DataType, FileType
-        input.extend_from_slice(&[1, 1]);
-        // TInfo1-4 (8 bytes)
-        input.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
-        // Comments count (1 byte) - 1 comment
-        input.push(1);
-        // TFlags (1 byte)
-        input.push(0);
-        // TInfoS (22 bytes)
-        input.extend_from_slice(b"\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+    // ========== ANSI music tests ==========
 
-        let result = convert(&input);
-        assert!(result.contains("Art content"));
-        assert!(result.contains("Title: Artwork Title"));
-        assert!(result.contains("Author: Artist"));
-        assert!(result.contains("Comment: This is a comment line for the ANSI art."));
+    #[test]
+    fn test_music_removed_from_output() {
+        let options = ConvertOptions {
+            ansi_music: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"Intro\x1b[MFT120O4L8CDEFG\x0e done", &options);
+        assert!(result.contains("Intro done"));
+        assert!(!result.contains("CDEFG"));
+        assert!(!result.contains("ans-music"));
+
+        // ESC[N form, ended by a line break instead of Ctrl-N
+        let result = convert_with_options(b"A\x1b[NL4C\r\nB", &options);
+        assert!(result.contains("A\nB"));
     }
 
     #[test]
-    fn test_content_after_sauce_continues() {
-        // Create input with content after SAUCE record
-        let mut input = b"Before SAUCE\x1a".to_vec();
-        // Minimal SAUCE record (128 bytes)
-        input.extend_from_slice(b"SAUCE00");
-        input.extend_from_slice(b"Title                              "); // 35
-        input.extend_from_slice(b"                    "); // 20 author
-        input.extend_from_slice(b"                    "); // 20 group
-        input.extend_from_slice(b"        "); // 8 date
-        input.extend_from_slice(&[0u8; 4]); // filesize
-        input.extend_from_slice(&[0, 0]); // datatype, filetype
-        input.extend_from_slice(&[0u8; 8]); // tinfo1-4
-        input.push(0); // comments
-        input.push(0); // tflags
-        input.extend_from_slice(&[0u8; 22]); // tinfos
-        // Content after SAUCE
-        input.extend_from_slice(b"Content after SAUCE record");
+    fn test_music_disabled_by_default() {
+        let result = convert(b"\x1b[MHello world\nnext");
+        assert!(result.contains("Hello world\nnext"));
+        let result = convert(b"line1\x1b[Nsome text here\r\n");
+        assert!(result.contains("line1some text here\n"));
+        assert!(
+            convert_to_document(b"\x1b[MCDE\x0e", &ConvertOptions::default())
+                .music
+                .is_empty()
+        );
+    }
 
-        let result = convert(&input);
-        assert!(result.contains("Before SAUCE"));
-        assert!(result.contains("Title: Title"));
-        assert!(result.contains("Content after SAUCE record"));
+    #[test]
+    fn test_music_delete_line_with_parameters_is_not_music() {
+        let options = ConvertOptions {
+            ansi_music: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"A\x1b[2MB", &options);
+        assert!(result.contains("AB"));
     }
 
     #[test]
-    fn test_sauce_utf8_mode() {
+    fn test_music_unterminated_at_end_of_input() {
         let options = ConvertOptions {
-            utf8_input: true,
+            ansi_music: true,
             ..Default::default()
         };
-        // Create input with UTF-8 content and SAUCE
-        let mut input = b"Hello UTF-8 \xc3\xa9\x1a".to_vec(); // é in UTF-8
-        // Full SAUCE record (128 bytes total)
-        // SAUCE00 (7) + Title (35) + Author (20) + Group (20) + Date (8) +
-        // FileSize (4) + DataType (1) + FileType (1) + TInfo1-4 (8) +
-        // Comments (1) + TFlags (1) + TInfoS (22) = 128
-        input.extend_from_slice(b"SAUCE00");                           // 7 bytes
-        input.extend_from_slice(b"UTF-8 Test                         "); // 35 bytes
-        input.extend_from_slice(&[b' '; 20]);                          // 20 bytes author
-        input.extend_from_slice(&[b' '; 20]);                          // 20 bytes group
-        input.extend_from_slice(b"        ");                          // 8 bytes date
-        input.extend_from_slice(&[0u8; 4]);                            // 4 bytes filesize
-        input.extend_from_slice(&[1, 1]);                              // 2 bytes datatype, filetype
-        input.extend_from_slice(&[0u8; 8]);                            // 8 bytes tinfo1-4
-        input.push(0);                                                 // 1 byte comments
-        input.push(0);                                                 // 1 byte tflags
-        input.extend_from_slice(&[0u8; 22]);                           // 22 bytes tinfos
+        let doc = convert_to_document(b"A\x1b[MCDE", &options);
+        assert_eq!(doc.lines[0].text(), "A");
+        assert_eq!(doc.music.len(), 1);
+        assert_eq!(doc.music[0].len(), 3);
+
+        let mut stream = StreamConverter::new(ConvertOptions {
+            music_elements: true,
+            ..options
+        });
+        let mut html = stream.push(b"A\x1b[MT120O3L4A");
+        html.push_str(&stream.finish());
+        assert!(html.contains("<ans-music notes=\"440.00,500,437\"></ans-music>"));
+    }
 
-        let result = convert_with_options(&input, &options);
-        assert!(result.contains("Hello UTF-8 é"));
-        assert!(result.contains("Title: UTF-8 Test"));
+    #[test]
+    fn test_music_dots_are_capped() {
+        let notes = parse_music(&format!("T32L1C{}", ".".repeat(120)));
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].dots, 4);
+        assert_eq!(
+            notes[0].duration(),
+            std::time::Duration::from_secs_f64(7.5 * 1.5f64.powi(4))
+        );
+        let note = MusicNote {
+            dots: u8::MAX,
+            ..notes[0]
+        };
+        assert_eq!(note.duration(), notes[0].duration());
+
+        let options = ConvertOptions {
+            music_elements: true,
+            ansi_music: true,
+            ..Default::default()
+        };
+        let input = format!("\x1b[MFT32L1C{}\x0e", ".".repeat(120));
+        assert!(convert_with_options(input.as_bytes(), &options).contains("<ans-music"));
     }
 
-    // ========== 256-color and RGB support tests ==========
+    #[test]
+    fn test_music_document_events() {
+        let options = ConvertOptions {
+            ansi_music: true,
+            ..Default::default()
+        };
+        let doc = convert_to_document(b"\x1b[MBT240O3ML A8.>C-P4\x0eText\x1b[NN1\x0e", &options);
+        assert_eq!(doc.lines[0].text(), "Text");
+        assert_eq!(doc.music.len(), 2);
+
+        let notes = &doc.music[0];
+        assert_eq!(notes.len(), 3);
+        assert_eq!(
+            (notes[0].note, notes[0].octave, notes[0].tempo),
+            (Some(9), 3, 240)
+        );
+        assert_eq!((notes[0].length, notes[0].dots), (8, 1));
+        assert_eq!(notes[0].articulation, Articulation::Legato);
+        assert_eq!(notes[0].duration().as_millis(), 187);
+        // C flat of octave 4 is B of octave 3
+        assert_eq!((notes[1].note, notes[1].octave), (Some(11), 3));
+        assert_eq!(notes[2].note, None);
+        assert_eq!(notes[2].sounding_duration(), std::time::Duration::ZERO);
+
+        assert_eq!((doc.music[1][0].note, doc.music[1][0].octave), (Some(0), 0));
+    }
 
     #[test]
-    fn test_256_color_foreground() {
-        // ESC[38;5;196m = 256-color foreground, color 196 (bright red in cube)
-        let input = b"\x1b[38;5;196mRed 256";
-        let result = convert(input);
-        assert!(result.contains("<ans-256 fg=\"196\" bg=\"bg-0\">"));
-        assert!(result.contains("Red 256"));
-        assert!(result.contains("</ans-256>"));
+    fn test_music_elements() {
+        let options = ConvertOptions {
+            music_elements: true,
+            ansi_music: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"A\x1b[MT120O3L4A P2\x0eB", &options);
+        assert!(result.contains("A<ans-music notes=\"440.00,500,437 0.00,1000,0\"></ans-music>B"));
     }
 
     #[test]
-    fn test_256_color_background() {
-        // ESC[48;5;21m = 256-color background, color 21 (blue in cube)
-        let input = b"\x1b[48;5;21mBlue BG";
-        let result = convert(input);
-        assert!(result.contains("<ans-256 fg=\"fg-7\" bg=\"21\">"));
-        assert!(result.contains("Blue BG"));
+    fn test_music_split_across_chunks() {
+        let options = ConvertOptions {
+            ansi_music: true,
+            ..Default::default()
+        };
+        let mut stream = StreamConverter::new(options);
+        let mut html = stream.push(b"X\x1b[MC");
+        html.push_str(&stream.push(b"DE\x0eY"));
+        html.push_str(&stream.finish());
+        assert!(html.contains("XY"));
     }
 
+    // ========== RIPscrip tests ==========
+
     #[test]
-    fn test_256_color_both() {
-        // ESC[38;5;226;48;5;21m = yellow fg (226) on blue bg (21)
-        let input = b"\x1b[38;5;226;48;5;21mYellow on Blue";
-        let result = convert(input);
-        assert!(result.contains("<ans-256 fg=\"226\" bg=\"21\">"));
+    fn test_ripscrip_draws_svg() {
+        let options = ConvertOptions {
+            ripscrip: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"Hello\r\n!|c0E|L00000A0A|R0A0A0K0K\r\nWorld", &options);
+        assert!(result.contains("Hello\nWorld"));
+        assert!(!result.contains("!|"));
+        assert!(result.contains("<svg class=\"rip\""));
+        assert!(result.contains(
+            "<line x1=\"0\" y1=\"0\" x2=\"10\" y2=\"10\" style=\"stroke:var(--rip-14);stroke-width:1\"/>"
+        ));
+        assert!(result.contains(
+            "<rect x=\"10\" y=\"10\" width=\"11\" height=\"11\" style=\"fill:none;stroke:var(--rip-14);stroke-width:1\"/>"
+        ));
     }
 
     #[test]
-    fn test_rgb_foreground() {
-        // ESC[38;2;255;128;0m = RGB foreground (orange)
-        let input = b"\x1b[38;2;255;128;0mOrange";
-        let result = convert(input);
-        assert!(result.contains("<ans-rgb fg=\"255,128,0\" bg=\"bg-0\">"));
-        assert!(result.contains("Orange"));
-        assert!(result.contains("</ans-rgb>"));
+    fn test_ripscrip_only_at_line_start() {
+        let options = ConvertOptions {
+            ripscrip: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"Hi !|L00000A0A\n!x", &options);
+        assert!(result.contains("Hi !|L00000A0A\n!x"));
+        assert!(!result.contains("<svg"));
+
+        let result = convert(b"!|L00000A0A\n");
+        assert!(result.contains("!|L00000A0A"));
+        assert!(!result.contains("<svg"));
     }
 
     #[test]
-    fn test_rgb_background() {
-        // ESC[48;2;0;64;128m = RGB background (dark blue)
-        let input = b"\x1b[48;2;0;64;128mDark Blue BG";
-        let result = convert(input);
-        assert!(result.contains("<ans-rgb fg=\"fg-7\" bg=\"0,64,128\">"));
-        assert!(result.contains("Dark Blue BG"));
+    fn test_ripscrip_text_continuation_and_erase() {
+        let options = ConvertOptions {
+            ripscrip: true,
+            ..Default::default()
+        };
+        let doc = convert_to_document(
+            b"!|c0F|@0A0Ba\\|b<\\\n|X0101\n!|e|L00000101\nText",
+            &options,
+        );
+        assert_eq!(doc.lines[0].text(), "Text");
+        assert_eq!(doc.graphics.len(), 2);
+        assert!(doc.graphics[0].contains("<text x=\"10\" y=\"11\""));
+        assert!(doc.graphics[0].contains(">a|b&lt;</text>"));
+        assert!(doc.graphics[0].contains("<rect x=\"1\" y=\"1\" width=\"1\" height=\"1\""));
+        assert!(!doc.graphics[1].contains("<text"));
+        assert!(doc.graphics[1].contains("<line x1=\"0\" y1=\"0\" x2=\"1\" y2=\"1\""));
     }
 
     #[test]
-    fn test_rgb_both() {
-        // ESC[38;2;255;255;0;48;2;128;0;128m = yellow fg on purple bg
-        let input = b"\x1b[38;2;255;255;0;48;2;128;0;128mYellow on Purple";
-        let result = convert(input);
-        assert!(result.contains("<ans-rgb fg=\"255,255,0\" bg=\"128,0,128\">"));
+    fn test_ripscrip_graphics_keep_their_place_in_screen_buffer() {
+        let options = ConvertOptions {
+            ripscrip: true,
+            screen_buffer: true,
+            ..Default::default()
+        };
+        // Graphics flushed by an erase sit where they were received
+        let result = convert_with_options(b"Before\r\n!|L00000A0A|e\r\nAfter", &options);
+        let svg = result.find("<svg").unwrap();
+        assert!(result.find("Before").unwrap() < svg);
+        assert!(svg < result.find("After").unwrap());
+
+        // Graphics still drawing at the end follow all of the text
+        let result = convert_with_options(b"Before\r\n!|L00000A0A\r\nAfter", &options);
+        assert!(result.find("After").unwrap() < result.find("<svg").unwrap());
     }
 
     #[test]
-    fn test_extended_color_reset() {
-        // Start with 256-color, then reset to default
-        let input = b"\x1b[38;5;196mRed\x1b[0mNormal";
-        let result = convert(input);
-        assert!(result.contains("<ans-256 fg=\"196\""));
-        assert!(result.contains("Red"));
-        assert!(result.contains("</ans-256>"));
-        assert!(result.contains("<ans-07>Normal"));
+    fn test_ripscrip_palette() {
+        let options = ConvertOptions {
+            ripscrip: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"!|a0F04|X0000\n", &options);
+        assert!(result.contains("--rip-6:#aa5500"));
+        assert!(result.contains("--rip-15:#aa0000"));
     }
 
+    // ========== Control string and Sixel tests ==========
+
     #[test]
-    fn test_switch_cga_to_256() {
-        // Start with CGA red, then switch to 256-color
-        let input = b"\x1b[31mCGA Red\x1b[38;5;196m256 Red";
-        let result = convert(input);
-        assert!(result.contains("<ans-04>CGA Red</ans-04>"));
-        assert!(result.contains("<ans-256 fg=\"196\""));
-        assert!(result.contains("256 Red"));
+    fn test_control_strings_removed() {
+        let result = convert(b"A\x1bPq#1~~\x1b\\B\x1b]0;title\x07C\x1b_apc\x1b\\D");
+        assert!(result.contains("ABCD"));
+        assert!(!result.contains("<img"));
+
+        // An escape sequence inside a control string ends it
+        let result = convert(b"\x1bPxyz\x1b[31mRed");
+        assert!(result.contains("<ans-04>Red"));
+
+        // CAN cancels a control string
+        let result = convert(b"\x1b]0;title\x18Text");
+        assert!(result.contains(">Text"));
     }
 
     #[test]
-    fn test_switch_256_to_rgb() {
-        // Start with 256-color, then switch to RGB
-        let input = b"\x1b[38;5;196m256\x1b[38;2;255;0;0mRGB";
-        let result = convert(input);
-        assert!(result.contains("<ans-256"));
-        assert!(result.contains("256"));
-        assert!(result.contains("<ans-rgb fg=\"255,0,0\""));
-        assert!(result.contains("RGB"));
+    #[cfg(feature = "sixel")]
+    fn test_sixel_image_element() {
+        let options = ConvertOptions {
+            sixel_images: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"A\x1bP0;1;0q#1;2;100;0;0#1!3~\x1b\\B", &options);
+        assert!(result.contains("A<img class=\"sixel\" width=\"3\" height=\"6\" style=\"display:block\" alt=\"\" src=\"data:image/png;base64,iVBORw0KGgo"));
+        assert!(result.contains("\">B"));
+        assert!(!result.contains("#1"));
     }
 
     #[test]
-    fn test_256_color_cga_range() {
-        // 256-color palette indices 0-15 are the standard CGA colors
-        // Test index 4 (red in 256-color, which maps to CGA red)
-        let input = b"\x1b[38;5;4mBlue";
-        let result = convert(input);
-        assert!(result.contains("<ans-256 fg=\"4\""));
+    #[cfg(feature = "sixel")]
+    fn test_sixel_image_keeps_its_place_in_screen_buffer() {
+        let options = ConvertOptions {
+            sixel_images: true,
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"Before\r\n\x1bPq#1~~\x1b\\After", &options);
+        let image = result.find("<img").unwrap();
+        assert!(result.find("Before\n").unwrap() < image);
+        assert!(image < result.find("After").unwrap());
+
+        // An image received mid-line sits between the cells around it
+        let result = convert_with_options(b"AB\x1bPq#1~~\x1b\\CD", &options);
+        assert!(result.contains("AB<img class=\"sixel\""));
+        assert!(result.contains("\">CD"));
     }
 
     #[test]
-    fn test_256_color_grayscale() {
-        // Test grayscale colors (232-255)
-        let input = b"\x1b[38;5;240mGray";
-        let result = convert(input);
-        assert!(result.contains("<ans-256 fg=\"240\""));
+    fn test_clear_screen_removes_sixel_images() {
+        let options = ConvertOptions {
+            sixel_images: true,
+            screen_buffer: true,
+            ..Default::default()
+        };
+        let result = convert_with_options(b"\x1bPq#1~~\x1b\\\x1b[2JText", &options);
+        assert!(!result.contains("<img"));
+        assert!(result.contains("Text"));
     }
 
-    // ========== Renegade escaped pipe tests ==========
+    #[test]
+    #[cfg(feature = "sixel")]
+    fn test_screen_embeds_sorted_and_bounded() {
+        let options = ConvertOptions {
+            sixel_images: true,
+            screen_buffer: true,
+            ..Default::default()
+        };
+        // Received bottom to top, output top to bottom; images at one cell keep their order
+        let input = b"\x1b[3;1H\x1bPq#1~\x1b\\\x1b[1;1H\x1bPq#2~\x1b\\\x1b[1;1H\x1bPq#3~\x1b\\";
+        let doc = convert_to_document(input, &options);
+        let red = |image: &Bitmap| image.pixels[..3].to_vec();
+        assert_eq!(
+            doc.images.iter().map(red).collect::<Vec<_>>(),
+            vec![vec![204, 33, 33], vec![51, 204, 51], vec![51, 51, 204]]
+        );
+
+        let input = "\x1bPq#1~\x1b\\".repeat(screen::MAX_EMBEDS + 10);
+        assert_eq!(
+            convert_to_document(input.as_bytes(), &options).images.len(),
+            screen::MAX_EMBEDS
+        );
+    }
 
     #[test]
-    fn test_renegade_escaped_pipe() {
+    #[cfg(feature = "sixel")]
+    fn test_sixel_split_across_chunks() {
         let options = ConvertOptions {
-            renegade_pipe: true,
+            sixel_images: true,
             ..Default::default()
         };
-        // || should output a single | and continue
-        let input = b"||Hello";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("|Hello"));
+        let mut stream = StreamConverter::new(options);
+        let mut html = stream.push(b"X\x1bPq#1!3");
+        html.push_str(&stream.push(b"~\x1b"));
+        html.push_str(&stream.push(b"\\Y"));
+        html.push_str(&stream.finish());
+        assert!(html.contains("X<img class=\"sixel\" width=\"3\" height=\"6\""));
+        assert!(html.contains("\">Y"));
     }
 
     #[test]
-    fn test_renegade_escaped_pipe_followed_by_digits() {
+    #[cfg(feature = "sixel")]
+    fn test_sixel_decoding() {
         let options = ConvertOptions {
-            renegade_pipe: true,
+            sixel_images: true,
             ..Default::default()
         };
-        // ||04 should output |04 (literal pipe followed by 04)
-        let input = b"||04Red";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("|04Red"));
+        // Transparent background, 4x8 raster, red (RGB) over two bands, then green (HLS)
+        let doc = convert_to_document(
+            b"\x1bP0;1q\"1;1;4;8#1;2;100;0;0#1!2~-#2;1;240;50;100#2@$#1?@\x1b\\",
+            &options,
+        );
+        assert_eq!(doc.images.len(), 1);
+        let image = &doc.images[0];
+        assert_eq!((image.width, image.height), (4, 8));
+        let pixel = |x: usize, y: usize| &image.pixels[(y * 4 + x) * 4..(y * 4 + x) * 4 + 4];
+        assert_eq!(pixel(0, 0), &[255, 0, 0, 255]);
+        assert_eq!(pixel(1, 5), &[255, 0, 0, 255]);
+        assert_eq!(pixel(2, 0), &[0, 0, 0, 0]);
+        assert_eq!(pixel(0, 6), &[0, 255, 0, 255]);
+        assert_eq!(pixel(1, 6), &[255, 0, 0, 255]);
+        assert_eq!(pixel(0, 7), &[0, 0, 0, 0]);
+
+        // Not a Sixel string
+        assert!(decode_sixel("1$qm").is_none());
+        assert!(decode_sixel("q").is_none());
     }
 
     #[test]
-    fn test_renegade_high_intensity_background() {
+    #[cfg(feature = "sixel")]
+    fn test_png_encoding() {
+        // RFC 4648 test vectors
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (data, encoded) in vectors {
+            assert_eq!(png::base64(data.as_bytes()), encoded);
+        }
+        assert_eq!(png::base64(&[0xFB, 0xFF]), "+/8=");
+
+        let bitmap = Bitmap {
+            width: 3,
+            height: 2,
+            pixels: (0..24).map(|i| i * 10).collect(),
+        };
+        let png = bitmap.to_png();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert!(
+            bitmap
+                .to_data_uri()
+                .starts_with("data:image/png;base64,iVBORw0KGgo")
+        );
+
+        let mut reader = ::png::Decoder::new(std::io::Cursor::new(png))
+            .read_info()
+            .unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(
+            (info.width, info.height, info.color_type),
+            (3, 2, ::png::ColorType::Rgba)
+        );
+        assert_eq!(pixels, bitmap.pixels);
+
+        // Pixels that do not match the size are not encoded
+        let bitmap = Bitmap {
+            width: 2,
+            height: 2,
+            pixels: vec![0; 4],
+        };
+        assert!(bitmap.to_png().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "sixel")]
+    fn test_png_compression() {
+        let bitmap = Bitmap {
+            width: 256,
+            height: 256,
+            pixels: vec![7; 256 * 256 * 4],
+        };
+        // 256 KiB of raw pixels
+        assert!(bitmap.to_png().len() < 4 * 1024);
+    }
+
+    #[test]
+    #[cfg(feature = "sixel")]
+    fn test_sixel_size_limits() {
+        // The raster attributes alone cannot declare a huge image
+        let image = decode_sixel("q\"1;1;4096;4096#1@").unwrap();
+        assert_eq!((image.width, image.height), (1024, 1024));
+
+        // Drawing past the pixel limit gives up
+        let within = |body: &str, max_pixels: usize| {
+            let mut paint = sixel::MAX_PAINT;
+            sixel::decode_sixel_within(body, max_pixels, &mut paint)
+        };
+        assert!(within("q!10~-!10~", 120).is_some());
+        assert!(within("q!10~-!10~", 119).is_none());
+        assert!(within("q!10~-!10@", 119).is_none());
+        assert!(within("q\"1;1;10;12#1@", 119).is_none());
+
+        // Every image of a conversion comes out of the same budget
         let options = ConvertOptions {
-            renegade_pipe: true,
+            sixel_images: true,
             ..Default::default()
         };
-        // |24 = dark gray background (high intensity black)
-        let input = b"|24Dark Gray BG";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-87>")); // Dark Gray bg (8), Light Gray fg (7)
+        let input = "\x1bPq\"1;1;1024;1024#1@\x1b\\".repeat(5);
+        assert_eq!(
+            convert_to_document(input.as_bytes(), &options).images.len(),
+            4
+        );
     }
 
     #[test]
-    fn test_renegade_high_intensity_background_range() {
+    #[cfg(feature = "sixel")]
+    fn test_sixel_paint_budget() {
+        // Repeats over the same columns paint 6 pixels per column each time
+        let mut paint = 120;
+        assert!(sixel::decode_sixel_within("q!10~$!10~", 60, &mut paint).is_some());
+        assert_eq!(paint, 0);
+        assert!(sixel::decode_sixel_within("q@", 60, &mut paint).is_none());
+        let mut paint = 119;
+        assert!(sixel::decode_sixel_within("q!10~$!10~", 60, &mut paint).is_none());
+        // Only set bits are painted
+        let mut paint = 20;
+        assert!(sixel::decode_sixel_within("q!10@$!10@", 60, &mut paint).is_some());
+
+        // Every image of a conversion paints from the same budget
         let options = ConvertOptions {
-            renegade_pipe: true,
+            sixel_images: true,
             ..Default::default()
         };
-        // |31 = white background (high intensity)
-        let input = b"|31White BG";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-f7>")); // White bg (f), Light Gray fg (7)
+        let redraws = "!4096~$".repeat(1400);
+        let input = format!("\x1bPq{redraws}\x1b\\\x1bPq{redraws}\x1b\\\x1bPq#1~\x1b\\");
+        let doc = convert_to_document(input.as_bytes(), &options);
+        assert_eq!(doc.images.len(), 1);
+        assert_eq!((doc.images[0].width, doc.images[0].height), (4096, 6));
     }
 
     #[test]
-    fn test_renegade_combined_high_intensity_bg_with_fg() {
+    #[cfg(feature = "sixel")]
+    fn test_control_strings_not_kept() {
         let options = ConvertOptions {
-            renegade_pipe: true,
+            sixel_images: true,
             ..Default::default()
         };
-        // |00 = black fg, |28 = light red background
-        let input = b"|00|28Black on Light Red";
-        let result = convert_with_options(input, &options);
-        assert!(result.contains("<ans-c0>")); // Light Red bg (c), Black fg (0)
+        let mut stream = StreamConverter::new(options.clone());
+        stream.push(b"\x1b]0;long title");
+        assert!(stream.converter.control_string.is_empty());
+
+        // DCS strings are only kept while they can be Sixel data
+        let mut stream = StreamConverter::new(options.clone());
+        stream.push(b"\x1bP1$qm");
+        assert!(stream.converter.control_string.is_empty());
+        let mut stream = StreamConverter::new(ConvertOptions::default());
+        stream.push(b"\x1bPq#1~~");
+        assert!(stream.converter.control_string.is_empty());
+        let mut stream = StreamConverter::new(options.clone());
+        stream.push(b"\x1bPq#1~~");
+        assert_eq!(stream.converter.control_string, "q#1~~");
+
+        // A DECRQSS request is not drawn as Sixel data
+        assert!(
+            convert_to_document(b"\x1bP1$qm\x1b\\A", &options)
+                .images
+                .is_empty()
+        );
     }
 }
-
@@ -0,0 +1,56 @@
+//! Macro substitution for BBS display files
+//!
+//! Menus and display files embed macros that the BBS replaces with live values while
+//! sending the file, such as Synchronet/PCBoard `@ALIAS@` codes or Mystic `|UN` MCI
+//! codes. A [`MacroProvider`] set in [`ConvertOptions::macros`](crate::ConvertOptions::macros)
+//! supplies the text for each macro the parser recognizes.
+
+use std::fmt;
+
+/// A macro recognized in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Macro<'a> {
+    /// `@NAME@` code (Synchronet, PCBoard, Wildcat!), without the at signs
+    AtCode(&'a str),
+    /// Mystic two-character MCI code such as `UN`, without the pipe
+    Mci(&'a str),
+}
+
+/// Supplies the text substituted for macros
+///
+/// Closures taking a [`Macro`] and returning `Option<String>` implement this trait.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use ansi_to_html_rs::{convert_with_options, ConvertOptions, Macro};
+///
+/// let options = ConvertOptions {
+///     macros: Some(Arc::new(|code: Macro<'_>| match code {
+///         Macro::AtCode("ALIAS") | Macro::Mci("UN") => Some("Sysop".to_string()),
+///         _ => None,
+///     })),
+///     ..Default::default()
+/// };
+/// let html = convert_with_options(b"Hi @ALIAS@! Try @UNKNOWN@.", &options);
+/// assert!(html.contains("Hi Sysop! Try @UNKNOWN@."));
+/// ```
+pub trait MacroProvider: Send + Sync {
+    /// Text to show in place of `code`, or `None` to leave the code in the output
+    fn expand(&self, code: Macro<'_>) -> Option<String>;
+}
+
+impl<F> MacroProvider for F
+where
+    F: Fn(Macro<'_>) -> Option<String> + Send + Sync,
+{
+    fn expand(&self, code: Macro<'_>) -> Option<String> {
+        self(code)
+    }
+}
+
+impl fmt::Debug for dyn MacroProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MacroProvider")
+    }
+}
@@ -0,0 +1,220 @@
+//! ANSI music
+//!
+//! BBS intros play tunes through the PC speaker with `ESC[M` or `ESC[N` followed by a
+//! BASIC `PLAY` string and Ctrl-N (0x0E). The string is a sequence of commands:
+//!
+//! | Command | Effect |
+//! |---------|--------|
+//! | `A`-`G` | Play a note, optionally followed by `#`/`+` (sharp) or `-` (flat), a length and dots |
+//! | `N` n | Play note n (1-84, counted in semitones from C of octave 0); `N0` is a rest |
+//! | `P` n | Pause for a note length of n |
+//! | `L` n | Default note length: 1 (whole note) to 64 |
+//! | `O` n, `>`, `<` | Set the octave (0-6), or move up / down one octave |
+//! | `T` n | Tempo in quarter notes per minute (32-255) |
+//! | `MN`, `ML`, `MS` | Normal, legato and staccato articulation |
+//! | `MF`, `MB` | Play in the foreground / background (no effect here) |
+//!
+//! A dot after a note or pause makes it half again as long; dots past the fourth are
+//! ignored.
+
+use std::time::Duration;
+
+/// Default tempo in quarter notes per minute
+const DEFAULT_TEMPO: u8 = 120;
+/// Default note length (quarter notes)
+const DEFAULT_LENGTH: u8 = 4;
+/// Default octave
+const DEFAULT_OCTAVE: u8 = 4;
+/// Highest octave
+const MAX_OCTAVE: u8 = 6;
+/// Most dots that lengthen a note
+const MAX_DOTS: u8 = 4;
+
+/// How much of a note's time is sounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Articulation {
+    /// Seven eighths of the note's time (`MN`)
+    #[default]
+    Normal,
+    /// The full time (`ML`)
+    Legato,
+    /// Three quarters of the note's time (`MS`)
+    Staccato,
+}
+
+/// A note or rest of an ANSI music sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MusicNote {
+    /// Semitone within the octave, from 0 (C) to 11 (B), or `None` for a rest
+    pub note: Option<u8>,
+    /// Octave 0-6; octave 3 starts at middle C
+    pub octave: u8,
+    /// Tempo in quarter notes per minute
+    pub tempo: u8,
+    /// Length as a fraction of a whole note, from 1 (whole note) to 64
+    pub length: u8,
+    /// Number of dots, each making the note half again as long (at most 4 count)
+    pub dots: u8,
+    /// How much of the note's time is sounded
+    pub articulation: Articulation,
+}
+
+impl MusicNote {
+    /// Pitch in Hz (A of octave 3 is 440 Hz), or `None` for a rest
+    pub fn frequency(&self) -> Option<f64> {
+        let semitone = f64::from(self.octave) * 12.0 + f64::from(self.note?);
+        Some(440.0 * 2f64.powf((semitone - 45.0) / 12.0))
+    }
+
+    /// Time from the start of this note to the start of the next one
+    pub fn duration(&self) -> Duration {
+        let whole_note = 240.0 / f64::from(self.tempo.max(1));
+        let dotted = 1.5f64.powi(i32::from(self.dots.min(MAX_DOTS)));
+        Duration::from_secs_f64(whole_note / f64::from(self.length.max(1)) * dotted)
+    }
+
+    /// Part of [`duration`](Self::duration) during which the note sounds (zero for rests)
+    pub fn sounding_duration(&self) -> Duration {
+        if self.note.is_none() {
+            return Duration::ZERO;
+        }
+        let duration = self.duration();
+        match self.articulation {
+            Articulation::Normal => duration * 7 / 8,
+            Articulation::Legato => duration,
+            Articulation::Staccato => duration * 3 / 4,
+        }
+    }
+}
+
+/// Parse a BASIC `PLAY` string into notes and rests.
+///
+/// Unknown commands and out-of-range values are skipped.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::parse_music;
+///
+/// let notes = parse_music("T240 O3 L8 A P4 C#2.");
+/// assert_eq!(notes.len(), 3);
+/// assert_eq!(notes[0].frequency(), Some(440.0));
+/// assert_eq!(notes[0].duration().as_millis(), 125);
+/// assert_eq!(notes[1].note, None);
+/// assert_eq!((notes[2].note, notes[2].length, notes[2].dots), (Some(1), 2, 1));
+/// ```
+pub fn parse_music(play: &str) -> Vec<MusicNote> {
+    let mut parser = PlayParser {
+        chars: play.chars().peekable(),
+        octave: DEFAULT_OCTAVE,
+        tempo: DEFAULT_TEMPO,
+        length: DEFAULT_LENGTH,
+        articulation: Articulation::Normal,
+    };
+    let mut notes = Vec::new();
+    while let Some(command) = parser.chars.next() {
+        match command.to_ascii_uppercase() {
+            letter @ 'A'..='G' => {
+                let semitone = [9, 11, 0, 2, 4, 5, 7][(letter as u8 - b'A') as usize];
+                let semitone = match parser.chars.peek() {
+                    Some('#' | '+') => {
+                        parser.chars.next();
+                        semitone + 1
+                    }
+                    Some('-') => {
+                        parser.chars.next();
+                        semitone - 1
+                    }
+                    _ => semitone,
+                };
+                let length = parser.length_argument();
+                let pitch = i32::from(parser.octave) * 12 + semitone;
+                notes.push(parser.note(Some(pitch), length));
+            }
+            'N' => {
+                if let Some(number) = parser.number().filter(|&number| number <= 84) {
+                    let pitch = (number > 0).then(|| number as i32 - 1);
+                    notes.push(parser.note(pitch, parser.length));
+                }
+            }
+            'P' => {
+                let length = parser.length_argument();
+                notes.push(parser.note(None, length));
+            }
+            'L' => parser.length = parser.length_argument(),
+            'O' => {
+                if let Some(octave) = parser
+                    .number()
+                    .filter(|&octave| octave <= u32::from(MAX_OCTAVE))
+                {
+                    parser.octave = octave as u8;
+                }
+            }
+            '>' => parser.octave = (parser.octave + 1).min(MAX_OCTAVE),
+            '<' => parser.octave = parser.octave.saturating_sub(1),
+            'T' => {
+                if let Some(tempo) = parser.number().filter(|tempo| (32..=255).contains(tempo)) {
+                    parser.tempo = tempo as u8;
+                }
+            }
+            'M' => {
+                let mode = parser.chars.next_if(|ch| "NLSFBnlsfb".contains(*ch));
+                match mode.map(|mode| mode.to_ascii_uppercase()) {
+                    Some('N') => parser.articulation = Articulation::Normal,
+                    Some('L') => parser.articulation = Articulation::Legato,
+                    Some('S') => parser.articulation = Articulation::Staccato,
+                    // Foreground / background play has no effect on the output
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    notes
+}
+
+/// State of a `PLAY` string being parsed
+struct PlayParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    octave: u8,
+    tempo: u8,
+    length: u8,
+    articulation: Articulation,
+}
+
+impl PlayParser<'_> {
+    /// Read a decimal number, if one follows
+    fn number(&mut self) -> Option<u32> {
+        let mut value: Option<u32> = None;
+        while let Some(digit) = self.chars.peek().and_then(|ch| ch.to_digit(10)) {
+            self.chars.next();
+            value = Some(value.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+        }
+        value
+    }
+
+    /// Read an optional note length (1-64), falling back to the default length
+    fn length_argument(&mut self) -> u8 {
+        match self.number() {
+            Some(length @ 1..=64) => length as u8,
+            _ => self.length,
+        }
+    }
+
+    /// Build a note at an absolute pitch (in semitones from C of octave 0), reading any
+    /// dots that follow
+    fn note(&mut self, pitch: Option<i32>, length: u8) -> MusicNote {
+        let mut dots = 0u8;
+        while self.chars.next_if_eq(&'.').is_some() {
+            dots = (dots + 1).min(MAX_DOTS);
+        }
+        let pitch = pitch.map(|pitch| pitch.clamp(0, i32::from(MAX_OCTAVE) * 12 + 11) as u8);
+        MusicNote {
+            note: pitch.map(|pitch| pitch % 12),
+            octave: pitch.map_or(self.octave, |pitch| pitch / 12),
+            tempo: self.tempo,
+            length,
+            dots,
+            articulation: self.articulation,
+        }
+    }
+}
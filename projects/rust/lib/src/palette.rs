@@ -0,0 +1,219 @@
+//! Color palettes for the sixteen CGA colors and the 256-color table
+//!
+//! A [`Palette`] maps the CGA color indices used by `<ans-KF>` elements (and the first
+//! sixteen entries of the 256-color table) to RGB values. The generated CSS exposes
+//! the sixteen colors as `--ans-color-0` through `--ans-color-15` custom properties, so
+//! a palette can be applied to a whole page or scoped to a single `<pre>` element. A
+//! custom 256-color table is scoped the same way as `--ans-256-0` through `--ans-256-255`.
+
+/// An RGB color value
+type Rgb = (u8, u8, u8);
+
+/// Sixteen base colors in CGA order, plus an optional 256-color table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Palette {
+    /// Colors for CGA indices 0-15 (black, blue, green, cyan, red, magenta, brown,
+    /// light gray, then the high-intensity versions)
+    pub colors: [Rgb; 16],
+    /// Colors for 256-color indices. `None` uses [`colors`](Palette::colors) for 0-15
+    /// followed by the standard xterm 6x6x6 color cube and grayscale ramp.
+    pub extended: Option<[Rgb; 256]>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::VGA
+    }
+}
+
+impl Palette {
+    /// IBM VGA text-mode palette (the default, same values as [`CGA_COLORS`](crate::CGA_COLORS))
+    pub const VGA: Palette = Palette {
+        colors: [
+            (0x00, 0x00, 0x00),
+            (0x00, 0x00, 0xAA),
+            (0x00, 0xAA, 0x00),
+            (0x00, 0xAA, 0xAA),
+            (0xAA, 0x00, 0x00),
+            (0xAA, 0x00, 0xAA),
+            (0xAA, 0x55, 0x00),
+            (0xAA, 0xAA, 0xAA),
+            (0x55, 0x55, 0x55),
+            (0x55, 0x55, 0xFF),
+            (0x55, 0xFF, 0x55),
+            (0x55, 0xFF, 0xFF),
+            (0xFF, 0x55, 0x55),
+            (0xFF, 0x55, 0xFF),
+            (0xFF, 0xFF, 0x55),
+            (0xFF, 0xFF, 0xFF),
+        ],
+        extended: None,
+    };
+
+    /// EGA RGBI palette
+    ///
+    /// VGA inherited its text colors from the EGA defaults, so the sixteen colors match
+    /// [`Palette::VGA`]. Since EGA hardware has no 256-color table, every 256-color index
+    /// is shown as the nearest of the sixteen colors.
+    pub const EGA: Palette = Palette {
+        colors: Palette::VGA.colors,
+        extended: Some(Palette::nearest_table(&Palette::VGA.colors)),
+    };
+
+    /// Amiga Workbench 2.x/3.x default 8-color screen palette
+    ///
+    /// The Amiga console device maps ANSI colors 0-7 straight onto pens 0-7 (gray,
+    /// black, white, blue, then the user pens), and has no separate bright colors.
+    pub const AMIGA_WORKBENCH: Palette = Palette {
+        colors: [
+            (0xAA, 0xAA, 0xAA), // pen 0 (ANSI black)
+            (0xEE, 0x44, 0x44), // pen 4 (ANSI blue)
+            (0xFF, 0xFF, 0xFF), // pen 2 (ANSI green)
+            (0x00, 0x44, 0xDD), // pen 6 (ANSI cyan)
+            (0x00, 0x00, 0x00), // pen 1 (ANSI red)
+            (0x55, 0xDD, 0x55), // pen 5 (ANSI magenta)
+            (0x66, 0x88, 0xBB), // pen 3 (ANSI yellow)
+            (0xEE, 0x99, 0x00), // pen 7 (ANSI white)
+            (0xAA, 0xAA, 0xAA),
+            (0xEE, 0x44, 0x44),
+            (0xFF, 0xFF, 0xFF),
+            (0x00, 0x44, 0xDD),
+            (0x00, 0x00, 0x00),
+            (0x55, 0xDD, 0x55),
+            (0x66, 0x88, 0xBB),
+            (0xEE, 0x99, 0x00),
+        ],
+        extended: None,
+    };
+
+    /// xterm default colors
+    pub const XTERM: Palette = Palette {
+        colors: [
+            (0x00, 0x00, 0x00),
+            (0x00, 0x00, 0xEE),
+            (0x00, 0xCD, 0x00),
+            (0x00, 0xCD, 0xCD),
+            (0xCD, 0x00, 0x00),
+            (0xCD, 0x00, 0xCD),
+            (0xCD, 0xCD, 0x00),
+            (0xE5, 0xE5, 0xE5),
+            (0x7F, 0x7F, 0x7F),
+            (0x5C, 0x5C, 0xFF),
+            (0x00, 0xFF, 0x00),
+            (0x00, 0xFF, 0xFF),
+            (0xFF, 0x00, 0x00),
+            (0xFF, 0x00, 0xFF),
+            (0xFF, 0xFF, 0x00),
+            (0xFF, 0xFF, 0xFF),
+        ],
+        extended: None,
+    };
+
+    /// Modern terminal palette (Windows Terminal "Campbell")
+    pub const MODERN: Palette = Palette {
+        colors: [
+            (0x0C, 0x0C, 0x0C),
+            (0x00, 0x37, 0xDA),
+            (0x13, 0xA1, 0x0E),
+            (0x3A, 0x96, 0xDD),
+            (0xC5, 0x0F, 0x1F),
+            (0x88, 0x17, 0x98),
+            (0xC1, 0x9C, 0x00),
+            (0xCC, 0xCC, 0xCC),
+            (0x76, 0x76, 0x76),
+            (0x3B, 0x78, 0xFF),
+            (0x16, 0xC6, 0x0C),
+            (0x61, 0xD6, 0xD6),
+            (0xE7, 0x48, 0x56),
+            (0xB4, 0x00, 0x9E),
+            (0xF9, 0xF1, 0xA5),
+            (0xF2, 0xF2, 0xF2),
+        ],
+        extended: None,
+    };
+
+    /// RGB value of a CGA color index (0-15)
+    pub fn color(&self, index: u8) -> Rgb {
+        self.colors[usize::from(index & 0x0F)]
+    }
+
+    /// RGB value of a 256-color palette index
+    pub fn color_256(&self, index: u8) -> Rgb {
+        match self.extended {
+            Some(table) => table[usize::from(index)],
+            None => Self::standard_256(&self.colors, index),
+        }
+    }
+
+    /// CSS custom property declarations for the sixteen colors,
+    /// e.g. `--ans-color-0: #000000; --ans-color-1: #0000AA; ...`, followed by
+    /// `--ans-256-0` through `--ans-256-255` when the palette has an
+    /// [`extended`](Palette::extended) table
+    pub fn css_variables(&self) -> String {
+        let mut css = String::new();
+        for (index, &color) in self.colors.iter().enumerate() {
+            if index > 0 {
+                css.push(' ');
+            }
+            css.push_str(&format!("--ans-color-{}: {};", index, hex(color)));
+        }
+        if let Some(table) = &self.extended {
+            for (index, &color) in table.iter().enumerate() {
+                css.push_str(&format!(" --ans-256-{}: {};", index, hex(color)));
+            }
+        }
+        css
+    }
+
+    /// xterm 256-color layout: base colors, 6x6x6 cube, then a 24-step grayscale ramp
+    const fn standard_256(colors: &[Rgb; 16], index: u8) -> Rgb {
+        if index < 16 {
+            colors[index as usize]
+        } else if index < 232 {
+            const fn level(c: u8) -> u8 {
+                if c == 0 { 0 } else { 55 + c * 40 }
+            }
+            let cube = index - 16;
+            (level(cube / 36), level((cube % 36) / 6), level(cube % 6))
+        } else {
+            let gray = (index - 232) * 10 + 8;
+            (gray, gray, gray)
+        }
+    }
+
+    /// 256-color table that replaces every entry with the nearest of the sixteen colors
+    const fn nearest_table(colors: &[Rgb; 16]) -> [Rgb; 256] {
+        let mut table = [(0, 0, 0); 256];
+        let mut index = 0;
+        while index < 256 {
+            let target = Self::standard_256(colors, index as u8);
+            let mut best = 0;
+            let mut best_distance = u32::MAX;
+            let mut candidate = 0;
+            while candidate < 16 {
+                let distance = distance(target, colors[candidate]);
+                if distance < best_distance {
+                    best = candidate;
+                    best_distance = distance;
+                }
+                candidate += 1;
+            }
+            table[index] = colors[best];
+            index += 1;
+        }
+        table
+    }
+}
+
+/// Squared euclidean distance between two colors
+const fn distance(a: Rgb, b: Rgb) -> u32 {
+    let dr = a.0.abs_diff(b.0) as u32;
+    let dg = a.1.abs_diff(b.1) as u32;
+    let db = a.2.abs_diff(b.2) as u32;
+    dr * dr + dg * dg + db * db
+}
+
+/// Format a color as an uppercase `#RRGGBB` string
+pub(crate) fn hex((r, g, b): Rgb) -> String {
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
@@ -0,0 +1,44 @@
+//! PNG encoding for `data:` URIs
+//!
+//! Images are written as 8-bit RGBA PNG files by the `png` crate, which filters the rows
+//! adaptively and deflates them; the flat areas of typical Sixel art shrink to a small
+//! fraction of their raw size. The files are then base64 encoded for `<img>` elements.
+
+use ::png::{BitDepth, ColorType, Encoder};
+
+/// Encode RGBA pixels (`width * height * 4` bytes, rows top to bottom) as a PNG file.
+///
+/// Returns `None` when the width or height is zero or `rgba` holds a different number of
+/// pixels.
+pub(crate) fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Option<Vec<u8>> {
+    let mut png = Vec::new();
+    let mut encoder = Encoder::new(&mut png, width, height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    let mut writer = encoder.write_header().ok()?;
+    writer.write_image_data(rgba).ok()?;
+    writer.finish().ok()?;
+    Some(png)
+}
+
+/// Standard base64 encoding with padding, as used in `data:` URIs
+pub(crate) fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for group in data.chunks(3) {
+        let bytes = [
+            group[0],
+            *group.get(1).unwrap_or(&0),
+            *group.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+        for index in 0..4 {
+            if index <= group.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * index) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
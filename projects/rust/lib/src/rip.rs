@@ -0,0 +1,423 @@
+//! RIPscrip vector graphics
+//!
+//! RIPscrip screens are lines starting with `!` that hold `|`-separated commands. A
+//! command is a letter followed by arguments written as two-digit base-36 numbers
+//! ("MegaNums") and, for text commands, the text itself. Lines ending with `\` continue
+//! on the next line, and `\|`, `\!` and `\\` escape those characters in text.
+//!
+//! [`RipCanvas`] collects the drawing commands of one screen (640x350 pixels) and
+//! renders them as an SVG image. Colors are written as `var(--rip-N)` so that palette
+//! changes apply to the whole screen, as they do on EGA hardware.
+//!
+//! Supported commands: colors, palettes, line, fill and font styles, viewport origin,
+//! pixels, lines, rectangles, bars, circles, ovals, arcs, pie slices, Bezier curves,
+//! polygons, polylines and text. Flood fills, write modes, level 1 commands (mouse
+//! regions, buttons, icons) and text windows are ignored.
+
+use std::fmt::Write as _;
+
+/// Width of the RIPscrip graphics screen
+const WIDTH: i32 = 640;
+/// Height of the RIPscrip graphics screen
+const HEIGHT: i32 = 350;
+
+/// EGA palette registers of the sixteen default colors
+const DEFAULT_PALETTE: [u8; 16] = [0, 1, 2, 3, 4, 5, 20, 7, 56, 57, 58, 59, 60, 61, 62, 63];
+
+/// Drawing state and rendered elements of a RIPscrip screen
+#[derive(Debug, Clone)]
+pub(crate) struct RipCanvas {
+    /// SVG elements drawn so far
+    elements: Vec<String>,
+    /// EGA palette register (0-63) of each color
+    palette: [u8; 16],
+    /// Drawing color
+    color: u8,
+    /// Fill pattern (0 fills with the background) and color
+    fill: (u8, u8),
+    /// Line style (solid, dotted, center, dashed, user) and thickness
+    line: (u8, u8),
+    /// Font number, direction (0 horizontal, 1 vertical) and size
+    font: (u8, u8, u8),
+    /// Graphics cursor
+    position: (i32, i32),
+    /// Top-left corner of the viewport, which drawing coordinates are relative to
+    origin: (i32, i32),
+}
+
+impl Default for RipCanvas {
+    fn default() -> Self {
+        Self {
+            elements: Vec::new(),
+            palette: DEFAULT_PALETTE,
+            color: 15,
+            fill: (1, 15),
+            line: (0, 1),
+            font: (0, 0, 1),
+            position: (0, 0),
+            origin: (0, 0),
+        }
+    }
+}
+
+impl RipCanvas {
+    /// Whether nothing has been drawn
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Remove everything drawn, keeping the palette and styles
+    pub fn clear(&mut self) {
+        self.elements.clear();
+    }
+
+    /// Run the commands of a RIPscrip line (without the leading `!`).
+    ///
+    /// When a command erases the screen, returns the SVG of what was drawn before it so
+    /// the caller can output it.
+    pub fn process_line(&mut self, line: &str) -> Option<String> {
+        let mut erased = None;
+        for command in split_commands(line) {
+            let mut chars = command.chars();
+            let Some(name) = chars.next() else {
+                continue;
+            };
+            if name.is_ascii_digit() {
+                // Level 1+ commands (mouse regions, buttons, icons)
+                continue;
+            }
+            if matches!(name, '*' | 'e' | 'E') {
+                if !self.is_empty() {
+                    erased = Some(self.to_svg());
+                }
+                self.clear();
+                if name == '*' {
+                    self.origin = (0, 0);
+                }
+                continue;
+            }
+            self.process_command(name, &mut Args(chars.as_str()));
+        }
+        erased
+    }
+
+    fn process_command(&mut self, name: char, args: &mut Args) -> Option<()> {
+        match name {
+            'c' => self.color = args.color()?,
+            'Q' => {
+                for index in 0..16 {
+                    self.palette[index] = (args.number()? & 0x3F) as u8;
+                }
+            }
+            'a' => {
+                let color = args.color()?;
+                self.palette[usize::from(color)] = (args.number()? & 0x3F) as u8;
+            }
+            'S' => self.fill = (args.number()? as u8, args.color()?),
+            '=' => {
+                let style = args.number()? as u8;
+                args.digits(4)?;
+                self.line = (style, args.number()?.clamp(1, 3) as u8);
+            }
+            'Y' => {
+                self.font = (
+                    args.number()? as u8,
+                    args.number()? as u8,
+                    args.number()?.clamp(1, 10) as u8,
+                )
+            }
+            'v' => self.origin = (args.number()?, args.number()?),
+            'm' => self.position = (args.number()?, args.number()?),
+            'X' => {
+                let (x, y) = self.point(args)?;
+                self.push(format!(
+                    r#"<rect x="{x}" y="{y}" width="1" height="1" style="fill:var(--rip-{})"/>"#,
+                    self.color
+                ));
+            }
+            'L' => {
+                let ((x1, y1), (x2, y2)) = (self.point(args)?, self.point(args)?);
+                let stroke = self.stroke();
+                self.push(format!(
+                    r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" style="{stroke}"/>"#
+                ));
+            }
+            'R' | 'B' => {
+                let ((x1, y1), (x2, y2)) = (self.point(args)?, self.point(args)?);
+                let (x, y) = (x1.min(x2), y1.min(y2));
+                let (width, height) = ((x1 - x2).abs() + 1, (y1 - y2).abs() + 1);
+                // Bars are filled without a border
+                let style = if name == 'B' {
+                    self.fill_style()
+                } else {
+                    format!("fill:none;{}", self.stroke())
+                };
+                self.push(format!(
+                    r#"<rect x="{x}" y="{y}" width="{width}" height="{height}" style="{style}"/>"#
+                ));
+            }
+            'C' => {
+                let (cx, cy) = self.point(args)?;
+                let radius = args.number()?;
+                self.push_ellipse(cx, cy, radius, radius, false);
+            }
+            'o' => {
+                let (cx, cy) = self.point(args)?;
+                let (rx, ry) = (args.number()?, args.number()?);
+                self.push_ellipse(cx, cy, rx, ry, true);
+            }
+            'A' | 'O' | 'V' | 'I' | 'i' => {
+                let (cx, cy) = self.point(args)?;
+                let (start, end) = (args.number()?, args.number()?);
+                let rx = args.number()?;
+                let ry = if matches!(name, 'A' | 'I') {
+                    rx
+                } else {
+                    args.number()?
+                };
+                self.push_arc(cx, cy, (start, end), (rx, ry), matches!(name, 'I' | 'i'));
+            }
+            'Z' => {
+                let points = [
+                    self.point(args)?,
+                    self.point(args)?,
+                    self.point(args)?,
+                    self.point(args)?,
+                ];
+                let [(x1, y1), (x2, y2), (x3, y3), (x4, y4)] = points;
+                let stroke = self.stroke();
+                self.push(format!(
+                    r#"<path d="M{x1} {y1}C{x2} {y2} {x3} {y3} {x4} {y4}" style="fill:none;{stroke}"/>"#
+                ));
+            }
+            'P' | 'p' | 'l' => {
+                let count = args.number()?;
+                let mut points = String::new();
+                for _ in 0..count {
+                    let (x, y) = self.point(args)?;
+                    let _ = write!(
+                        points,
+                        "{}{x},{y}",
+                        if points.is_empty() { "" } else { " " }
+                    );
+                }
+                let (element, style) = match name {
+                    'P' => ("polygon", format!("fill:none;{}", self.stroke())),
+                    'p' => (
+                        "polygon",
+                        format!("{};{}", self.fill_style(), self.stroke()),
+                    ),
+                    _ => ("polyline", format!("fill:none;{}", self.stroke())),
+                };
+                self.push(format!(r#"<{element} points="{points}" style="{style}"/>"#));
+            }
+            '@' => {
+                let (x, y) = self.point(args)?;
+                self.push_text(x, y, args.0);
+            }
+            'T' => {
+                let (x, y) = (
+                    self.origin.0 + self.position.0,
+                    self.origin.1 + self.position.1,
+                );
+                self.push_text(x, y, args.0);
+                let advance = args.0.chars().count() as i32 * 8 * i32::from(self.font.2);
+                if self.font.1 == 0 {
+                    self.position.0 += advance;
+                } else {
+                    self.position.1 -= advance;
+                }
+            }
+            // Flood fill, write mode, text window and other commands
+            _ => {}
+        }
+        Some(())
+    }
+
+    /// Read an x/y pair, translated to screen coordinates
+    fn point(&self, args: &mut Args) -> Option<(i32, i32)> {
+        Some((
+            self.origin.0 + args.number()?,
+            self.origin.1 + args.number()?,
+        ))
+    }
+
+    fn push(&mut self, element: String) {
+        self.elements.push(element);
+    }
+
+    /// CSS for outlines in the drawing color and line style
+    fn stroke(&self) -> String {
+        let (style, thickness) = self.line;
+        let dashes = match style {
+            1 => ";stroke-dasharray:1 2",
+            2 => ";stroke-dasharray:6 2 2 2",
+            3 => ";stroke-dasharray:6 3",
+            _ => "",
+        };
+        format!(
+            "stroke:var(--rip-{});stroke-width:{thickness}{dashes}",
+            self.color
+        )
+    }
+
+    /// CSS for filled areas; patterns other than empty (0) are drawn solid
+    fn fill_style(&self) -> String {
+        let (pattern, color) = self.fill;
+        format!("fill:var(--rip-{})", if pattern == 0 { 0 } else { color })
+    }
+
+    fn push_ellipse(&mut self, cx: i32, cy: i32, rx: i32, ry: i32, filled: bool) {
+        let fill = if filled {
+            self.fill_style()
+        } else {
+            String::from("fill:none")
+        };
+        let stroke = self.stroke();
+        self.push(format!(
+            r#"<ellipse cx="{cx}" cy="{cy}" rx="{rx}" ry="{ry}" style="{fill};{stroke}"/>"#
+        ));
+    }
+
+    /// Arc (or pie slice) counter-clockwise from `start` to `end` degrees, 0 pointing right
+    fn push_arc(
+        &mut self,
+        cx: i32,
+        cy: i32,
+        (start, end): (i32, i32),
+        (rx, ry): (i32, i32),
+        pie: bool,
+    ) {
+        let span = (end - start).rem_euclid(360);
+        if span == 0 && end != start {
+            self.push_ellipse(cx, cy, rx, ry, pie);
+            return;
+        }
+        let at = |angle: i32| {
+            let radians = f64::from(angle).to_radians();
+            (
+                f64::from(cx) + f64::from(rx) * radians.cos(),
+                f64::from(cy) - f64::from(ry) * radians.sin(),
+            )
+        };
+        let ((x1, y1), (x2, y2)) = (at(start), at(end));
+        let large = i32::from(span > 180);
+        let arc = format!("M{x1:.1} {y1:.1}A{rx} {ry} 0 {large} 0 {x2:.1} {y2:.1}");
+        let element = if pie {
+            format!(
+                r#"<path d="M{cx} {cy}L{}Z" style="{};{}"/>"#,
+                &arc[1..],
+                self.fill_style(),
+                self.stroke()
+            )
+        } else {
+            format!(r#"<path d="{arc}" style="fill:none;{}"/>"#, self.stroke())
+        };
+        self.push(element);
+    }
+
+    fn push_text(&mut self, x: i32, y: i32, text: &str) {
+        let (font, direction, size) = self.font;
+        let family = match font {
+            0 => "monospace",
+            1 | 4 => "serif",
+            _ => "sans-serif",
+        };
+        let rotate = if direction == 0 {
+            String::new()
+        } else {
+            format!(r#" transform="rotate(-90 {x} {y})""#)
+        };
+        let mut element = format!(
+            r#"<text x="{x}" y="{y}"{rotate} style="fill:var(--rip-{});font-family:{family};font-size:{}px;dominant-baseline:text-before-edge;white-space:pre">"#,
+            self.color,
+            8 * u32::from(size)
+        );
+        for ch in text.chars() {
+            match ch {
+                '<' => element.push_str("&lt;"),
+                '>' => element.push_str("&gt;"),
+                '&' => element.push_str("&amp;"),
+                _ => element.push(ch),
+            }
+        }
+        element.push_str("</text>");
+        self.push(element);
+    }
+
+    /// Render the screen as an SVG element
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            r#"<svg class="rip" xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" style="display:block"#
+        );
+        for (index, &register) in self.palette.iter().enumerate() {
+            let _ = write!(svg, ";--rip-{index}:{}", ega_color(register));
+        }
+        svg.push_str(r#""><rect width="100%" height="100%" style="fill:var(--rip-0)"/>"#);
+        for element in &self.elements {
+            svg.push_str(element);
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+}
+
+/// CSS color of an EGA palette register (`rgbRGB` bits: secondary then primary)
+fn ega_color(register: u8) -> String {
+    let channel = |primary: u8, secondary: u8| {
+        0xAA * ((register >> primary) & 1) + 0x55 * ((register >> secondary) & 1)
+    };
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(2, 5),
+        channel(1, 4),
+        channel(0, 3)
+    )
+}
+
+/// Split a RIPscrip line into its commands, resolving backslash escapes
+fn split_commands(line: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '|' => commands.extend(current.replace(String::new())),
+            '\\' => {
+                if let (Some(command), Some(escaped)) = (current.as_mut(), chars.next()) {
+                    command.push(escaped);
+                }
+            }
+            _ => {
+                if let Some(command) = current.as_mut() {
+                    command.push(ch);
+                }
+            }
+        }
+    }
+    commands.extend(current);
+    commands
+}
+
+/// Remaining arguments of a command
+struct Args<'a>(&'a str);
+
+impl Args<'_> {
+    /// Read a base-36 number of `count` digits
+    fn digits(&mut self, count: usize) -> Option<i32> {
+        let digits = self.0.get(..count)?;
+        let value = i32::from_str_radix(digits, 36).ok()?;
+        self.0 = &self.0[count..];
+        Some(value)
+    }
+
+    /// Read a two-digit MegaNum
+    fn number(&mut self) -> Option<i32> {
+        self.digits(2)
+    }
+
+    /// Read a color number (0-15)
+    fn color(&mut self) -> Option<u8> {
+        Some((self.number()? & 0x0F) as u8)
+    }
+}
@@ -0,0 +1,304 @@
+//! Virtual screen buffer for cursor-addressed rendering
+//!
+//! This module provides a grid of character cells that grows downward as text is
+//! written. Cursor movement and erase sequences are applied against the grid, so
+//! text placed with CUP or relative cursor moves ends up in its intended cell.
+//! The final screen is serialized once input processing completes.
+//!
+//! The grid is limited to [`MAX_ROWS`] rows; moving further down stays on the last row.
+//! It holds at most [`MAX_CELLS`] cells in total: characters and colored erases that
+//! would grow it past that are dropped.
+//!
+//! Graphics received while the screen is buffered are anchored to the cursor cell and
+//! output in front of that cell, so they keep their place among the text. At most
+//! [`MAX_EMBEDS`] graphics are kept; later ones are dropped.
+
+use crate::{Attributes, Bitmap, ExtendedColor, Style};
+
+/// Number of rows the screen can grow to
+pub(crate) const MAX_ROWS: usize = 65536;
+
+/// Number of cells all rows together can hold (52428 rows of 80 columns)
+pub(crate) const MAX_CELLS: usize = 1 << 22;
+
+/// Number of graphics that can be anchored to the screen
+pub(crate) const MAX_EMBEDS: usize = 4096;
+
+/// A single character cell in the virtual screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Cell {
+    pub ch: char,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            ch: ' ',
+            style: Style::default(),
+        }
+    }
+}
+
+impl Cell {
+    /// A blank cell renders identically to an untouched one (plain space on black)
+    pub fn is_blank(&self) -> bool {
+        self.ch == ' '
+            && self.style.background == ExtendedColor::Cga(0)
+            && self.style.attributes == Attributes::default()
+    }
+}
+
+/// Graphics anchored between the cells of the screen
+pub(crate) enum Embed {
+    /// Inline SVG markup (RIPscrip)
+    Graphics(String),
+    /// Decoded Sixel image
+    Image(Bitmap),
+}
+
+/// Grid of cells with a cursor, addressed as (row, column) from the top-left
+pub(crate) struct ScreenBuffer {
+    width: usize,
+    rows: Vec<Vec<Cell>>,
+    /// Cells held by all rows
+    cells: usize,
+    /// Anchored graphics as (row, column, graphics), in the order they were received
+    embeds: Vec<(usize, usize, Embed)>,
+    row: usize,
+    /// Cursor column; equal to `width` when a wrap is pending
+    col: usize,
+    saved: (usize, usize),
+}
+
+impl ScreenBuffer {
+    pub fn new(width: usize) -> Self {
+        Self {
+            width: width.max(1),
+            rows: Vec::new(),
+            cells: 0,
+            embeds: Vec::new(),
+            row: 0,
+            col: 0,
+            saved: (0, 0),
+        }
+    }
+
+    /// Rows written so far (rows are not padded to the screen width)
+    pub fn rows(&self) -> &[Vec<Cell>] {
+        &self.rows
+    }
+
+    /// Anchored graphics, sorted by row and column once [`sort_embeds`](Self::sort_embeds)
+    /// has been called
+    pub fn embeds(&self) -> &[(usize, usize, Embed)] {
+        &self.embeds
+    }
+
+    /// Sort the anchored graphics by row and column (graphics at the same cell keep the
+    /// order they were received in)
+    pub fn sort_embeds(&mut self) {
+        self.embeds.sort_by_key(|&(row, col, _)| (row, col));
+    }
+
+    /// Number of rows holding cells or anchored graphics
+    pub fn height(&self) -> usize {
+        let embedded = self
+            .embeds
+            .iter()
+            .map(|&(row, _, _)| row + 1)
+            .max()
+            .unwrap_or(0);
+        self.rows.len().max(embedded)
+    }
+
+    /// Anchor graphics at the cursor (dropped once [`MAX_EMBEDS`] graphics are anchored)
+    pub fn embed(&mut self, embed: Embed) {
+        if self.embeds.len() < MAX_EMBEDS {
+            self.embeds.push((self.row, self.col, embed));
+        }
+    }
+
+    /// Write a character at the cursor and advance.
+    ///
+    /// Wrapping is deferred: the cursor parks past the last column and only moves
+    /// to the next row when another character is written.
+    pub fn put(&mut self, ch: char, style: Style) {
+        if self.col >= self.width {
+            self.line_feed();
+        }
+        let col = self.col;
+        if let Some(row) = self.row_with_len(self.row, col + 1) {
+            row[col] = Cell { ch, style };
+        }
+        self.col += 1;
+    }
+
+    /// Zero-based cursor column
+    pub fn column(&self) -> usize {
+        self.col
+    }
+
+    pub fn carriage_return(&mut self) {
+        self.col = 0;
+    }
+
+    /// Move to the start of the next row (LF is treated as CR+LF, as in ANSI art viewers)
+    pub fn line_feed(&mut self) {
+        self.row = Self::clamp_row(self.row + 1);
+        self.col = 0;
+    }
+
+    pub fn cursor_up(&mut self, n: usize) {
+        self.row = self.row.saturating_sub(n);
+        self.clamp_col();
+    }
+
+    pub fn cursor_down(&mut self, n: usize) {
+        self.row = Self::clamp_row(self.row.saturating_add(n));
+        self.clamp_col();
+    }
+
+    pub fn cursor_forward(&mut self, n: usize) {
+        self.col = (self.col + n).min(self.width - 1);
+    }
+
+    pub fn cursor_back(&mut self, n: usize) {
+        self.clamp_col();
+        self.col = self.col.saturating_sub(n);
+    }
+
+    /// Move to an absolute zero-based position (column is clamped to the screen width)
+    pub fn move_to(&mut self, row: usize, col: usize) {
+        self.row = Self::clamp_row(row);
+        self.col = col.min(self.width - 1);
+    }
+
+    /// Move to an absolute zero-based column on the current row
+    pub fn move_to_column(&mut self, col: usize) {
+        self.col = col.min(self.width - 1);
+    }
+
+    /// Move to an absolute zero-based row, keeping the column
+    pub fn move_to_row(&mut self, row: usize) {
+        self.row = Self::clamp_row(row);
+        self.clamp_col();
+    }
+
+    pub fn save_cursor(&mut self) {
+        self.saved = (self.row, self.col);
+    }
+
+    pub fn restore_cursor(&mut self) {
+        (self.row, self.col) = self.saved;
+    }
+
+    /// ED - Erase in Display
+    ///
+    /// Mode 0 erases from the cursor to the end of the screen, mode 1 from the start of
+    /// the screen to the cursor, and modes 2/3 clear everything (graphics included) and home
+    /// the cursor (ANSI.SYS behavior, which BBS art relies on).
+    pub fn erase_display(&mut self, mode: u16, style: Style) {
+        match mode {
+            0 => {
+                self.erase_line(0, style);
+                let blank = Self::blank(style);
+                for row in self.rows.iter_mut().skip(self.row + 1) {
+                    row.fill(blank);
+                }
+            }
+            1 => {
+                self.erase_line(1, style);
+                let blank = Self::blank(style);
+                for row in self.rows.iter_mut().take(self.row) {
+                    row.fill(blank);
+                }
+            }
+            2 | 3 => {
+                self.rows.clear();
+                self.embeds.clear();
+                self.cells = 0;
+                self.row = 0;
+                self.col = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// EL - Erase in Line
+    ///
+    /// Mode 0 erases from the cursor to the end of the line, mode 1 from the start of
+    /// the line to the cursor, and mode 2 erases the whole line.
+    pub fn erase_line(&mut self, mode: u16, style: Style) {
+        let blank = Self::blank(style);
+        let col = self.col.min(self.width - 1);
+        let range = match mode {
+            0 => col..self.width,
+            1 => 0..col + 1,
+            2 => 0..self.width,
+            _ => return,
+        };
+        if blank.is_blank() {
+            // Erasing with the default background only needs to drop existing cells
+            let row = self.row_mut(self.row);
+            let len = row.len();
+            if range.end >= len {
+                row.truncate(range.start.min(len));
+                let removed = len - row.len();
+                self.cells -= removed;
+            } else {
+                row[range].fill(blank);
+            }
+        } else if let Some(row) = self.row_with_len(self.row, range.end) {
+            row[range].fill(blank);
+        } else {
+            // Out of cells: only recolor the cells the row already has
+            let row = &mut self.rows[self.row];
+            let end = range.end.min(row.len());
+            if range.start < end {
+                row[range.start..end].fill(blank);
+            }
+        }
+    }
+
+    /// Erased cell: keeps the colors of `style` but none of its attributes
+    fn blank(style: Style) -> Cell {
+        Cell {
+            ch: ' ',
+            style: Style {
+                attributes: Attributes::default(),
+                ..style
+            },
+        }
+    }
+
+    fn clamp_row(row: usize) -> usize {
+        row.min(MAX_ROWS - 1)
+    }
+
+    fn clamp_col(&mut self) {
+        self.col = self.col.min(self.width - 1);
+    }
+
+    fn row_mut(&mut self, row: usize) -> &mut Vec<Cell> {
+        if self.rows.len() <= row {
+            self.rows.resize_with(row + 1, Vec::new);
+        }
+        &mut self.rows[row]
+    }
+
+    /// Row `row` with at least `len` cells, or `None` if growing it would pass
+    /// [`MAX_CELLS`]
+    fn row_with_len(&mut self, row: usize, len: usize) -> Option<&mut Vec<Cell>> {
+        let current = self.row_mut(row).len();
+        if current < len {
+            let added = len - current;
+            if self.cells + added > MAX_CELLS {
+                return None;
+            }
+            self.cells += added;
+            self.rows[row].resize(len, Cell::default());
+        }
+        Some(&mut self.rows[row])
+    }
+}
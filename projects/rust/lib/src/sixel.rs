@@ -0,0 +1,312 @@
+//! Sixel graphics
+//!
+//! Sixel images are sent as a DCS string: `ESC P`, optional parameters, `q`, the image
+//! data and the string terminator `ESC \`. Each data character from `?` to `~` encodes a
+//! column of six pixels (its value minus 63, least significant bit on top), drawn in the
+//! current color:
+//!
+//! | Data | Effect |
+//! |------|--------|
+//! | `#Pc` | Select color register Pc |
+//! | `#Pc;Pu;Px;Py;Pz` | Define color register Pc as HLS (Pu=1, hue 0-360 with blue at 0, lightness and saturation 0-100) or RGB (Pu=2, 0-100 each) |
+//! | `!Pn` + character | Repeat the character Pn times |
+//! | `$` | Return to the start of the current six-pixel band |
+//! | `-` | Move to the start of the next band |
+//! | `"Pan;Pad;Ph;Pv` | Raster attributes: aspect ratio (ignored) and image size (up to 1024x1024) |
+//!
+//! The second DCS parameter selects whether pixels that are never drawn stay transparent
+//! (1) or show color register 0 (0 or 2).
+//!
+//! Images are limited to [`MAX_PIXELS`] pixels, which is also the budget for all of the
+//! images of one conversion. Repeats can paint the same pixels over and over, so the
+//! images of one conversion also share a budget of [`MAX_PAINT`] painted pixels.
+
+#[cfg(feature = "sixel")]
+use crate::png::{base64, encode_png};
+
+/// Largest width and height of a decoded image, in pixels
+const MAX_SIZE: usize = 4096;
+
+/// Largest width and height declared by the raster attributes alone, in pixels
+const MAX_RASTER_SIZE: usize = 1024;
+
+/// Most pixels in one image, or in all of the images of one conversion (16 MiB of RGBA)
+pub(crate) const MAX_PIXELS: usize = 4096 * 1024;
+
+/// Most pixels painted while decoding one image, or all of the images of one conversion
+pub(crate) const MAX_PAINT: usize = 16 * MAX_PIXELS;
+
+/// Number of color registers
+const REGISTERS: usize = 256;
+
+/// Default colors of the first sixteen registers (VT340), as RGB percentages
+const DEFAULT_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),    // 0 - Black
+    (20, 20, 80), // 1 - Blue
+    (80, 13, 13), // 2 - Red
+    (20, 80, 20), // 3 - Green
+    (80, 20, 80), // 4 - Magenta
+    (20, 80, 80), // 5 - Cyan
+    (80, 80, 20), // 6 - Yellow
+    (53, 53, 53), // 7 - Gray 50%
+    (26, 26, 26), // 8 - Gray 25%
+    (33, 33, 60), // 9 - Light Blue
+    (60, 26, 26), // 10 - Light Red
+    (33, 60, 33), // 11 - Light Green
+    (60, 33, 60), // 12 - Light Magenta
+    (33, 60, 60), // 13 - Light Cyan
+    (60, 60, 33), // 14 - Light Yellow
+    (80, 80, 80), // 15 - Gray 75%
+];
+
+/// A decoded image of RGBA pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Red, green, blue and alpha bytes of each pixel, row by row from the top
+    pub pixels: Vec<u8>,
+}
+
+#[cfg(feature = "sixel")]
+impl Bitmap {
+    /// Encode the image as a PNG file.
+    ///
+    /// Returns an empty vector when `pixels` does not hold `width * height` pixels.
+    pub fn to_png(&self) -> Vec<u8> {
+        encode_png(self.width, self.height, &self.pixels).unwrap_or_default()
+    }
+
+    /// The image as a `data:image/png;base64,...` URI, for use in an `<img>` element
+    pub fn to_data_uri(&self) -> String {
+        format!("data:image/png;base64,{}", base64(&self.to_png()))
+    }
+}
+
+/// Decode the body of a Sixel DCS string (the parameters, `q` and the image data,
+/// without `ESC P` and the string terminator).
+///
+/// Returns `None` when the string is not a Sixel image, draws no pixels, the image would
+/// be larger than 4 megapixels, or drawing it paints more than 64 megapixels.
+///
+/// # Example
+/// ```
+/// use ansi_to_html_rs::decode_sixel;
+///
+/// // Register 1 set to pure red, then a 2x6 block of it
+/// let image = decode_sixel("0;1;0q#1;2;100;0;0#1~~").unwrap();
+/// assert_eq!((image.width, image.height), (2, 6));
+/// assert_eq!(&image.pixels[..4], &[255, 0, 0, 255]);
+/// ```
+pub fn decode_sixel(body: &str) -> Option<Bitmap> {
+    let mut paint = MAX_PAINT;
+    decode_sixel_within(body, MAX_PIXELS, &mut paint)
+}
+
+/// Decode the body of a Sixel DCS string like [`decode_sixel`], giving up as soon as the
+/// image would be larger than `max_pixels` or paint more than `paint` pixels.
+///
+/// The pixels painted are taken from `paint`, even when the image is given up.
+pub(crate) fn decode_sixel_within(
+    body: &str,
+    max_pixels: usize,
+    paint: &mut usize,
+) -> Option<Bitmap> {
+    let (parameters, data) = body.split_once('q')?;
+    if !parameters
+        .bytes()
+        .all(|byte| byte.is_ascii_digit() || byte == b';')
+    {
+        return None;
+    }
+    let transparent = parameters.split(';').nth(1) == Some("1");
+
+    let mut palette = [[0u8, 0, 0, 255]; REGISTERS];
+    for (register, &(r, g, b)) in palette.iter_mut().zip(DEFAULT_COLORS.iter()) {
+        *register = [percent(r.into()), percent(g.into()), percent(b.into()), 255];
+    }
+
+    let mut rows: Vec<Vec<[u8; 4]>> = Vec::new();
+    let mut size = (0usize, 0usize);
+    let mut drawn = (0usize, 0usize);
+    let mut color = 0usize;
+    let (mut x, mut band) = (0usize, 0usize);
+    let mut chars = data.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '#' => {
+                let args = numbers(&mut chars);
+                color = args.first().copied().unwrap_or(0) as usize % REGISTERS;
+                if let [_, space, a, b, c] = args[..] {
+                    match space {
+                        1 => palette[color] = hls(a, b, c),
+                        2 => palette[color] = [percent(a), percent(b), percent(c), 255],
+                        _ => {}
+                    }
+                }
+            }
+            '"' => {
+                if let [_, _, width, height] = numbers(&mut chars)[..] {
+                    size = (
+                        (width as usize).min(MAX_RASTER_SIZE),
+                        (height as usize).min(MAX_RASTER_SIZE),
+                    );
+                }
+            }
+            '!' => {
+                let count = numbers(&mut chars).first().copied().unwrap_or(1).max(1) as usize;
+                if let Some(sixel) = chars.next_if(|ch| ('?'..='~').contains(ch)) {
+                    if sixel != '?' && !cover(&mut drawn, x, band, count, max_pixels) {
+                        return None;
+                    }
+                    if !draw(&mut rows, x, band, count, sixel, palette[color], paint) {
+                        return None;
+                    }
+                    x = x.saturating_add(count);
+                }
+            }
+            '$' => x = 0,
+            '-' => {
+                x = 0;
+                band += 1;
+            }
+            '?'..='~' => {
+                if ch != '?' && !cover(&mut drawn, x, band, 1, max_pixels) {
+                    return None;
+                }
+                if !draw(&mut rows, x, band, 1, ch, palette[color], paint) {
+                    return None;
+                }
+                x += 1;
+            }
+            // Line breaks and other characters between commands
+            _ => {}
+        }
+    }
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0).max(size.0);
+    let height = rows.len().max(size.1);
+    if width == 0 || height == 0 || width * height > max_pixels {
+        return None;
+    }
+    let background = if transparent { [0; 4] } else { palette[0] };
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        let row = rows.get(y).map_or(&[][..], Vec::as_slice);
+        for x in 0..width {
+            match row.get(x) {
+                Some(&pixel) if pixel[3] != 0 => pixels.extend_from_slice(&pixel),
+                _ => pixels.extend_from_slice(&background),
+            }
+        }
+    }
+    Some(Bitmap {
+        width: width as u32,
+        height: height as u32,
+        pixels,
+    })
+}
+
+/// Grow the drawn area to cover `count` columns from `x` in a six-pixel band.
+///
+/// Returns `false` when the area would hold more than `max_pixels` pixels.
+fn cover(
+    drawn: &mut (usize, usize),
+    x: usize,
+    band: usize,
+    count: usize,
+    max_pixels: usize,
+) -> bool {
+    let width = drawn.0.max(x.saturating_add(count).min(MAX_SIZE));
+    let height = drawn
+        .1
+        .max(band.saturating_mul(6).saturating_add(6).min(MAX_SIZE));
+    *drawn = (width, height);
+    width * height <= max_pixels
+}
+
+/// Draw `count` copies of a sixel character at column `x` of a six-pixel band.
+///
+/// Returns `false` without drawing when there are fewer than the needed pixels left in
+/// `paint`.
+fn draw(
+    rows: &mut Vec<Vec<[u8; 4]>>,
+    x: usize,
+    band: usize,
+    count: usize,
+    sixel: char,
+    color: [u8; 4],
+    paint: &mut usize,
+) -> bool {
+    let bits = sixel as u32 - u32::from(b'?');
+    let end = x.saturating_add(count).min(MAX_SIZE);
+    if bits == 0 || x >= end {
+        return true;
+    }
+    let painted = (end - x) * bits.count_ones() as usize;
+    if painted > *paint {
+        *paint = 0;
+        return false;
+    }
+    *paint -= painted;
+    for bit in 0..6 {
+        let y = band * 6 + bit;
+        if bits & (1 << bit) == 0 || y >= MAX_SIZE {
+            continue;
+        }
+        if rows.len() <= y {
+            rows.resize_with(y + 1, Vec::new);
+        }
+        let row = &mut rows[y];
+        if row.len() < end {
+            row.resize(end, [0; 4]);
+        }
+        row[x..end].fill(color);
+    }
+    true
+}
+
+/// Read `;`-separated decimal numbers
+fn numbers(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Vec<u32> {
+    let mut values = Vec::new();
+    let mut value: Option<u32> = None;
+    while let Some(ch) = chars.next_if(|ch| ch.is_ascii_digit() || *ch == ';') {
+        match ch.to_digit(10) {
+            Some(digit) => {
+                value = Some(value.unwrap_or(0).saturating_mul(10).saturating_add(digit))
+            }
+            None => values.push(value.take().unwrap_or(0)),
+        }
+    }
+    values.extend(value);
+    values
+}
+
+/// Scale a percentage (0-100) to a color channel
+fn percent(value: u32) -> u8 {
+    (value.min(100) * 255 / 100) as u8
+}
+
+/// Convert a Sixel HLS color (hue 0-360 starting at blue, lightness and saturation 0-100)
+/// to RGBA
+fn hls(hue: u32, lightness: u32, saturation: u32) -> [u8; 4] {
+    // Sixel hues put blue at 0 and red at 120 degrees
+    let hue = f64::from((hue % 360 + 240) % 360) / 60.0;
+    let lightness = f64::from(lightness.min(100)) / 100.0;
+    let saturation = f64::from(saturation.min(100)) / 100.0;
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let second = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, second, 0.0),
+        1 => (second, chroma, 0.0),
+        2 => (0.0, chroma, second),
+        3 => (0.0, second, chroma),
+        4 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+    let offset = lightness - chroma / 2.0;
+    let channel = |value: f64| ((value + offset) * 255.0).round() as u8;
+    [channel(r), channel(g), channel(b), 255]
+}
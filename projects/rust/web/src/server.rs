@@ -5,14 +5,18 @@
 //! - HTML templates for the UI
 //! - Server startup logic
 
+use ansi_to_html_rs::{
+    BlinkMode, ConvertOptions, MAX_COLUMNS, Palette, convert_with_options, decode_artworx,
+    decode_binary_text, decode_ice_draw, decode_tundra, decode_xbin, escape_html,
+};
 use axum::{
     Router,
     extract::Multipart,
     response::Html,
     routing::{get, post},
 };
-use ansi_to_html_rs::{convert_with_options, ConvertOptions};
 use std::net::SocketAddr;
+use std::path::Path;
 use tower_http::services::ServeDir;
 
 use crate::config::Config;
@@ -52,7 +56,22 @@ async fn upload_handler(mut multipart: Multipart) -> Html<String> {
     let mut file_name = String::from("upload");
     let mut synchronet_enabled = false;
     let mut renegade_enabled = false;
+    let mut pcboard_enabled = false;
+    let mut wildcat_enabled = false;
+    let mut celerity_enabled = false;
+    let mut mystic_enabled = false;
+    let mut wwiv_enabled = false;
+    let mut avatar_enabled = false;
+    let mut mirc_enabled = false;
+    let mut door_enabled = false;
     let mut utf8_input_enabled = false;
+    let mut screen_buffer_enabled = false;
+    let mut music_enabled = false;
+    let mut ripscrip_enabled = false;
+    let mut sixel_enabled = false;
+    let mut columns: Option<u16> = None;
+    let mut blink_mode = BlinkMode::Auto;
+    let mut palette: Option<Palette> = None;
 
     while let Some(field) = multipart.next_field().await.unwrap_or(None) {
         match field.name() {
@@ -66,9 +85,73 @@ async fn upload_handler(mut multipart: Multipart) -> Html<String> {
             Some("renegade") => {
                 renegade_enabled = true;
             }
+            Some("pcboard") => {
+                pcboard_enabled = true;
+            }
+            Some("wildcat") => {
+                wildcat_enabled = true;
+            }
+            Some("celerity") => {
+                celerity_enabled = true;
+            }
+            Some("mystic") => {
+                mystic_enabled = true;
+            }
+            Some("wwiv") => {
+                wwiv_enabled = true;
+            }
+            Some("avatar") => {
+                avatar_enabled = true;
+            }
+            Some("mirc") => {
+                mirc_enabled = true;
+            }
+            Some("door") => {
+                door_enabled = true;
+            }
             Some("utf8_input") => {
                 utf8_input_enabled = true;
             }
+            Some("screen_buffer") => {
+                screen_buffer_enabled = true;
+            }
+            Some("music") => {
+                music_enabled = true;
+            }
+            Some("ripscrip") => {
+                ripscrip_enabled = true;
+            }
+            Some("sixel") => {
+                sixel_enabled = true;
+            }
+            Some("columns") => {
+                // Empty or invalid values fall back to the SAUCE width / 80 columns
+                let value = field.text().await.unwrap_or_default();
+                columns = value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|&c| c > 0)
+                    .map(|c: u16| c.min(MAX_COLUMNS));
+            }
+            Some("blink_mode") => {
+                blink_mode = match field.text().await.unwrap_or_default().as_str() {
+                    "ice" => BlinkMode::IceColors,
+                    "blink" => BlinkMode::Blink,
+                    "ignore" => BlinkMode::Ignore,
+                    _ => BlinkMode::Auto,
+                };
+            }
+            Some("palette") => {
+                // The stylesheet already uses the VGA palette, so it needs no override
+                palette = match field.text().await.unwrap_or_default().as_str() {
+                    "ega" => Some(Palette::EGA),
+                    "amiga" => Some(Palette::AMIGA_WORKBENCH),
+                    "xterm" => Some(Palette::XTERM),
+                    "modern" => Some(Palette::MODERN),
+                    _ => None,
+                };
+            }
             _ => {}
         }
     }
@@ -76,13 +159,32 @@ async fn upload_handler(mut multipart: Multipart) -> Html<String> {
     let options = ConvertOptions {
         synchronet_ctrl_a: synchronet_enabled,
         renegade_pipe: renegade_enabled,
+        pcboard_at_x: pcboard_enabled,
+        wildcat_at: wildcat_enabled,
+        celerity_pipe: celerity_enabled,
+        mystic_mci: mystic_enabled,
+        wwiv_heart: wwiv_enabled,
+        wwiv_colors: None,
+        avatar: avatar_enabled,
+        mirc_colors: mirc_enabled,
+        door_backtick: door_enabled,
+        ansi_music: music_enabled,
+        music_elements: music_enabled,
+        ripscrip: ripscrip_enabled,
+        sixel_images: sixel_enabled,
         utf8_input: utf8_input_enabled,
+        screen_buffer: screen_buffer_enabled,
+        columns,
+        blink_mode,
+        palette,
+        macros: None,
     };
 
     let content = match file_content {
-        Some(bytes) => convert_with_options(&bytes, &options),
+        Some(bytes) => convert_upload(&file_name, &bytes, &options),
         None => "<p>No file uploaded</p>".to_string(),
     };
+    let title = escape_html(&file_name);
 
     let html = format!(
         r#"<!DOCTYPE html>
@@ -108,12 +210,36 @@ async fn upload_handler(mut multipart: Multipart) -> Html<String> {
     </main>
 </body>
 </html>"#,
-        file_name, file_name, content
+        title, title, content
     );
 
     Html(html)
 }
 
+/// Convert an uploaded file, decoding binary art formats by their file extension
+fn convert_upload(file_name: &str, bytes: &[u8], options: &ConvertOptions) -> String {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    let decoded = match extension.as_deref() {
+        Some("xb") => decode_xbin(bytes),
+        Some("bin") => decode_binary_text(bytes, options.columns),
+        Some("adf") => decode_artworx(bytes),
+        Some("idf") => decode_ice_draw(bytes),
+        Some("tnd") => decode_tundra(bytes),
+        _ => return convert_with_options(bytes, options),
+    };
+    match decoded {
+        Ok(image) => image.to_html(options),
+        Err(err) => format!(
+            "<p>Could not decode {}: {}</p>",
+            escape_html(file_name),
+            err
+        ),
+    }
+}
+
 const INDEX_HTML: &str = r#"<!DOCTYPE html>
 <html lang="en">
 <head>
@@ -131,7 +257,7 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
         <form class="upload-form" action="/upload" method="post" enctype="multipart/form-data">
             <div class="file-input-wrapper">
                 <label for="file">Select File:</label>
-                <input type="file" id="file" name="file" accept=".msg,.ans,.txt">
+                <input type="file" id="file" name="file" accept=".msg,.ans,.txt,.xb,.bin,.adf,.idf,.tnd,.rip">
             </div>
             <fieldset class="options-fieldset">
                 <legend>Input Options</legend>
@@ -139,6 +265,45 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
                     <input type="checkbox" id="utf8_input" name="utf8_input" value="1">
                     <label for="utf8_input">UTF-8 input (skip CP437 conversion, only convert control chars)</label>
                 </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="screen_buffer" name="screen_buffer" value="1">
+                    <label for="screen_buffer">Virtual screen (apply cursor positioning and erase sequences)</label>
+                </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="music" name="music" value="1">
+                    <label for="music">ANSI music player (&#9834; button for each tune)</label>
+                </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="ripscrip" name="ripscrip" value="1">
+                    <label for="ripscrip">RIPscrip graphics (!| lines drawn as SVG)</label>
+                </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="sixel" name="sixel" value="1">
+                    <label for="sixel">Sixel images (ESC P ... q shown as PNG images)</label>
+                </div>
+                <div class="number-wrapper">
+                    <label for="columns">Columns:</label>
+                    <input type="number" id="columns" name="columns" min="1" max="1000" placeholder="auto (SAUCE or 80)">
+                </div>
+                <div class="select-wrapper">
+                    <label for="blink_mode">Blink attribute:</label>
+                    <select id="blink_mode" name="blink_mode">
+                        <option value="auto" selected>Auto (SAUCE iCE flag)</option>
+                        <option value="ice">iCE colors (bright backgrounds)</option>
+                        <option value="blink">Blinking text</option>
+                        <option value="ignore">Ignore</option>
+                    </select>
+                </div>
+                <div class="select-wrapper">
+                    <label for="palette">Palette:</label>
+                    <select id="palette" name="palette">
+                        <option value="vga" selected>VGA</option>
+                        <option value="ega">EGA</option>
+                        <option value="amiga">Amiga Workbench</option>
+                        <option value="xterm">xterm</option>
+                        <option value="modern">Modern (Windows Terminal)</option>
+                    </select>
+                </div>
             </fieldset>
             <fieldset class="options-fieldset">
                 <legend>BBS Color Code Options</legend>
@@ -150,12 +315,62 @@ const INDEX_HTML: &str = r#"<!DOCTYPE html>
                     <input type="checkbox" id="renegade" name="renegade" value="1">
                     <label for="renegade">Renegade pipe codes (|00-|23)</label>
                 </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="pcboard" name="pcboard" value="1">
+                    <label for="pcboard">PCBoard @X codes (@X1F)</label>
+                </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="wildcat" name="wildcat" value="1">
+                    <label for="wildcat">Wildcat! codes (@1F@)</label>
+                </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="celerity" name="celerity" value="1">
+                    <label for="celerity">Celerity pipe codes (|k, |B, |S)</label>
+                </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="mystic" name="mystic" value="1">
+                    <label for="mystic">Mystic pipe and MCI codes (|CL, |CR, |[X##)</label>
+                </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="wwiv" name="wwiv" value="1">
+                    <label for="wwiv">WWIV heart codes (Ctrl-C + 0-9)</label>
+                </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="avatar" name="avatar" value="1">
+                    <label for="avatar">AVATAR/0 codes (Ctrl-V, Ctrl-Y, Ctrl-L)</label>
+                </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="mirc" name="mirc" value="1">
+                    <label for="mirc">mIRC color codes (Ctrl-C fg,bg, Ctrl-B, Ctrl-O)</label>
+                </div>
+                <div class="checkbox-wrapper">
+                    <input type="checkbox" id="door" name="door" value="1">
+                    <label for="door">Door game backtick codes (`1-`%, `r0-`r7)</label>
+                </div>
             </fieldset>
             <button type="submit">Convert &amp; View</button>
         </form>
         <p class="help-text">
-            Supported formats: .msg, .ans (ANSI art files with CP437 encoding)
+            Supported formats: .msg, .ans (ANSI art files with CP437 encoding), .xb (XBin), .bin (BinaryText), .adf (Artworx), .idf (iCE Draw), .tnd (TundraDraw), .rip (RIPscrip, with RIPscrip graphics enabled)
         </p>
     </main>
 </body>
 </html>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_upload_escapes_file_name_on_error() {
+        let result = convert_upload(
+            "<script>alert(1)</script>.xb",
+            b"not xbin",
+            &ConvertOptions::default(),
+        );
+        assert!(
+            result.starts_with("<p>Could not decode &lt;script&gt;alert(1)&lt;/script&gt;.xb: ")
+        );
+        assert!(!result.contains("<script>"));
+    }
+}
@@ -7,16 +7,44 @@
     "#FF5555", "#FF55FF", "#FFFF55", "#FFFFFF"
   ];
 
+  // 256-color table, or null for the standard xterm layout
+  const extendedColors = null;
+
   const hexChars = "0123456789abcdef";
 
+  // CGA colors resolve through --ans-color-N so a <pre> can carry its own palette
+  function cgaColor(index) {
+    return `var(--ans-color-${index}, ${colors[index]})`;
+  }
+
+  // Text attributes: boolean attributes on any ans-* element, e.g. <ans-07 blink underline>
+  if (!document.getElementById("ans-attribute-style")) {
+    const style = document.createElement("style");
+    style.id = "ans-attribute-style";
+    style.textContent = [
+      "pre.ansi [blink] { animation: ans-blink var(--ans-blink-duration, 1s) step-end infinite; }",
+      "@keyframes ans-blink { 50% { color: transparent; } }",
+      "pre.ansi [faint] { opacity: 0.6; }",
+      "pre.ansi [italic] { font-style: italic; }",
+      "pre.ansi [underline] { --ans-underline: underline; }",
+      "pre.ansi [double-underline] { --ans-underline: underline; text-decoration-style: double; }",
+      "pre.ansi [strike] { --ans-strike: line-through; }",
+      "pre.ansi [overline] { --ans-overline: overline; }",
+      "pre.ansi [underline], pre.ansi [double-underline], pre.ansi [strike], pre.ansi [overline] {" +
+        " text-decoration-line: var(--ans-underline,) var(--ans-strike,) var(--ans-overline,); }",
+      "pre.ansi [conceal] { color: transparent !important; }"
+    ].join("\n");
+    document.head.appendChild(style);
+  }
+
   // Standard 16-color CGA web components: <ans-KF>
   for (let bg = 0; bg < 16; bg++) {
     for (let fg = 0; fg < 16; fg++) {
       const tagName = `ans-${hexChars[bg]}${hexChars[fg]}`;
 
-      if (!customElements.get(tagName.toLowerCase())) {
-        const bgColor = colors[bg];
-        const fgColor = colors[fg];
+      if (!customElements.get(tagName)) {
+        const bgColor = cgaColor(bg);
+        const fgColor = cgaColor(fg);
 
         class AnsElement extends HTMLElement {
           constructor() {
@@ -30,7 +58,7 @@
           }
         }
 
-        customElements.define(tagName.toLowerCase(), AnsElement);
+        customElements.define(tagName, AnsElement);
       }
     }
   }
@@ -47,16 +75,16 @@
    * @returns {string} CSS color value
    */
   function parseColorAttribute(value, isForeground) {
+    const fallback = cgaColor(isForeground ? 7 : 0);
     if (!value) {
       // Default: light gray foreground, black background
-      return isForeground ? colors[7] : colors[0];
+      return fallback;
     }
 
     // Check for CGA fallback: "fg-#" or "bg-#"
     const cgaMatch = value.match(/^(fg|bg)-([0-9a-f])$/i);
     if (cgaMatch) {
-      const colorIndex = parseInt(cgaMatch[2], 16);
-      return colors[colorIndex] || (isForeground ? colors[7] : colors[0]);
+      return cgaColor(parseInt(cgaMatch[2], 16));
     }
 
     // Check for RGB: "R,G,B"
@@ -75,18 +103,28 @@
     }
 
     // Fallback to default
-    return isForeground ? colors[7] : colors[0];
+    return fallback;
   }
 
   /**
-   * Convert a 256-color palette index to RGB CSS value.
+   * Convert a 256-color palette index to a CSS color value.
+   * A --ans-256-N property (declared on a <pre> with a custom 256-color table) takes
+   * precedence over the page-wide table.
    * @param {number} index - Palette index (0-255)
-   * @returns {string} CSS rgb() color value
+   * @returns {string} CSS color value
    */
   function palette256ToRgb(index) {
+    return `var(--ans-256-${index}, ${defaultPalette256(index)})`;
+  }
+
+  function defaultPalette256(index) {
+    if (extendedColors) {
+      return extendedColors[index];
+    }
+
     // Colors 0-15: Standard CGA colors
     if (index < 16) {
-      return colors[index];
+      return cgaColor(index);
     }
 
     // Colors 16-231: 6x6x6 color cube
@@ -105,65 +143,75 @@
     return `rgb(${gray},${gray},${gray})`;
   }
 
-  // 256-color web component: <ans-256 fg="N" bg="N">
-  if (!customElements.get("ans-256")) {
-    class Ans256Element extends HTMLElement {
-      constructor() {
-        super();
-      }
+  // Extended color web components: <ans-256 fg="N" bg="N"> and <ans-rgb fg="R,G,B" bg="R,G,B">
+  for (const tagName of ["ans-256", "ans-rgb"]) {
+    if (!customElements.get(tagName)) {
+      class AnsExtendedElement extends HTMLElement {
+        constructor() {
+          super();
+        }
 
-      connectedCallback() {
-        const fg = this.getAttribute("fg");
-        const bg = this.getAttribute("bg");
-        this.style.color = parseColorAttribute(fg, true);
-        this.style.backgroundColor = parseColorAttribute(bg, false);
-        this.style.display = "inline";
-      }
+        connectedCallback() {
+          this.style.color = parseColorAttribute(this.getAttribute("fg"), true);
+          this.style.backgroundColor = parseColorAttribute(this.getAttribute("bg"), false);
+          this.style.display = "inline";
+        }
 
-      static get observedAttributes() {
-        return ["fg", "bg"];
-      }
+        static get observedAttributes() {
+          return ["fg", "bg"];
+        }
 
-      attributeChangedCallback(name, oldValue, newValue) {
-        if (name === "fg") {
-          this.style.color = parseColorAttribute(newValue, true);
-        } else if (name === "bg") {
-          this.style.backgroundColor = parseColorAttribute(newValue, false);
+        attributeChangedCallback(name, oldValue, newValue) {
+          if (name === "fg") {
+            this.style.color = parseColorAttribute(newValue, true);
+          } else if (name === "bg") {
+            this.style.backgroundColor = parseColorAttribute(newValue, false);
+          }
         }
       }
-    }
 
-    customElements.define("ans-256", Ans256Element);
+      customElements.define(tagName, AnsExtendedElement);
+    }
   }
 
-  // RGB color web component: <ans-rgb fg="R,G,B" bg="R,G,B">
-  if (!customElements.get("ans-rgb")) {
-    class AnsRgbElement extends HTMLElement {
+  // ANSI music: <ans-music notes="frequency,duration,sounding ..."> (Hz, ms), played as a
+  // PC speaker square wave when clicked. Positioned out of flow so the art keeps its layout.
+  if (!customElements.get("ans-music")) {
+    let audioContext = null;
+
+    class AnsMusicElement extends HTMLElement {
       constructor() {
         super();
       }
 
       connectedCallback() {
-        const fg = this.getAttribute("fg");
-        const bg = this.getAttribute("bg");
-        this.style.color = parseColorAttribute(fg, true);
-        this.style.backgroundColor = parseColorAttribute(bg, false);
-        this.style.display = "inline";
+        this.style.position = "absolute";
+        this.style.cursor = "pointer";
+        this.title = "Play music";
+        this.textContent = "\u266a";
+        this.addEventListener("click", () => this.play());
       }
 
-      static get observedAttributes() {
-        return ["fg", "bg"];
-      }
-
-      attributeChangedCallback(name, oldValue, newValue) {
-        if (name === "fg") {
-          this.style.color = parseColorAttribute(newValue, true);
-        } else if (name === "bg") {
-          this.style.backgroundColor = parseColorAttribute(newValue, false);
+      play() {
+        audioContext = audioContext || new AudioContext();
+        let time = audioContext.currentTime;
+        for (const note of (this.getAttribute("notes") || "").split(" ")) {
+          const [frequency, duration, sounding] = note.split(",").map(Number);
+          if (frequency > 0 && sounding > 0) {
+            const oscillator = audioContext.createOscillator();
+            const gain = audioContext.createGain();
+            oscillator.type = "square";
+            oscillator.frequency.value = frequency;
+            gain.gain.value = 0.1;
+            oscillator.connect(gain).connect(audioContext.destination);
+            oscillator.start(time);
+            oscillator.stop(time + sounding / 1000);
+          }
+          time += (duration || 0) / 1000;
         }
       }
     }
 
-    customElements.define("ans-rgb", AnsRgbElement);
+    customElements.define("ans-music", AnsMusicElement);
   }
 })();